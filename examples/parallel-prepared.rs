@@ -1,9 +1,7 @@
 use anyhow::Result;
+use futures::stream::StreamExt;
 use scylla::{Session, SessionBuilder};
 use std::env;
-use std::sync::Arc;
-
-use tokio::sync::Semaphore;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -12,7 +10,6 @@ async fn main() -> Result<()> {
     println!("Connecting to {} ...", uri);
 
     let session: Session = SessionBuilder::new().known_node(uri).build().await?;
-    let session = Arc::new(session);
 
     session.query("CREATE KEYSPACE IF NOT EXISTS ks WITH REPLICATION = {'class' : 'SimpleStrategy', 'replication_factor' : 1}", &[]).await?;
 
@@ -23,37 +20,25 @@ async fn main() -> Result<()> {
         )
         .await?;
 
-    let prepared = Arc::new(
-        session
-            .prepare("INSERT INTO ks.t2 (a, b, c) VALUES (?, ?, 'abc')")
-            .await?,
-    );
+    let prepared = session
+        .prepare("INSERT INTO ks.t2 (a, b, c) VALUES (?, ?, 'abc')")
+        .await?;
     println!("Prepared statement: {:#?}", prepared);
 
     let parallelism = 256;
-    let sem = Arc::new(Semaphore::new(parallelism));
+    let values = (0..100_000usize).map(|i| (i as i32, 2 * i as i32));
+
+    // Results are returned in the order of values, at most `parallelism` requests are in flight
+    let mut results = session
+        .execute_many(&prepared, values, parallelism)
+        .stop_on_error()
+        .enumerate();
 
-    for i in 0..100_000usize {
+    while let Some((i, result)) = results.next().await {
+        result?;
         if i % 1000 == 0 {
             println!("{}", i);
         }
-        let session = session.clone();
-        let prepared = prepared.clone();
-        let permit = sem.clone().acquire_owned().await;
-        tokio::task::spawn(async move {
-            let i = i;
-            session
-                .execute(&prepared, (i as i32, 2 * i as i32))
-                .await
-                .unwrap();
-
-            let _permit = permit;
-        });
-    }
-
-    // Wait for all in-flight requests to finish
-    for _ in 0..parallelism {
-        sem.acquire().await.unwrap().forget();
     }
 
     println!("Ok.");
@@ -3,7 +3,7 @@
 use crate::frame::frame_errors::{FrameError, ParseError};
 use crate::frame::protocol_features::ProtocolFeatures;
 use crate::frame::types::LegacyConsistency;
-use crate::frame::value::SerializeValuesError;
+use crate::frame::value::{SerializeValuesError, TypeCheckError};
 use bytes::Bytes;
 use std::io::ErrorKind;
 use std::sync::Arc;
@@ -339,6 +339,10 @@ pub enum BadQuery {
     #[error("Passed invalid keyspace name to use: {0}")]
     BadKeyspaceName(#[from] BadKeyspaceName),
 
+    /// Rust types don't match bound values or result columns of a typed prepared statement
+    #[error("Types don't match the statement: {0}")]
+    TypeCheckError(#[from] TypeCheckError),
+
     /// Other reasons of bad query
     #[error("{0}")]
     Other(String),
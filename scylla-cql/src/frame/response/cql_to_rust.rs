@@ -1,10 +1,11 @@
-use super::result::{CqlValue, Row};
-use crate::frame::value::Counter;
+use super::result::{ColumnSpec, ColumnType, CqlValue, Row};
+use crate::frame::value::{Counter, TypeCheckError};
 use bigdecimal::BigDecimal;
 use chrono::{Duration, NaiveDate};
 use num_bigint::BigInt;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::hash::Hash;
+use std::iter::Peekable;
 use std::net::IpAddr;
 use thiserror::Error;
 use uuid::Uuid;
@@ -15,6 +16,10 @@ pub enum FromRowError {
     BadCqlVal { err: FromCqlValError, column: usize },
     #[error("Wrong row size: expected {expected}, actual {actual}")]
     WrongRowSize { expected: usize, actual: usize },
+    #[error("Column {column} is missing in the row")]
+    MissingColumn { column: String },
+    #[error("Matching columns by name requires column specifications, which were not provided")]
+    ColumnSpecsRequired,
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -28,6 +33,27 @@ pub enum CqlTypeError {
 // is forbidden since neither From nor String are defined in this crate
 pub trait FromCqlVal<T>: Sized {
     fn from_cql(cql_val: T) -> Result<Self, FromCqlValError>;
+
+    /// Checks whether values of the given CQL type can be converted to this type.\
+    /// By default every type is assumed to be compatible.
+    fn is_compatible_with(_typ: &ColumnType) -> bool {
+        true
+    }
+}
+
+/// Reads consecutive fields of a User Defined Type value into a struct.\
+/// Implemented by `#[derive(FromUserType)]`, it allows to nest the struct
+/// in other user types with `#[scylla(flatten)]`.
+pub trait FromUserTypeFields: Sized {
+    /// Reads the fields of the struct, leaving the remaining ones in `fields`
+    fn from_udt_fields(
+        fields: &mut Peekable<std::vec::IntoIter<(String, Option<CqlValue>)>>,
+    ) -> Result<Self, FromCqlValError>;
+
+    /// Checks the types of the fields of the struct, consuming them from `field_types`
+    fn udt_fields_compatible_with(
+        field_types: &mut Peekable<std::slice::Iter<'_, (String, ColumnType)>>,
+    ) -> bool;
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
@@ -36,11 +62,71 @@ pub enum FromCqlValError {
     BadCqlType,
     #[error("Value is null")]
     ValIsNull,
+    #[error("Value {value} doesn't match any variant of enum {enum_name}")]
+    UnknownEnumValue {
+        enum_name: &'static str,
+        value: String,
+    },
 }
 
 /// This trait defines a way to convert CQL Row into some rust type
 pub trait FromRow: Sized {
     fn from_row(row: Row) -> Result<Self, FromRowError>;
+
+    /// Converts the row using specifications of its columns, which allows
+    /// to match the columns by their names.\
+    /// By default the specifications are ignored and columns are matched by position.
+    fn from_row_with_specs(row: Row, _col_specs: &[ColumnSpec]) -> Result<Self, FromRowError> {
+        Self::from_row(row)
+    }
+
+    /// Checks whether rows with the given column specifications can be converted to this type.\
+    /// By default every row is assumed to be compatible.
+    fn type_check(_col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        Ok(())
+    }
+}
+
+/// Checks whether values of the given column can be converted to type `T`
+pub fn check_column_type<T: FromCqlVal<Option<CqlValue>>>(
+    col_spec: &ColumnSpec,
+) -> Result<(), TypeCheckError> {
+    if !T::is_compatible_with(&col_spec.typ) {
+        return Err(TypeCheckError::IncompatibleType {
+            name: col_spec.name.clone(),
+            rust_type: std::any::type_name::<T>(),
+            cql_type: col_spec.typ.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks whether the number of columns returned by a statement matches the size of a row type
+pub fn check_row_size(col_specs: &[ColumnSpec], size: usize) -> Result<(), TypeCheckError> {
+    if col_specs.len() != size {
+        return Err(TypeCheckError::WrongRowSize {
+            expected: size,
+            actual: col_specs.len(),
+        });
+    }
+    Ok(())
+}
+
+// Unit type represents rows without columns, e.g. results of statements which don't return rows
+impl FromRow for () {
+    fn from_row(row: Row) -> Result<Self, FromRowError> {
+        if !row.columns.is_empty() {
+            return Err(FromRowError::WrongRowSize {
+                expected: 0,
+                actual: row.columns.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn type_check(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        check_row_size(col_specs, 0)
+    }
 }
 
 // CqlValue can be converted to CqlValue
@@ -56,6 +142,10 @@ impl<T: FromCqlVal<CqlValue>> FromCqlVal<Option<CqlValue>> for T {
     fn from_cql(cql_val_opt: Option<CqlValue>) -> Result<Self, FromCqlValError> {
         T::from_cql(cql_val_opt.ok_or(FromCqlValError::ValIsNull)?)
     }
+
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        <T as FromCqlVal<CqlValue>>::is_compatible_with(typ)
+    }
 }
 
 // Implement from_cql<Option<CqlValue>> for Option<T> for every type that has from_cql<CqlValue>
@@ -68,34 +158,47 @@ impl<T: FromCqlVal<CqlValue>> FromCqlVal<Option<CqlValue>> for Option<T> {
             None => Ok(None),
         }
     }
+
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        <T as FromCqlVal<CqlValue>>::is_compatible_with(typ)
+    }
 }
-// This macro implements FromCqlVal given a type and method of CqlValue that returns this type
+// This macro implements FromCqlVal given a type, method of CqlValue that returns this type
+// and a pattern matching CQL types which the method accepts
 macro_rules! impl_from_cql_val {
-    ($T:ty, $convert_func:ident) => {
+    ($T:ty, $convert_func:ident, $compatible:pat) => {
         impl FromCqlVal<CqlValue> for $T {
             fn from_cql(cql_val: CqlValue) -> Result<$T, FromCqlValError> {
                 cql_val.$convert_func().ok_or(FromCqlValError::BadCqlType)
             }
+
+            fn is_compatible_with(typ: &ColumnType) -> bool {
+                matches!(typ, $compatible)
+            }
         }
     };
 }
 
-impl_from_cql_val!(i32, as_int); // i32::from_cql<CqlValue>
-impl_from_cql_val!(i64, as_bigint); // i64::from_cql<CqlValue>
-impl_from_cql_val!(Counter, as_counter); // Counter::from_cql<CqlValue>
-impl_from_cql_val!(i16, as_smallint); // i16::from_cql<CqlValue>
-impl_from_cql_val!(BigInt, into_varint); // BigInt::from_cql<CqlValue>
-impl_from_cql_val!(i8, as_tinyint); // i8::from_cql<CqlValue>
-impl_from_cql_val!(NaiveDate, as_date); // NaiveDate::from_cql<CqlValue>
-impl_from_cql_val!(f32, as_float); // f32::from_cql<CqlValue>
-impl_from_cql_val!(f64, as_double); // f64::from_cql<CqlValue>
-impl_from_cql_val!(bool, as_boolean); // bool::from_cql<CqlValue>
-impl_from_cql_val!(String, into_string); // String::from_cql<CqlValue>
-impl_from_cql_val!(Vec<u8>, into_blob); // Vec<u8>::from_cql<CqlValue>
-impl_from_cql_val!(IpAddr, as_inet); // IpAddr::from_cql<CqlValue>
-impl_from_cql_val!(Uuid, as_uuid); // Uuid::from_cql<CqlValue>
-impl_from_cql_val!(BigDecimal, into_decimal); // BigDecimal::from_cql<CqlValue>
-impl_from_cql_val!(Duration, as_duration); // Duration::from_cql<CqlValue>
+impl_from_cql_val!(i32, as_int, ColumnType::Int); // i32::from_cql<CqlValue>
+impl_from_cql_val!(i64, as_bigint, ColumnType::BigInt | ColumnType::Timestamp); // i64::from_cql<CqlValue>
+impl_from_cql_val!(Counter, as_counter, ColumnType::Counter); // Counter::from_cql<CqlValue>
+impl_from_cql_val!(i16, as_smallint, ColumnType::SmallInt); // i16::from_cql<CqlValue>
+impl_from_cql_val!(BigInt, into_varint, ColumnType::Varint); // BigInt::from_cql<CqlValue>
+impl_from_cql_val!(i8, as_tinyint, ColumnType::TinyInt); // i8::from_cql<CqlValue>
+impl_from_cql_val!(NaiveDate, as_date, ColumnType::Date); // NaiveDate::from_cql<CqlValue>
+impl_from_cql_val!(f32, as_float, ColumnType::Float); // f32::from_cql<CqlValue>
+impl_from_cql_val!(f64, as_double, ColumnType::Double); // f64::from_cql<CqlValue>
+impl_from_cql_val!(bool, as_boolean, ColumnType::Boolean); // bool::from_cql<CqlValue>
+impl_from_cql_val!(String, into_string, ColumnType::Ascii | ColumnType::Text); // String::from_cql<CqlValue>
+impl_from_cql_val!(Vec<u8>, into_blob, ColumnType::Blob); // Vec<u8>::from_cql<CqlValue>
+impl_from_cql_val!(IpAddr, as_inet, ColumnType::Inet); // IpAddr::from_cql<CqlValue>
+impl_from_cql_val!(Uuid, as_uuid, ColumnType::Uuid | ColumnType::Timeuuid); // Uuid::from_cql<CqlValue>
+impl_from_cql_val!(BigDecimal, into_decimal, ColumnType::Decimal); // BigDecimal::from_cql<CqlValue>
+impl_from_cql_val!(
+    Duration,
+    as_duration,
+    ColumnType::Timestamp | ColumnType::Time
+); // Duration::from_cql<CqlValue>
 
 impl FromCqlVal<CqlValue> for crate::frame::value::Time {
     fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
@@ -104,6 +207,10 @@ impl FromCqlVal<CqlValue> for crate::frame::value::Time {
             _ => Err(FromCqlValError::BadCqlType),
         }
     }
+
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Time)
+    }
 }
 
 impl FromCqlVal<CqlValue> for crate::frame::value::Timestamp {
@@ -113,6 +220,10 @@ impl FromCqlVal<CqlValue> for crate::frame::value::Timestamp {
             _ => Err(FromCqlValError::BadCqlType),
         }
     }
+
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Timestamp)
+    }
 }
 
 // Vec<T>::from_cql<CqlValue>
@@ -125,6 +236,15 @@ impl<T: FromCqlVal<CqlValue>> FromCqlVal<CqlValue> for Vec<T> {
             .map(T::from_cql)
             .collect::<Result<Vec<T>, FromCqlValError>>()
     }
+
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => {
+                <T as FromCqlVal<CqlValue>>::is_compatible_with(elem_type)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl<T1: FromCqlVal<CqlValue> + Eq + Hash, T2: FromCqlVal<CqlValue>> FromCqlVal<CqlValue>
@@ -138,6 +258,16 @@ impl<T1: FromCqlVal<CqlValue> + Eq + Hash, T2: FromCqlVal<CqlValue>> FromCqlVal<
         }
         Ok(res)
     }
+
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::Map(key_type, value_type) => {
+                <T1 as FromCqlVal<CqlValue>>::is_compatible_with(key_type)
+                    && <T2 as FromCqlVal<CqlValue>>::is_compatible_with(value_type)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl<T: FromCqlVal<CqlValue> + Eq + Hash> FromCqlVal<CqlValue> for HashSet<T> {
@@ -149,6 +279,15 @@ impl<T: FromCqlVal<CqlValue> + Eq + Hash> FromCqlVal<CqlValue> for HashSet<T> {
             .map(T::from_cql)
             .collect::<Result<HashSet<T>, FromCqlValError>>()
     }
+
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => {
+                <T as FromCqlVal<CqlValue>>::is_compatible_with(elem_type)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl<T: FromCqlVal<CqlValue> + Ord> FromCqlVal<CqlValue> for BTreeSet<T> {
@@ -160,6 +299,15 @@ impl<T: FromCqlVal<CqlValue> + Ord> FromCqlVal<CqlValue> for BTreeSet<T> {
             .map(T::from_cql)
             .collect::<Result<BTreeSet<T>, FromCqlValError>>()
     }
+
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => {
+                <T as FromCqlVal<CqlValue>>::is_compatible_with(elem_type)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl<K: FromCqlVal<CqlValue> + Ord, V: FromCqlVal<CqlValue>> FromCqlVal<CqlValue>
@@ -173,6 +321,16 @@ impl<K: FromCqlVal<CqlValue> + Ord, V: FromCqlVal<CqlValue>> FromCqlVal<CqlValue
         }
         Ok(res)
     }
+
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::Map(key_type, value_type) => {
+                <K as FromCqlVal<CqlValue>>::is_compatible_with(key_type)
+                    && <V as FromCqlVal<CqlValue>>::is_compatible_with(value_type)
+            }
+            _ => false,
+        }
+    }
 }
 
 macro_rules! replace_expr {
@@ -220,6 +378,18 @@ macro_rules! impl_tuple_from_row {
                     ,)+
                 ))
             }
+
+            fn type_check(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+                let expected_len = <[()]>::len(&[$(replace_expr!(($Ti) ())),*]);
+                check_row_size(col_specs, expected_len)?;
+
+                let mut col_specs_iter = col_specs.iter();
+                $(
+                    // The number of columns is checked above, so it is safe to unwrap
+                    check_column_type::<$Ti>(col_specs_iter.next().unwrap())?;
+                )+
+                Ok(())
+            }
         }
     }
 }
@@ -262,6 +432,21 @@ macro_rules! impl_tuple_from_cql {
                     ,)+
                 ))
             }
+
+            fn is_compatible_with(typ: &ColumnType) -> bool {
+                match typ {
+                    ColumnType::Tuple(elem_types) => {
+                        let mut elem_types_iter = elem_types.iter();
+                        let compatible = true $(
+                            && elem_types_iter
+                                .next()
+                                .map_or(false, <$Ti as FromCqlVal<Option<CqlValue>>>::is_compatible_with)
+                        )+;
+                        compatible && elem_types_iter.next().is_none()
+                    }
+                    _ => false,
+                }
+            }
         }
     }
 }
@@ -287,6 +472,7 @@ impl_tuple_from_cql!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14
 mod tests {
     use super::{CqlValue, FromCqlVal, FromCqlValError, FromRow, FromRowError, Row};
     use crate as scylla;
+    use crate::frame::response::result::{ColumnSpec, ColumnType, TableSpec};
     use crate::frame::value::Counter;
     use crate::macros::FromRow;
     use bigdecimal::BigDecimal;
@@ -645,4 +831,499 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn struct_from_row_with_field_attributes() {
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate")]
+        struct MyRow {
+            a: i32,
+            #[scylla(skip)]
+            not_a_column: Vec<i32>,
+            #[scylla(default)]
+            b: String,
+            #[scylla(default)]
+            c: i32,
+        }
+
+        let row = Row {
+            columns: vec![Some(CqlValue::Int(16)), None, Some(CqlValue::Int(32))],
+        };
+        assert_eq!(
+            MyRow::from_row(row),
+            Ok(MyRow {
+                a: 16,
+                not_a_column: vec![],
+                b: String::new(),
+                c: 32,
+            })
+        );
+
+        // Trailing columns with default values can be missing
+        let short_row = Row {
+            columns: vec![Some(CqlValue::Int(16))],
+        };
+        assert_eq!(
+            MyRow::from_row(short_row),
+            Ok(MyRow {
+                a: 16,
+                not_a_column: vec![],
+                b: String::new(),
+                c: 0,
+            })
+        );
+
+        let empty_row = Row { columns: vec![] };
+        assert_eq!(
+            MyRow::from_row(empty_row),
+            Err(FromRowError::WrongRowSize {
+                expected: 3,
+                actual: 0
+            })
+        );
+    }
+
+    #[test]
+    fn udt_with_field_attributes() {
+        use crate::macros::FromUserType;
+
+        #[derive(FromUserType, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate")]
+        struct MyUdt {
+            #[scylla(rename = "first")]
+            a: i32,
+            #[scylla(skip)]
+            not_a_field: Option<i32>,
+            #[scylla(default)]
+            second: String,
+            #[scylla(default)]
+            third: i32,
+        }
+
+        let udt = CqlValue::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "my_udt".to_string(),
+            fields: vec![
+                ("first".to_string(), Some(CqlValue::Int(1))),
+                ("second".to_string(), None),
+            ],
+        };
+
+        assert_eq!(
+            MyUdt::from_cql(udt),
+            Ok(MyUdt {
+                a: 1,
+                not_a_field: None,
+                second: String::new(),
+                third: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn udt_with_flattened_struct() {
+        use crate::macros::FromUserType;
+
+        #[derive(FromUserType, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate")]
+        struct Coordinates {
+            x: i32,
+            #[scylla(default)]
+            y: i32,
+        }
+
+        #[derive(FromUserType, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate")]
+        struct Point {
+            name: String,
+            #[scylla(flatten)]
+            coordinates: Coordinates,
+            z: i32,
+        }
+
+        let field_types = vec![
+            ("name".to_string(), ColumnType::Text),
+            ("x".to_string(), ColumnType::Int),
+            ("y".to_string(), ColumnType::Int),
+            ("z".to_string(), ColumnType::Int),
+        ];
+        let typ = ColumnType::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "point".to_string(),
+            field_types: field_types.clone(),
+        };
+        assert!(<Point as FromCqlVal<CqlValue>>::is_compatible_with(&typ));
+
+        let swapped = ColumnType::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "point".to_string(),
+            field_types: vec![
+                field_types[0].clone(),
+                field_types[3].clone(),
+                field_types[1].clone(),
+                field_types[2].clone(),
+            ],
+        };
+        assert!(!<Point as FromCqlVal<CqlValue>>::is_compatible_with(
+            &swapped
+        ));
+
+        let udt = CqlValue::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "point".to_string(),
+            fields: vec![
+                ("name".to_string(), Some(CqlValue::Text("p".to_string()))),
+                ("x".to_string(), Some(CqlValue::Int(1))),
+                ("z".to_string(), Some(CqlValue::Int(3))),
+            ],
+        };
+        assert_eq!(
+            Point::from_cql(udt),
+            Ok(Point {
+                name: "p".to_string(),
+                coordinates: Coordinates { x: 1, y: 0 },
+                z: 3,
+            })
+        );
+    }
+
+    fn int_col_spec(name: &str) -> ColumnSpec {
+        ColumnSpec {
+            table_spec: TableSpec {
+                ks_name: "ks".to_string(),
+                table_name: "tab".to_string(),
+            },
+            name: name.to_string(),
+            typ: ColumnType::Int,
+        }
+    }
+
+    #[test]
+    fn struct_from_row_by_name() {
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate", match_by_name)]
+        struct MyRow {
+            a: i32,
+            #[scylla(rename = "c")]
+            renamed: i32,
+            #[scylla(default)]
+            d: i32,
+            #[scylla(skip)]
+            not_a_column: i32,
+        }
+
+        let col_specs = vec![int_col_spec("c"), int_col_spec("extra"), int_col_spec("a")];
+        let row = Row {
+            columns: vec![
+                Some(CqlValue::Int(3)),
+                Some(CqlValue::Int(100)),
+                Some(CqlValue::Int(1)),
+            ],
+        };
+        assert_eq!(
+            MyRow::from_row_with_specs(row, &col_specs),
+            Ok(MyRow {
+                a: 1,
+                renamed: 3,
+                d: 0,
+                not_a_column: 0,
+            })
+        );
+
+        let row = Row {
+            columns: vec![Some(CqlValue::Int(3)), None, None],
+        };
+        assert_eq!(
+            MyRow::from_row_with_specs(row, &col_specs),
+            Err(FromRowError::BadCqlVal {
+                err: FromCqlValError::ValIsNull,
+                column: 2,
+            })
+        );
+
+        let row = Row {
+            columns: vec![Some(CqlValue::Int(1))],
+        };
+        assert_eq!(
+            MyRow::from_row_with_specs(row, &[int_col_spec("a")]),
+            Err(FromRowError::MissingColumn {
+                column: "c".to_string()
+            })
+        );
+
+        let row = Row {
+            columns: vec![Some(CqlValue::Int(1)), Some(CqlValue::Int(3))],
+        };
+        assert_eq!(MyRow::from_row(row), Err(FromRowError::ColumnSpecsRequired));
+    }
+
+    #[test]
+    fn cql_enum_from_cql() {
+        use crate::macros::CqlEnum;
+
+        #[derive(CqlEnum, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate", repr = "text")]
+        enum Color {
+            Red,
+            #[scylla(rename = "GREEN")]
+            Green,
+        }
+
+        #[derive(CqlEnum, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate", repr = "text")]
+        enum Shape {
+            Circle,
+            Unknown(String),
+        }
+
+        #[derive(CqlEnum, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate", repr = "int")]
+        enum Level {
+            Low,
+            High = 10,
+            Higher,
+        }
+
+        assert_eq!(
+            Color::from_cql(CqlValue::Text("Red".to_string())),
+            Ok(Color::Red)
+        );
+        assert_eq!(
+            Color::from_cql(CqlValue::Text("GREEN".to_string())),
+            Ok(Color::Green)
+        );
+        assert_eq!(
+            Color::from_cql(CqlValue::Text("Blue".to_string())),
+            Err(FromCqlValError::UnknownEnumValue {
+                enum_name: "Color",
+                value: "Blue".to_string()
+            })
+        );
+        assert_eq!(
+            Color::from_cql(CqlValue::Int(1)),
+            Err(FromCqlValError::BadCqlType)
+        );
+
+        assert_eq!(
+            Shape::from_cql(CqlValue::Text("Square".to_string())),
+            Ok(Shape::Unknown("Square".to_string()))
+        );
+
+        assert_eq!(Level::from_cql(CqlValue::Int(0)), Ok(Level::Low));
+        assert_eq!(Level::from_cql(CqlValue::Int(11)), Ok(Level::Higher));
+        assert_eq!(
+            Level::from_cql(CqlValue::Int(1)),
+            Err(FromCqlValError::UnknownEnumValue {
+                enum_name: "Level",
+                value: "1".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn generic_struct_from_row() {
+        #[derive(FromRow, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate")]
+        struct MyRow<T> {
+            a: T,
+            b: Option<T>,
+        }
+
+        let row = Row {
+            columns: vec![Some(CqlValue::Int(1)), None],
+        };
+
+        assert_eq!(MyRow::<i32>::from_row(row), Ok(MyRow { a: 1, b: None }));
+    }
+
+    #[test]
+    fn generic_udt_from_cql() {
+        use crate::macros::FromUserType;
+
+        #[derive(FromUserType, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate")]
+        struct Pair<K, V>
+        where
+            K: Eq,
+        {
+            key: K,
+            value: V,
+        }
+
+        let udt = CqlValue::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "pair".to_string(),
+            fields: vec![
+                ("key".to_string(), Some(CqlValue::Int(1))),
+                ("value".to_string(), Some(CqlValue::Text("one".to_string()))),
+            ],
+        };
+
+        assert_eq!(
+            Pair::<i32, String>::from_cql(udt),
+            Ok(Pair {
+                key: 1,
+                value: "one".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn cql_newtype_from_cql() {
+        use crate::macros::CqlNewtype;
+
+        #[derive(CqlNewtype, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate")]
+        struct UserId(Uuid);
+
+        #[derive(CqlNewtype, PartialEq, Eq, Debug)]
+        #[scylla(crate = "crate")]
+        struct Wrapper<T> {
+            inner: T,
+        }
+
+        let uuid = Uuid::from_str("8e14e760-7fa8-11eb-bc66-000000000001").unwrap();
+        assert_eq!(UserId::from_cql(CqlValue::Uuid(uuid)), Ok(UserId(uuid)));
+        assert_eq!(
+            UserId::from_cql(CqlValue::Int(1)),
+            Err(FromCqlValError::BadCqlType)
+        );
+        assert_eq!(
+            Wrapper::<i32>::from_cql(CqlValue::Int(1)),
+            Ok(Wrapper { inner: 1 })
+        );
+
+        // Newtypes can be used as fields of rows
+        let row = Row {
+            columns: vec![Some(CqlValue::Uuid(uuid))],
+        };
+        assert_eq!(<(UserId,)>::from_row(row), Ok((UserId(uuid),)));
+    }
+
+    fn col_spec(name: &str, typ: ColumnType) -> ColumnSpec {
+        ColumnSpec {
+            table_spec: TableSpec {
+                ks_name: "ks".to_string(),
+                table_name: "tab".to_string(),
+            },
+            name: name.to_string(),
+            typ,
+        }
+    }
+
+    #[test]
+    fn tuple_type_check() {
+        use crate::frame::value::TypeCheckError;
+
+        let specs = vec![
+            col_spec("a", ColumnType::Int),
+            col_spec("b", ColumnType::List(Box::new(ColumnType::Text))),
+            col_spec(
+                "c",
+                ColumnType::Tuple(vec![ColumnType::Uuid, ColumnType::Timeuuid]),
+            ),
+        ];
+
+        assert_eq!(
+            <(i32, Vec<String>, (Uuid, Option<Uuid>))>::type_check(&specs),
+            Ok(())
+        );
+        assert_eq!(
+            <(i32, Vec<String>)>::type_check(&specs),
+            Err(TypeCheckError::WrongRowSize {
+                expected: 2,
+                actual: 3
+            })
+        );
+        assert!(matches!(
+            <(i32, Vec<i32>, (Uuid, Uuid))>::type_check(&specs),
+            Err(TypeCheckError::IncompatibleType { name, .. }) if name == "b"
+        ));
+        assert!(matches!(
+            <(i32, Vec<String>, (Uuid,))>::type_check(&specs),
+            Err(TypeCheckError::IncompatibleType { name, .. }) if name == "c"
+        ));
+        assert_eq!(<()>::type_check(&[]), Ok(()));
+    }
+
+    #[test]
+    fn derived_type_check() {
+        use crate::frame::value::TypeCheckError;
+        use crate::macros::FromUserType;
+
+        #[derive(FromUserType)]
+        #[scylla(crate = "crate")]
+        struct MyUdt {
+            x: i32,
+            #[scylla(default)]
+            y: String,
+        }
+
+        #[derive(FromRow)]
+        #[scylla(crate = "crate")]
+        struct ByPosition {
+            _a: i32,
+            _b: MyUdt,
+            #[scylla(default)]
+            _c: i64,
+        }
+
+        #[derive(FromRow)]
+        #[scylla(crate = "crate", match_by_name)]
+        struct ByName {
+            _b: MyUdt,
+            #[scylla(rename = "a")]
+            _first: Option<i32>,
+        }
+
+        let udt_type = |fields: &[(&str, ColumnType)]| ColumnType::UserDefinedType {
+            type_name: "my_udt".to_string(),
+            keyspace: "ks".to_string(),
+            field_types: fields
+                .iter()
+                .map(|(name, typ)| (name.to_string(), typ.clone()))
+                .collect(),
+        };
+        let udt_spec = col_spec(
+            "_b",
+            udt_type(&[("x", ColumnType::Int), ("y", ColumnType::Text)]),
+        );
+        let specs = vec![col_spec("a", ColumnType::Int), udt_spec.clone()];
+
+        assert_eq!(ByPosition::type_check(&specs), Ok(()));
+        assert_eq!(ByName::type_check(&specs), Ok(()));
+        assert_eq!(
+            ByPosition::type_check(&specs[..1]),
+            Err(TypeCheckError::WrongRowSize {
+                expected: 3,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            ByName::type_check(&specs[1..]),
+            Err(TypeCheckError::MissingColumn {
+                column: "a".to_string()
+            })
+        );
+
+        // Missing field with #[scylla(default)]
+        assert!(<MyUdt as FromCqlVal<CqlValue>>::is_compatible_with(
+            &udt_type(&[("x", ColumnType::Int)])
+        ));
+        // Missing required field
+        assert!(!<MyUdt as FromCqlVal<CqlValue>>::is_compatible_with(
+            &udt_type(&[("y", ColumnType::Text)])
+        ));
+        // Field which doesn't exist in the struct
+        assert!(!<MyUdt as FromCqlVal<CqlValue>>::is_compatible_with(
+            &udt_type(&[
+                ("x", ColumnType::Int),
+                ("y", ColumnType::Text),
+                ("z", ColumnType::Int)
+            ])
+        ));
+        assert!(!<MyUdt as FromCqlVal<CqlValue>>::is_compatible_with(
+            &udt_type(&[("x", ColumnType::BigInt)])
+        ));
+    }
 }
@@ -34,13 +34,13 @@ pub struct SchemaChange {
     pub event: SchemaChangeEvent,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TableSpec {
     pub ks_name: String,
     pub table_name: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ColumnType {
     Custom(String),
     Ascii,
@@ -102,7 +102,8 @@ pub enum CqlValue {
         type_name: String,
         /// Order of `fields` vector must match the order of fields as defined in the UDT. The
         /// driver does not check it by itself, so incorrect data will be written if the order is
-        /// wrong.
+        /// wrong. To match the fields with the definition by name, wrap the value in
+        /// [UdtByName](crate::frame::value::UdtByName).
         fields: Vec<(String, Option<CqlValue>)>,
     },
     SmallInt(i16),
@@ -327,14 +328,14 @@ impl CqlValue {
     // TODO
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ColumnSpec {
     pub table_spec: TableSpec,
     pub name: String,
     pub typ: ColumnType,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ResultMetadata {
     col_count: usize,
     pub paging_state: Option<Bytes>,
@@ -350,7 +351,9 @@ pub struct PartitionKeyIndex {
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct PreparedMetadata {
+    pub flags: i32,
     pub col_count: usize,
     /// pk_indexes are sorted by `index` and can be reordered in partition key order
     /// using `sequence` field
@@ -358,6 +361,28 @@ pub struct PreparedMetadata {
     pub col_specs: Vec<ColumnSpec>,
 }
 
+impl PreparedMetadata {
+    /// Creates metadata of bound values with no flags set
+    pub fn new(
+        col_count: usize,
+        pk_indexes: Vec<PartitionKeyIndex>,
+        col_specs: Vec<ColumnSpec>,
+    ) -> Self {
+        PreparedMetadata {
+            flags: 0,
+            col_count,
+            pk_indexes,
+            col_specs,
+        }
+    }
+
+    /// Sets the flags received from the database, e.g. the LWT mark
+    pub fn with_flags(mut self, flags: i32) -> Self {
+        self.flags = flags;
+        self
+    }
+}
+
 #[derive(Debug, Default, PartialEq)]
 pub struct Row {
     pub columns: Vec<Option<CqlValue>>,
@@ -368,6 +393,15 @@ impl Row {
     pub fn into_typed<RowT: FromRow>(self) -> StdResult<RowT, FromRowError> {
         RowT::from_row(self)
     }
+
+    /// Same as [into_typed](Row::into_typed), but passes specifications of the columns
+    /// to the conversion, which is required by structs matching columns by name
+    pub fn into_typed_with_specs<RowT: FromRow>(
+        self,
+        col_specs: &[ColumnSpec],
+    ) -> StdResult<RowT, FromRowError> {
+        RowT::from_row_with_specs(self, col_specs)
+    }
 }
 
 #[derive(Debug)]
@@ -550,6 +584,7 @@ fn deser_prepared_metadata(buf: &mut &[u8]) -> StdResult<PreparedMetadata, Parse
     let col_specs = deser_col_specs(buf, &global_table_spec, col_count)?;
 
     Ok(PreparedMetadata {
+        flags,
         col_count,
         pk_indexes,
         col_specs,
@@ -11,13 +11,22 @@ use std::net::IpAddr;
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::response::result::CqlValue;
+use super::response::result::{ColumnSpec, ColumnType, CqlValue};
 use super::types::vint_encode;
 
 /// Every value being sent in a query must implement this trait
 /// serialize() should write the Value as [bytes] to the provided buffer
 pub trait Value {
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig>;
+
+    /// Checks whether values of this type can be sent as values of the given CQL type.\
+    /// By default every type is assumed to be compatible.
+    fn is_compatible_with(_typ: &ColumnType) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
 }
 
 #[derive(Debug, Error, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -76,6 +85,10 @@ pub enum SerializeValuesError {
     TooManyValues,
     #[error("Mixing named and not named values is not allowed")]
     MixingNamedAndNotNamedValues,
+    #[error(
+        "Named values are not allowed in a batch, values of batch statements are bound by position"
+    )]
+    NamedValuesInBatch,
     #[error(transparent)]
     ValueTooBig(#[from] ValueTooBig),
     #[error("Parsing serialized values failed")]
@@ -84,6 +97,58 @@ pub enum SerializeValuesError {
 
 pub type SerializedResult<'a> = Result<Cow<'a, SerializedValues>, SerializeValuesError>;
 
+/// User Defined Type value with fields accessible by name.
+/// Allows to serialize the value according to the type definition using [UdtByName],
+/// so that the order of fields in the value doesn't matter.
+/// Implemented by `#[derive(IntoUserType)]` and [CqlValue::UserDefinedType].
+pub trait UserTypeFields {
+    /// Names of fields present in the value
+    fn field_names(&self) -> Vec<&str>;
+
+    /// Serializes the field with the given name as [bytes].
+    /// Returns `Ok(false)` without writing anything if the value has no such field.
+    fn serialize_field(&self, name: &str, buf: &mut Vec<u8>) -> Result<bool, ValueTooBig>;
+
+    /// Checks whether the value can be sent as the user defined type `keyspace.type_name`.
+    /// Derived implementations check the names set with `#[scylla(keyspace = "...", udt = "...")]`,
+    /// by default any type is accepted.
+    fn is_user_type(keyspace: &str, type_name: &str) -> bool {
+        let _ = (keyspace, type_name);
+        true
+    }
+}
+
+/// Serializes consecutive fields of a User Defined Type value from a struct.\
+/// Implemented by `#[derive(IntoUserType)]`, it allows to nest the struct
+/// in other user types with `#[scylla(flatten)]`.
+pub trait SerializeUserTypeFields {
+    /// Writes the fields of the struct in order, without the size of the whole value
+    fn serialize_udt_fields(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig>;
+
+    /// Checks the types of the fields of the struct, consuming them from `field_types`
+    fn udt_fields_compatible_with(
+        field_types: &mut std::slice::Iter<'_, (String, ColumnType)>,
+    ) -> bool;
+}
+
+/// Wrapper which serializes a User Defined Type value with its fields matched by name
+/// with the type definition. Fields are written in the order of the definition
+/// and fields missing from the value are sent as null.
+pub struct UdtByName<'a, T: ?Sized> {
+    value: &'a T,
+    definition: Vec<&'a str>,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum UdtSerializationError {
+    #[error("Field {field} is not defined in user defined type {type_name}")]
+    UnknownField { type_name: String, field: String },
+    #[error("Expected a user defined type, got {0:?}")]
+    NotUserDefinedType(ColumnType),
+    #[error("The value can't be sent as user defined type {keyspace}.{type_name}")]
+    WrongUserDefinedType { keyspace: String, type_name: String },
+}
+
 /// Represents list of values to be sent in a query
 /// gets serialized and but into request
 pub trait ValueList {
@@ -97,6 +162,109 @@ pub trait ValueList {
 
         Ok(())
     }
+
+    /// Checks whether the values can be bound to a statement with the given specifications
+    /// of bound values.\
+    /// By default every list of values is assumed to be compatible.
+    fn type_check(_col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+}
+
+/// Rust types don't match the types of bound values or result columns of a statement
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum TypeCheckError {
+    #[error("Wrong number of bound values: statement expects {expected}, provided {actual}")]
+    WrongValuesCount { expected: usize, actual: usize },
+    #[error("Wrong row size: expected {expected}, statement returns {actual} columns")]
+    WrongRowSize { expected: usize, actual: usize },
+    #[error("Column {column} is missing in the statement result")]
+    MissingColumn { column: String },
+    #[error("No value named {name} provided for a bind marker of the statement")]
+    MissingNamedValue { name: String },
+    #[error("Statement has no bind marker named {name}")]
+    UnknownNamedValue { name: String },
+    #[error("Rust type {rust_type} is not compatible with CQL type {cql_type:?} of {name}")]
+    IncompatibleType {
+        name: String,
+        rust_type: &'static str,
+        cql_type: ColumnType,
+    },
+}
+
+/// Checks whether the number of values matches the number of bound values of a statement
+pub fn check_values_count(col_specs: &[ColumnSpec], count: usize) -> Result<(), TypeCheckError> {
+    if col_specs.len() != count {
+        return Err(TypeCheckError::WrongValuesCount {
+            expected: col_specs.len(),
+            actual: count,
+        });
+    }
+    Ok(())
+}
+
+/// Checks whether values of type `T` can be sent as the given bound value
+pub fn check_value_type<T: Value>(col_spec: &ColumnSpec) -> Result<(), TypeCheckError> {
+    if !T::is_compatible_with(&col_spec.typ) {
+        return Err(TypeCheckError::IncompatibleType {
+            name: col_spec.name.clone(),
+            rust_type: std::any::type_name::<T>(),
+            cql_type: col_spec.typ.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks whether the value is compatible with all bind markers named `name`
+pub fn check_named_value_type<T: Value>(
+    col_specs: &[ColumnSpec],
+    name: &str,
+) -> Result<(), TypeCheckError> {
+    let mut found = false;
+    for col_spec in col_specs.iter().filter(|col_spec| col_spec.name == name) {
+        check_value_type::<T>(col_spec)?;
+        found = true;
+    }
+    if !found {
+        return Err(TypeCheckError::UnknownNamedValue {
+            name: name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks whether every bind marker of a statement has a value among the named ones
+pub fn check_named_values_present(
+    col_specs: &[ColumnSpec],
+    names: &[&str],
+) -> Result<(), TypeCheckError> {
+    match col_specs
+        .iter()
+        .find(|col_spec| !names.contains(&col_spec.name.as_str()))
+    {
+        Some(col_spec) => Err(TypeCheckError::MissingNamedValue {
+            name: col_spec.name.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Values bound to markers by their names.\
+/// Implemented by `#[derive(ValueList)]` with `#[scylla(named)]`, it allows to nest
+/// the struct in other named value lists with `#[scylla(flatten)]`.
+pub trait NamedValues {
+    /// Adds the values together with their names to `values`
+    fn add_named_values(&self, values: &mut SerializedValues) -> Result<(), SerializeValuesError>;
+
+    /// Names of all the values
+    fn value_names() -> Vec<&'static str>;
+
+    /// Checks the types of the values against bind markers with the same names,
+    /// see [check_named_value_type]
+    fn check_named_value_types(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError>;
 }
 
 /// Represents List of ValueList for Batch statement
@@ -186,6 +354,56 @@ impl SerializedValues {
         Ok(())
     }
 
+    /// Replaces all null values with unset, so that `None` doesn't overwrite existing data.\
+    /// Only the values themselves are affected, nulls nested in collections or UDTs are kept.
+    pub fn replace_nulls_with_unset(&mut self) {
+        replace_nulls_with_unset(&mut self.serialized_values, self.contains_names);
+    }
+
+    /// Converts named values to positional ones, in the order of bind markers described by `col_specs`.\
+    /// Every bind marker has to have a value with its name and every value has to match some bind marker.
+    /// Values without names are returned as they are.
+    pub fn bind_by_name(
+        &self,
+        col_specs: &[ColumnSpec],
+    ) -> Result<Cow<'_, SerializedValues>, TypeCheckError> {
+        if !self.contains_names {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        // Pairs of names and values, values are kept serialized together with their length
+        let mut named_values: Vec<(&str, &[u8])> = Vec::with_capacity(self.values_num as usize);
+        let mut buf: &[u8] = &self.serialized_values;
+        while !buf.is_empty() {
+            let name = types::read_string(&mut buf).expect("badly encoded value name");
+            let value_start = buf;
+            types::read_bytes_opt(&mut buf).expect("badly encoded value");
+            named_values.push((name, &value_start[..value_start.len() - buf.len()]));
+        }
+
+        if let Some((name, _)) = named_values
+            .iter()
+            .find(|(name, _)| !col_specs.iter().any(|col_spec| col_spec.name == *name))
+        {
+            return Err(TypeCheckError::UnknownNamedValue {
+                name: name.to_string(),
+            });
+        }
+
+        let mut result = SerializedValues::with_capacity(self.serialized_values.len());
+        for col_spec in col_specs {
+            let (_, value) = named_values
+                .iter()
+                .find(|(name, _)| *name == col_spec.name)
+                .ok_or_else(|| TypeCheckError::MissingNamedValue {
+                    name: col_spec.name.clone(),
+                })?;
+            result.serialized_values.extend_from_slice(value);
+            result.values_num += 1;
+        }
+        Ok(Cow::Owned(result))
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = Option<&[u8]>> {
         SerializedValuesIterator {
             serialized_values: &self.serialized_values,
@@ -205,6 +423,29 @@ impl SerializedValues {
     pub fn len(&self) -> i16 {
         self.values_num
     }
+
+    /// Total size of the serialized values in bytes
+    pub fn size(&self) -> usize {
+        self.serialized_values.len()
+    }
+}
+
+// Replaces lengths of null values in `serialized_values` (without the count of values)
+// with the length marking unset values
+fn replace_nulls_with_unset(serialized_values: &mut [u8], contains_names: bool) {
+    let mut pos = 0;
+    while pos < serialized_values.len() {
+        let mut buf = &serialized_values[pos..];
+        if contains_names {
+            types::read_string(&mut buf).expect("badly encoded value name");
+        }
+        let len_pos = serialized_values.len() - buf.len();
+        let len = types::read_int(&mut buf).expect("badly encoded value");
+        if len == -1 {
+            serialized_values[len_pos..len_pos + 4].copy_from_slice(&(-2_i32).to_be_bytes());
+        }
+        pos = len_pos + 4 + len.max(0) as usize;
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -236,6 +477,10 @@ impl<'a> Iterator for SerializedValuesIterator<'a> {
 
 // Implement Value for primitive types
 impl Value for i8 {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::TinyInt)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(1);
         buf.put_i8(*self);
@@ -244,6 +489,10 @@ impl Value for i8 {
 }
 
 impl Value for i16 {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::SmallInt)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(2);
         buf.put_i16(*self);
@@ -252,6 +501,10 @@ impl Value for i16 {
 }
 
 impl Value for i32 {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Int)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(4);
         buf.put_i32(*self);
@@ -260,6 +513,13 @@ impl Value for i32 {
 }
 
 impl Value for i64 {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(
+            typ,
+            ColumnType::BigInt | ColumnType::Counter | ColumnType::Timestamp
+        )
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(8);
         buf.put_i64(*self);
@@ -268,6 +528,10 @@ impl Value for i64 {
 }
 
 impl Value for BigDecimal {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Decimal)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         let (value, scale) = self.as_bigint_and_exponent();
 
@@ -283,6 +547,10 @@ impl Value for BigDecimal {
 }
 
 impl Value for NaiveDate {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Date)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(4);
         let unix_epoch = NaiveDate::from_ymd(1970, 1, 1);
@@ -300,6 +568,10 @@ impl Value for NaiveDate {
 }
 
 impl Value for Date {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Date)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(4);
         buf.put_u32(self.0);
@@ -308,6 +580,10 @@ impl Value for Date {
 }
 
 impl Value for Timestamp {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Timestamp)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(8);
         buf.put_i64(self.0.num_milliseconds());
@@ -316,6 +592,10 @@ impl Value for Timestamp {
 }
 
 impl Value for Time {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Time)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(8);
         buf.put_i64(self.0.num_nanoseconds().ok_or(ValueTooBig)?);
@@ -324,6 +604,10 @@ impl Value for Time {
 }
 
 impl Value for bool {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Boolean)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(1);
         let false_bytes: &[u8] = &[0x00];
@@ -339,6 +623,10 @@ impl Value for bool {
 }
 
 impl Value for f32 {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Float)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(4);
         buf.put_f32(*self);
@@ -347,6 +635,10 @@ impl Value for f32 {
 }
 
 impl Value for f64 {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Double)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(8);
         buf.put_f64(*self);
@@ -355,6 +647,10 @@ impl Value for f64 {
 }
 
 impl Value for Uuid {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Uuid | ColumnType::Timeuuid)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         buf.put_i32(16);
         buf.extend_from_slice(self.as_bytes());
@@ -363,6 +659,10 @@ impl Value for Uuid {
 }
 
 impl Value for BigInt {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Varint)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         let serialized = self.to_signed_bytes_be();
         let serialized_len: i32 = serialized.len().try_into().map_err(|_| ValueTooBig)?;
@@ -375,6 +675,10 @@ impl Value for BigInt {
 }
 
 impl Value for &str {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Ascii | ColumnType::Text)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         let str_bytes: &[u8] = self.as_bytes();
         let val_len: i32 = str_bytes.len().try_into().map_err(|_| ValueTooBig)?;
@@ -387,6 +691,10 @@ impl Value for &str {
 }
 
 impl Value for Vec<u8> {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Blob)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         let val_len: i32 = self.len().try_into().map_err(|_| ValueTooBig)?;
         buf.put_i32(val_len);
@@ -398,6 +706,10 @@ impl Value for Vec<u8> {
 }
 
 impl Value for IpAddr {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Inet)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         match self {
             IpAddr::V4(addr) => {
@@ -415,6 +727,10 @@ impl Value for IpAddr {
 }
 
 impl Value for String {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Ascii | ColumnType::Text)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         <&str as Value>::serialize(&self.as_str(), buf)
     }
@@ -422,6 +738,10 @@ impl Value for String {
 
 /// Every Option<T> can be serialized as None -> NULL, Some(val) -> val.serialize()
 impl<T: Value> Value for Option<T> {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        <T as Value>::is_compatible_with(typ)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         match self {
             Some(val) => <T as Value>::serialize(val, buf),
@@ -442,12 +762,20 @@ impl Value for Unset {
 }
 
 impl Value for Counter {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Counter)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         self.0.serialize(buf)
     }
 }
 
 impl Value for CqlDuration {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(typ, ColumnType::Duration)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         let bytes_num_pos: usize = buf.len();
         buf.put_i32(0);
@@ -465,6 +793,10 @@ impl Value for CqlDuration {
 }
 
 impl<V: Value> Value for MaybeUnset<V> {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        <V as Value>::is_compatible_with(typ)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         match self {
             MaybeUnset::Set(v) => v.serialize(buf),
@@ -475,6 +807,10 @@ impl<V: Value> Value for MaybeUnset<V> {
 
 // Every &impl Value should also implement Value
 impl<T: Value> Value for &T {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        <T as Value>::is_compatible_with(typ)
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         <T as Value>::serialize(*self, buf)
     }
@@ -522,36 +858,90 @@ fn serialize_list_or_set<'a, V: 'a + Value>(
 }
 
 impl<V: Value> Value for HashSet<V> {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => {
+                <V as Value>::is_compatible_with(elem_type)
+            }
+            _ => false,
+        }
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_list_or_set(self.iter(), self.len(), buf)
     }
 }
 
 impl<K: Value, V: Value> Value for HashMap<K, V> {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::Map(key_type, value_type) => {
+                K::is_compatible_with(key_type) && V::is_compatible_with(value_type)
+            }
+            _ => false,
+        }
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_map(self.iter(), self.len(), buf)
     }
 }
 
 impl<V: Value> Value for BTreeSet<V> {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => {
+                <V as Value>::is_compatible_with(elem_type)
+            }
+            _ => false,
+        }
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_list_or_set(self.iter(), self.len(), buf)
     }
 }
 
 impl<K: Value, V: Value> Value for BTreeMap<K, V> {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::Map(key_type, value_type) => {
+                K::is_compatible_with(key_type) && V::is_compatible_with(value_type)
+            }
+            _ => false,
+        }
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_map(self.iter(), self.len(), buf)
     }
 }
 
 impl<T: Value> Value for Vec<T> {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => {
+                <T as Value>::is_compatible_with(elem_type)
+            }
+            _ => false,
+        }
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_list_or_set(self.iter(), self.len(), buf)
     }
 }
 
 impl<T: Value> Value for &[T] {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        match typ {
+            ColumnType::List(elem_type) | ColumnType::Set(elem_type) => {
+                <T as Value>::is_compatible_with(elem_type)
+            }
+            _ => false,
+        }
+    }
+
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
         serialize_list_or_set(self.iter(), self.len(), buf)
     }
@@ -626,6 +1016,21 @@ macro_rules! impl_value_for_tuple {
         where
             $($Ti: Value),+
         {
+            fn is_compatible_with(typ: &ColumnType) -> bool {
+                match typ {
+                    ColumnType::Tuple(elem_types) => {
+                        let mut elem_types_iter = elem_types.iter();
+                        let compatible = true $(
+                            && elem_types_iter
+                                .next()
+                                .map_or(false, <$Ti as Value>::is_compatible_with)
+                        )*;
+                        compatible && elem_types_iter.next().is_none()
+                    }
+                    _ => false,
+                }
+            }
+
             fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
                 let bytes_num_pos: usize = buf.len();
                 buf.put_i32(0);
@@ -643,6 +1048,121 @@ macro_rules! impl_value_for_tuple {
     }
 }
 
+impl UserTypeFields for CqlValue {
+    // Values other than CqlValue::UserDefinedType don't have any fields
+    fn field_names(&self) -> Vec<&str> {
+        match self {
+            CqlValue::UserDefinedType { fields, .. } => {
+                fields.iter().map(|(name, _)| name.as_str()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn serialize_field(&self, name: &str, buf: &mut Vec<u8>) -> Result<bool, ValueTooBig> {
+        let field_value = match self {
+            CqlValue::UserDefinedType { fields, .. } => fields
+                .iter()
+                .find(|(field_name, _)| field_name == name)
+                .map(|(_, value)| value),
+            _ => None,
+        };
+
+        match field_value {
+            Some(value) => value.serialize(buf).map(|()| true),
+            None => Ok(false),
+        }
+    }
+}
+
+impl<'a, T: UserTypeFields + ?Sized> UdtByName<'a, T> {
+    /// Creates a wrapper serializing `value` according to the definition of the type `type_name`,
+    /// given as names of its fields in the defined order,
+    /// e.g. taken from `Keyspace::user_defined_types` in cluster metadata.
+    ///
+    /// Fails if the value has a field which isn't part of the definition.
+    pub fn new(
+        value: &'a T,
+        type_name: &str,
+        definition: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, UdtSerializationError> {
+        let definition: Vec<&'a str> = definition.into_iter().collect();
+
+        if let Some(unknown) = value
+            .field_names()
+            .into_iter()
+            .find(|name| !definition.contains(name))
+        {
+            return Err(UdtSerializationError::UnknownField {
+                type_name: type_name.to_string(),
+                field: unknown.to_string(),
+            });
+        }
+
+        Ok(Self { value, definition })
+    }
+
+    /// Creates a wrapper serializing `value` according to the type of a bound value
+    /// or column, e.g. taken from metadata of a prepared statement.
+    ///
+    /// Fails if `typ` isn't a user defined type, it's a different type than the one the value
+    /// is declared as (see [UserTypeFields::is_user_type]) or the value has a field which isn't part of it.
+    pub fn with_column_type(
+        value: &'a T,
+        typ: &'a ColumnType,
+    ) -> Result<Self, UdtSerializationError> {
+        match typ {
+            ColumnType::UserDefinedType {
+                keyspace,
+                type_name,
+                ..
+            } if !T::is_user_type(keyspace, type_name) => {
+                Err(UdtSerializationError::WrongUserDefinedType {
+                    keyspace: keyspace.clone(),
+                    type_name: type_name.clone(),
+                })
+            }
+            ColumnType::UserDefinedType {
+                type_name,
+                field_types,
+                ..
+            } => Self::new(
+                value,
+                type_name,
+                field_types.iter().map(|(name, _)| name.as_str()),
+            ),
+            other => Err(UdtSerializationError::NotUserDefinedType(other.clone())),
+        }
+    }
+}
+
+impl<'a, T: UserTypeFields + ?Sized> Value for UdtByName<'a, T> {
+    fn is_compatible_with(typ: &ColumnType) -> bool {
+        matches!(
+            typ,
+            ColumnType::UserDefinedType { keyspace, type_name, .. } if T::is_user_type(keyspace, type_name)
+        )
+    }
+
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+        let bytes_num_pos: usize = buf.len();
+        buf.put_i32(0);
+
+        for name in &self.definition {
+            if !self.value.serialize_field(name, buf)? {
+                // Fields missing from the value are null
+                buf.put_i32(-1);
+            }
+        }
+
+        let written_bytes: usize = buf.len() - bytes_num_pos - 4;
+        let written_bytes_i32: i32 = written_bytes.try_into().map_err(|_| ValueTooBig)?;
+        buf[bytes_num_pos..(bytes_num_pos + 4)].copy_from_slice(&written_bytes_i32.to_be_bytes());
+
+        Ok(())
+    }
+}
+
 impl_value_for_tuple!(T0; 0);
 impl_value_for_tuple!(T0, T1; 0, 1);
 impl_value_for_tuple!(T0, T1, T2; 0, 1, 2);
@@ -676,6 +1196,10 @@ impl ValueList for () {
     fn serialized(&self) -> SerializedResult<'_> {
         Ok(Cow::Owned(SerializedValues::new()))
     }
+
+    fn type_check(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        check_values_count(col_specs, 0)
+    }
 }
 
 // Implement ValueList for &[] - u8 because otherwise rust can't infer type
@@ -683,6 +1207,10 @@ impl ValueList for [u8; 0] {
     fn serialized(&self) -> SerializedResult<'_> {
         Ok(Cow::Owned(SerializedValues::new()))
     }
+
+    fn type_check(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        check_values_count(col_specs, 0)
+    }
 }
 
 // Implement ValueList for slices of Value types
@@ -695,6 +1223,11 @@ impl<T: Value> ValueList for &[T] {
 
         Ok(Cow::Owned(result))
     }
+
+    // The number of values is known only at runtime
+    fn type_check(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        col_specs.iter().try_for_each(check_value_type::<T>)
+    }
 }
 
 // Implement ValueList for Vec<Value>
@@ -707,6 +1240,11 @@ impl<T: Value> ValueList for Vec<T> {
 
         Ok(Cow::Owned(result))
     }
+
+    // The number of values is known only at runtime
+    fn type_check(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        col_specs.iter().try_for_each(check_value_type::<T>)
+    }
 }
 
 // Implement ValueList for maps, which serializes named values
@@ -740,6 +1278,11 @@ impl<T0: Value> ValueList for (T0,) {
         result.add_value(&self.0)?;
         Ok(Cow::Owned(result))
     }
+
+    fn type_check(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        check_values_count(col_specs, 1)?;
+        check_value_type::<T0>(&col_specs[0])
+    }
 }
 
 macro_rules! impl_value_list_for_tuple {
@@ -755,6 +1298,14 @@ macro_rules! impl_value_list_for_tuple {
                 )*
                 Ok(Cow::Owned(result))
             }
+
+            fn type_check(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+                check_values_count(col_specs, $size)?;
+                $(
+                    check_value_type::<$Ti>(&col_specs[$FieldI])?;
+                )*
+                Ok(())
+            }
         }
     }
 }
@@ -787,6 +1338,10 @@ impl<T: ValueList> ValueList for &T {
     fn serialized(&self) -> SerializedResult<'_> {
         <T as ValueList>::serialized(*self)
     }
+
+    fn type_check(col_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        <T as ValueList>::type_check(col_specs)
+    }
 }
 
 impl ValueList for SerializedValues {
@@ -805,6 +1360,19 @@ impl<'b> ValueList for Cow<'b, SerializedValues> {
 // BatchValues impls
 //
 
+// Values in a batch are bound by position, the frame has no place for their names
+fn write_batch_statement_values(
+    values: &impl ValueList,
+    buf: &mut impl BufMut,
+) -> Result<(), SerializeValuesError> {
+    let serialized = values.serialized()?;
+    if serialized.has_names() {
+        return Err(SerializeValuesError::NamedValuesInBatch);
+    }
+    SerializedValues::write_to_request(&serialized, buf);
+    Ok(())
+}
+
 // Implement BatchValues for slices of ValueList types
 impl<T: ValueList> BatchValues for &[T] {
     fn len(&self) -> usize {
@@ -816,7 +1384,7 @@ impl<T: ValueList> BatchValues for &[T] {
         n: usize,
         buf: &mut impl BufMut,
     ) -> Result<(), SerializeValuesError> {
-        self[n].write_to_request(buf)?;
+        write_batch_statement_values(&self[n], buf)?;
         Ok(())
     }
 }
@@ -832,7 +1400,7 @@ impl<T: ValueList> BatchValues for Vec<T> {
         n: usize,
         buf: &mut impl BufMut,
     ) -> Result<(), SerializeValuesError> {
-        self[n].write_to_request(buf)?;
+        write_batch_statement_values(&self[n], buf)?;
         Ok(())
     }
 }
@@ -850,7 +1418,7 @@ impl<T0: ValueList> BatchValues for (T0,) {
         buf: &mut impl BufMut,
     ) -> Result<(), SerializeValuesError> {
         match n {
-            0 => self.0.write_to_request(buf)?,
+            0 => write_batch_statement_values(&self.0, buf)?,
             _ => panic!("Tried to serialize ValueList with an out of range index! index: {}, ValueList len: {}", n, 1),
         };
 
@@ -871,7 +1439,7 @@ macro_rules! impl_batch_values_for_tuple {
             fn write_nth_to_request(&self, n: usize, buf: &mut impl BufMut) -> Result<(), SerializeValuesError> {
                 match n {
                     $(
-                        $FieldI => self.$FieldI.write_to_request(buf) ?,
+                        $FieldI => write_batch_statement_values(&self.$FieldI, buf) ?,
                     )*
                     _ => panic!("Tried to serialize ValueList with an out of range index! index: {}, ValueList len: {}", n, $TupleSize),
                 }
@@ -905,6 +1473,49 @@ impl_batch_values_for_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T
 impl_batch_values_for_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15;
                              0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15; 16);
 
+/// Batch values which send null values as unset in the chosen statements,
+/// used to apply `none_as_unset` of each statement in a batch.
+pub struct NoneAsUnsetBatchValues<T: BatchValues> {
+    values: T,
+    none_as_unset: Vec<bool>,
+}
+
+impl<T: BatchValues> NoneAsUnsetBatchValues<T> {
+    /// `none_as_unset[n]` tells whether nulls of the n-th statement are sent as unset.
+    /// Statements past the end of `none_as_unset` keep their nulls.
+    pub fn new(values: T, none_as_unset: Vec<bool>) -> Self {
+        NoneAsUnsetBatchValues {
+            values,
+            none_as_unset,
+        }
+    }
+}
+
+impl<T: BatchValues> BatchValues for NoneAsUnsetBatchValues<T> {
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn write_nth_to_request(
+        &self,
+        n: usize,
+        buf: &mut impl BufMut,
+    ) -> Result<(), SerializeValuesError> {
+        if !self.none_as_unset.get(n).copied().unwrap_or(false) {
+            return self.values.write_nth_to_request(n, buf);
+        }
+
+        // Values in a batch are never named, they start with the number of values
+        let mut serialized = Vec::new();
+        self.values.write_nth_to_request(n, &mut serialized)?;
+        if serialized.len() > 2 {
+            replace_nulls_with_unset(&mut serialized[2..], false);
+        }
+        buf.put_slice(&serialized);
+        Ok(())
+    }
+}
+
 // Every &impl BatchValues should also implement BatchValues
 impl<T: BatchValues> BatchValues for &T {
     fn len(&self) -> usize {
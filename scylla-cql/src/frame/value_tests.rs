@@ -1,6 +1,7 @@
+use super::response::result::{ColumnType, CqlValue};
 use super::value::{
-    BatchValues, Date, MaybeUnset, SerializeValuesError, SerializedValues, Time, Timestamp, Unset,
-    Value, ValueList, ValueTooBig,
+    BatchValues, Date, MaybeUnset, NoneAsUnsetBatchValues, SerializeValuesError, SerializedValues,
+    Time, Timestamp, UdtByName, UdtSerializationError, Unset, Value, ValueList, ValueTooBig,
 };
 use bytes::BufMut;
 use chrono::{Duration, NaiveDate};
@@ -633,6 +634,376 @@ fn tuple_batch_values() {
     );
 }
 
+#[test]
+fn derived_value_list_skips_fields() {
+    use crate::macros::ValueList;
+
+    #[derive(ValueList)]
+    #[scylla(crate = "crate")]
+    struct MyValues {
+        a: i8,
+        #[scylla(skip)]
+        _not_a_value: String,
+        b: i8,
+    }
+
+    let values = MyValues {
+        a: 1,
+        _not_a_value: "skipped".to_string(),
+        b: 2,
+    };
+    let serialized: SerializedValues = values.serialized().unwrap().into_owned();
+
+    assert_eq!(
+        serialized.iter().collect::<Vec<_>>(),
+        vec![Some([1].as_ref()), Some([2].as_ref())]
+    );
+}
+
+#[test]
+fn derived_user_type_skips_fields() {
+    use crate::macros::IntoUserType;
+
+    #[derive(IntoUserType)]
+    #[scylla(crate = "crate")]
+    struct MyUdt {
+        a: i8,
+        #[scylla(skip)]
+        _not_a_field: String,
+        b: i8,
+    }
+
+    let udt = MyUdt {
+        a: 1,
+        _not_a_field: "skipped".to_string(),
+        b: 2,
+    };
+
+    assert_eq!(
+        serialized(udt),
+        vec![0, 0, 0, 10, 0, 0, 0, 1, 1, 0, 0, 0, 1, 2]
+    );
+}
+
+#[test]
+fn derived_generic_value_list() {
+    use crate::macros::ValueList;
+
+    #[derive(ValueList)]
+    #[scylla(crate = "crate")]
+    struct MyValues<'a, T> {
+        a: T,
+        b: &'a str,
+    }
+
+    let values = MyValues { a: 1_i8, b: "b" };
+    let serialized: SerializedValues = values.serialized().unwrap().into_owned();
+
+    assert_eq!(
+        serialized.iter().collect::<Vec<_>>(),
+        vec![Some([1].as_ref()), Some(b"b".as_ref())]
+    );
+}
+
+#[test]
+fn derived_cql_newtype() {
+    use crate::macros::CqlNewtype;
+
+    #[derive(CqlNewtype)]
+    #[scylla(crate = "crate")]
+    struct UserId(Uuid);
+
+    #[derive(CqlNewtype)]
+    #[scylla(crate = "crate")]
+    struct Wrapper<T> {
+        inner: T,
+    }
+
+    let uuid = Uuid::from_u128(0x8e14e760_7fa8_11eb_bc66_000000000001);
+    assert_eq!(serialized(UserId(uuid)), serialized(uuid));
+    assert_eq!(serialized(Wrapper { inner: 7_i32 }), serialized(7_i32));
+    assert_eq!(
+        serialized(Wrapper {
+            inner: Some(UserId(uuid))
+        }),
+        serialized(uuid)
+    );
+}
+
+#[test]
+fn udt_by_name_serialization() {
+    use crate::macros::IntoUserType;
+
+    #[derive(IntoUserType)]
+    #[scylla(crate = "crate")]
+    struct MyUdt {
+        b: i8,
+        #[scylla(rename = "a")]
+        first: i8,
+        #[scylla(skip)]
+        _not_a_field: i8,
+    }
+
+    let udt = MyUdt {
+        b: 2,
+        first: 1,
+        _not_a_field: 3,
+    };
+
+    // Fields are written in the definition order, missing ones are null
+    let by_name = UdtByName::new(&udt, "my_udt", ["a", "b", "c"]).unwrap();
+    assert_eq!(
+        serialized(by_name),
+        vec![0, 0, 0, 14, 0, 0, 0, 1, 1, 0, 0, 0, 1, 2, 255, 255, 255, 255]
+    );
+
+    let column_type = ColumnType::UserDefinedType {
+        type_name: "my_udt".to_string(),
+        keyspace: "ks".to_string(),
+        field_types: vec![
+            ("a".to_string(), ColumnType::TinyInt),
+            ("b".to_string(), ColumnType::TinyInt),
+        ],
+    };
+    let by_name = UdtByName::with_column_type(&udt, &column_type).unwrap();
+    assert_eq!(
+        serialized(by_name),
+        vec![0, 0, 0, 10, 0, 0, 0, 1, 1, 0, 0, 0, 1, 2]
+    );
+
+    assert_eq!(
+        UdtByName::new(&udt, "my_udt", ["a"]).err(),
+        Some(UdtSerializationError::UnknownField {
+            type_name: "my_udt".to_string(),
+            field: "b".to_string()
+        })
+    );
+    assert_eq!(
+        UdtByName::with_column_type(&udt, &ColumnType::Int).err(),
+        Some(UdtSerializationError::NotUserDefinedType(ColumnType::Int))
+    );
+}
+
+#[test]
+fn udt_with_flattened_struct_serialization() {
+    use crate::frame::value::UserTypeFields;
+    use crate::macros::IntoUserType;
+
+    #[derive(IntoUserType)]
+    #[scylla(crate = "crate")]
+    struct Coordinates {
+        x: i8,
+        y: i8,
+    }
+
+    #[derive(IntoUserType)]
+    #[scylla(crate = "crate")]
+    struct Point {
+        #[scylla(rename = "a")]
+        first: i8,
+        #[scylla(flatten)]
+        coordinates: Coordinates,
+        z: i8,
+    }
+
+    let point = Point {
+        first: 1,
+        coordinates: Coordinates { x: 2, y: 3 },
+        z: 4,
+    };
+    assert_eq!(
+        serialized(&point),
+        vec![0, 0, 0, 20, 0, 0, 0, 1, 1, 0, 0, 0, 1, 2, 0, 0, 0, 1, 3, 0, 0, 0, 1, 4]
+    );
+    assert_eq!(point.field_names(), vec!["a", "x", "y", "z"]);
+
+    let udt_type = |names: &[&str]| ColumnType::UserDefinedType {
+        type_name: "point".to_string(),
+        keyspace: "ks".to_string(),
+        field_types: names
+            .iter()
+            .map(|name| (name.to_string(), ColumnType::TinyInt))
+            .collect(),
+    };
+    assert!(Point::is_compatible_with(&udt_type(&["a", "x", "y", "z"])));
+    assert!(!Point::is_compatible_with(&udt_type(&["a", "z", "x", "y"])));
+
+    // Fields of the flattened struct are matched by name as well
+    let by_name = UdtByName::new(&point, "point", ["z", "y", "x", "a"]).unwrap();
+    assert_eq!(
+        serialized(by_name),
+        vec![0, 0, 0, 20, 0, 0, 0, 1, 4, 0, 0, 0, 1, 3, 0, 0, 0, 1, 2, 0, 0, 0, 1, 1]
+    );
+}
+
+#[test]
+fn udt_name_and_keyspace_check() {
+    use crate::macros::IntoUserType;
+
+    #[derive(IntoUserType)]
+    #[scylla(crate = "crate", keyspace = "ks", udt = "my_udt")]
+    struct Named {
+        a: i8,
+    }
+
+    #[derive(IntoUserType)]
+    #[scylla(crate = "crate")]
+    struct Unnamed {
+        a: i8,
+    }
+
+    let udt_type = |keyspace: &str, type_name: &str| ColumnType::UserDefinedType {
+        type_name: type_name.to_string(),
+        keyspace: keyspace.to_string(),
+        field_types: vec![("a".to_string(), ColumnType::TinyInt)],
+    };
+
+    assert!(Named::is_compatible_with(&udt_type("ks", "my_udt")));
+    assert!(!Named::is_compatible_with(&udt_type("ks", "other_udt")));
+    assert!(!Named::is_compatible_with(&udt_type("other_ks", "my_udt")));
+    assert!(Unnamed::is_compatible_with(&udt_type(
+        "other_ks",
+        "other_udt"
+    )));
+
+    assert!(UdtByName::<Named>::is_compatible_with(&udt_type(
+        "ks", "my_udt"
+    )));
+    assert!(!UdtByName::<Named>::is_compatible_with(&udt_type(
+        "ks",
+        "other_udt"
+    )));
+    assert!(UdtByName::<Unnamed>::is_compatible_with(&udt_type(
+        "ks",
+        "other_udt"
+    )));
+
+    let value = Named { a: 1 };
+    let other_type = udt_type("ks", "other_udt");
+    assert_eq!(
+        UdtByName::with_column_type(&value, &other_type).err(),
+        Some(UdtSerializationError::WrongUserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "other_udt".to_string()
+        })
+    );
+    let my_type = udt_type("ks", "my_udt");
+    assert!(UdtByName::with_column_type(&value, &my_type).is_ok());
+}
+
+#[test]
+fn cql_value_udt_by_name_serialization() {
+    let udt = CqlValue::UserDefinedType {
+        keyspace: "ks".to_string(),
+        type_name: "my_udt".to_string(),
+        fields: vec![
+            ("b".to_string(), Some(CqlValue::TinyInt(2))),
+            ("a".to_string(), None),
+        ],
+    };
+
+    let by_name = UdtByName::new(&udt, "my_udt", ["a", "b"]).unwrap();
+    assert_eq!(
+        serialized(by_name),
+        vec![0, 0, 0, 9, 255, 255, 255, 255, 0, 0, 0, 1, 2]
+    );
+}
+
+#[test]
+fn value_list_type_check() {
+    use crate::frame::response::result::{ColumnSpec, TableSpec};
+    use crate::frame::value::TypeCheckError;
+    use crate::macros::{IntoUserType, ValueList};
+
+    #[derive(IntoUserType)]
+    #[scylla(crate = "crate")]
+    struct MyUdt {
+        x: i32,
+        y: Option<String>,
+    }
+
+    #[derive(ValueList)]
+    #[scylla(crate = "crate")]
+    struct MyValues {
+        a: i64,
+        b: MyUdt,
+    }
+
+    let spec = |name: &str, typ: ColumnType| ColumnSpec {
+        table_spec: TableSpec {
+            ks_name: "ks".to_string(),
+            table_name: "tab".to_string(),
+        },
+        name: name.to_string(),
+        typ,
+    };
+    let udt_type = |fields: &[&str]| ColumnType::UserDefinedType {
+        type_name: "my_udt".to_string(),
+        keyspace: "ks".to_string(),
+        field_types: fields
+            .iter()
+            .map(|name| match *name {
+                "x" => (name.to_string(), ColumnType::Int),
+                _ => (name.to_string(), ColumnType::Text),
+            })
+            .collect(),
+    };
+    let specs = vec![
+        spec("a", ColumnType::BigInt),
+        spec("b", udt_type(&["x", "y", "z"])),
+    ];
+
+    assert_eq!(MyValues::type_check(&specs), Ok(()));
+    assert_eq!(<(i64, MyUdt)>::type_check(&specs), Ok(()));
+    assert_eq!(
+        <(i64,)>::type_check(&specs),
+        Err(TypeCheckError::WrongValuesCount {
+            expected: 2,
+            actual: 1
+        })
+    );
+    assert!(matches!(
+        <(i32, MyUdt)>::type_check(&specs),
+        Err(TypeCheckError::IncompatibleType { name, .. }) if name == "a"
+    ));
+
+    // Fields of the struct have to be in the order of the type definition
+    assert!(!MyUdt::is_compatible_with(&udt_type(&["y", "x"])));
+    assert!(!MyUdt::is_compatible_with(&udt_type(&["x"])));
+    assert!(!MyUdt::is_compatible_with(&ColumnType::Int));
+
+    assert_eq!(<()>::type_check(&[]), Ok(()));
+    assert!(<Vec<i32>>::type_check(&[spec("a", ColumnType::Text)]).is_err());
+}
+
+#[test]
+fn cql_enum_serialization() {
+    use crate::macros::CqlEnum;
+
+    #[derive(CqlEnum)]
+    #[scylla(crate = "crate", repr = "text")]
+    enum Shape {
+        #[scylla(rename = "circle")]
+        Circle,
+        Unknown(String),
+    }
+
+    #[derive(CqlEnum)]
+    #[scylla(crate = "crate", repr = "int")]
+    enum Level {
+        Low,
+        High = 10,
+    }
+
+    assert_eq!(serialized(Shape::Circle), serialized("circle"));
+    assert_eq!(
+        serialized(Shape::Unknown("square".to_string())),
+        serialized("square")
+    );
+    assert_eq!(serialized(Level::Low), serialized(0_i32));
+    assert_eq!(serialized(Level::High), serialized(10_i32));
+}
+
 #[test]
 fn ref_batch_values() {
     let batch_values: &[&[i8]] = &[&[1, 2], &[2, 3, 4, 5], &[6]];
@@ -646,3 +1017,259 @@ fn ref_batch_values() {
         assert_eq!(request, vec![0, 2, 0, 0, 0, 1, 1, 0, 0, 0, 1, 2]);
     }
 }
+
+#[test]
+fn named_values_bind_by_name() {
+    use crate::frame::response::result::{ColumnSpec, TableSpec};
+    use crate::frame::value::TypeCheckError;
+    use crate::macros::ValueList;
+    use std::collections::HashMap;
+
+    #[derive(ValueList)]
+    #[scylla(crate = "crate", named)]
+    struct MyValues {
+        b: Option<i32>,
+        #[scylla(rename = "a")]
+        renamed: i64,
+        #[scylla(skip)]
+        _skipped: bool,
+    }
+
+    let spec = |name: &str, typ: ColumnType| ColumnSpec {
+        table_spec: TableSpec {
+            ks_name: "ks".to_string(),
+            table_name: "tab".to_string(),
+        },
+        name: name.to_string(),
+        typ,
+    };
+    let specs = vec![spec("a", ColumnType::BigInt), spec("b", ColumnType::Int)];
+
+    let values = MyValues {
+        b: None,
+        renamed: 1,
+        _skipped: true,
+    };
+    let serialized = values.serialized().unwrap().into_owned();
+    assert!(serialized.has_names());
+
+    // Values are reordered to match the markers, null value is preserved
+    let mut expected = SerializedValues::new();
+    expected.add_value(&1_i64).unwrap();
+    expected.add_value(&None::<i32>).unwrap();
+    assert_eq!(
+        serialized.bind_by_name(&specs).unwrap().into_owned(),
+        expected
+    );
+    assert_eq!(MyValues::type_check(&specs), Ok(()));
+
+    let mut map: HashMap<&str, i64> = HashMap::new();
+    map.insert("a", 1);
+    let serialized = map.serialized().unwrap().into_owned();
+    assert_eq!(
+        serialized.bind_by_name(&specs),
+        Err(TypeCheckError::MissingNamedValue {
+            name: "b".to_string()
+        })
+    );
+    map.insert("c", 2);
+    let serialized = map.serialized().unwrap().into_owned();
+    assert!(serialized.bind_by_name(&specs[..1]).is_err());
+
+    assert_eq!(
+        MyValues::type_check(&specs[..1]),
+        Err(TypeCheckError::UnknownNamedValue {
+            name: "b".to_string()
+        })
+    );
+    assert_eq!(
+        MyValues::type_check(&[
+            specs[0].clone(),
+            specs[1].clone(),
+            spec("c", ColumnType::Int)
+        ]),
+        Err(TypeCheckError::MissingNamedValue {
+            name: "c".to_string()
+        })
+    );
+    assert!(matches!(
+        MyValues::type_check(&[spec("a", ColumnType::Text), specs[1].clone()]),
+        Err(TypeCheckError::IncompatibleType { name, .. }) if name == "a"
+    ));
+
+    // Positional values are left as they are
+    let positional = (1_i64, 2_i32).serialized().unwrap().into_owned();
+    assert!(matches!(
+        positional.bind_by_name(&specs),
+        Ok(Cow::Borrowed(_))
+    ));
+}
+
+#[test]
+fn named_values_with_flattened_struct() {
+    use crate::frame::response::result::{ColumnSpec, TableSpec};
+    use crate::frame::value::TypeCheckError;
+    use crate::macros::ValueList;
+
+    #[derive(ValueList)]
+    #[scylla(crate = "crate", named)]
+    struct Key {
+        pk: i32,
+        ck: i32,
+    }
+
+    #[derive(ValueList)]
+    #[scylla(crate = "crate", named)]
+    struct Update {
+        value: String,
+        #[scylla(flatten)]
+        key: Key,
+    }
+
+    let spec = |name: &str, typ: ColumnType| ColumnSpec {
+        table_spec: TableSpec {
+            ks_name: "ks".to_string(),
+            table_name: "tab".to_string(),
+        },
+        name: name.to_string(),
+        typ,
+    };
+    let specs = vec![
+        spec("pk", ColumnType::Int),
+        spec("ck", ColumnType::Int),
+        spec("value", ColumnType::Text),
+    ];
+
+    let values = Update {
+        value: "v".to_string(),
+        key: Key { pk: 1, ck: 2 },
+    };
+    let mut expected = SerializedValues::new();
+    expected.add_value(&1_i32).unwrap();
+    expected.add_value(&2_i32).unwrap();
+    expected.add_value(&"v").unwrap();
+    assert_eq!(
+        values
+            .serialized()
+            .unwrap()
+            .bind_by_name(&specs)
+            .unwrap()
+            .into_owned(),
+        expected
+    );
+
+    assert_eq!(Update::type_check(&specs), Ok(()));
+    assert_eq!(
+        Update::type_check(&specs[1..]),
+        Err(TypeCheckError::UnknownNamedValue {
+            name: "pk".to_string()
+        })
+    );
+    assert!(matches!(
+        Update::type_check(&[spec("pk", ColumnType::Text), specs[1].clone(), specs[2].clone()]),
+        Err(TypeCheckError::IncompatibleType { name, .. }) if name == "pk"
+    ));
+}
+
+#[test]
+fn none_as_unset() {
+    use crate::macros::ValueList;
+
+    let mut values = SerializedValues::new();
+    values.add_value(&None::<i32>).unwrap();
+    values.add_value(&vec![None::<i32>]).unwrap();
+    values.add_value(&Some(1_i32)).unwrap();
+    values.add_value(&Unset).unwrap();
+    values.replace_nulls_with_unset();
+
+    // Null nested in the list is kept
+    let mut expected = SerializedValues::new();
+    expected.add_value(&Unset).unwrap();
+    expected.add_value(&vec![None::<i32>]).unwrap();
+    expected.add_value(&Some(1_i32)).unwrap();
+    expected.add_value(&Unset).unwrap();
+    assert_eq!(values, expected);
+
+    let mut named = SerializedValues::new();
+    named.add_named_value("a", &None::<i32>).unwrap();
+    named.add_named_value("b", &2_i32).unwrap();
+    named.replace_nulls_with_unset();
+
+    let mut expected = SerializedValues::new();
+    expected.add_named_value("a", &Unset).unwrap();
+    expected.add_named_value("b", &2_i32).unwrap();
+    assert_eq!(named, expected);
+
+    #[derive(ValueList)]
+    #[scylla(crate = "crate", none_as_unset)]
+    struct PartialUpdate {
+        a: Option<i32>,
+        b: Option<String>,
+    }
+
+    let update = PartialUpdate {
+        a: None,
+        b: Some("b".to_string()),
+    };
+    let mut expected = SerializedValues::new();
+    expected.add_value(&Unset).unwrap();
+    expected.add_value(&"b").unwrap();
+    assert_eq!(update.serialized().unwrap().into_owned(), expected);
+}
+
+#[test]
+fn none_as_unset_batch_values() {
+    let values = NoneAsUnsetBatchValues::new(
+        (
+            (None::<i32>, 1_i32),
+            (None::<i32>,),
+            (Some(2_i32), None::<i32>),
+        ),
+        vec![true, false],
+    );
+    assert_eq!(values.len(), 3);
+
+    let written = |n: usize| {
+        let mut request: Vec<u8> = Vec::new();
+        values.write_nth_to_request(n, &mut request).unwrap();
+        request
+    };
+    fn expected(values: impl ValueList) -> Vec<u8> {
+        let mut request: Vec<u8> = Vec::new();
+        values.write_to_request(&mut request).unwrap();
+        request
+    }
+
+    assert_eq!(written(0), expected((Unset, 1_i32)));
+    // Statements without none_as_unset keep their nulls
+    assert_eq!(written(1), expected((None::<i32>,)));
+    assert_eq!(written(2), expected((Some(2_i32), None::<i32>)));
+}
+
+#[test]
+fn named_values_in_batch() {
+    use crate::macros::ValueList;
+    use std::collections::HashMap;
+
+    #[derive(ValueList)]
+    #[scylla(crate = "crate", named)]
+    struct NamedValues {
+        a: i32,
+    }
+
+    // The frame of a batch has no place for names of the values
+    let mut request: Vec<u8> = Vec::new();
+    assert_eq!(
+        vec![NamedValues { a: 1 }].write_nth_to_request(0, &mut request),
+        Err(SerializeValuesError::NamedValuesInBatch)
+    );
+
+    let mut map: HashMap<&str, i32> = HashMap::new();
+    map.insert("a", 1);
+    let batch_values = ((1_i32,), &map);
+    assert_eq!(
+        batch_values.write_nth_to_request(1, &mut request),
+        Err(SerializeValuesError::NamedValuesInBatch)
+    );
+    assert_eq!(batch_values.write_nth_to_request(0, &mut request), Ok(()));
+}
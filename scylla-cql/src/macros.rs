@@ -1,17 +1,181 @@
 /// #[derive(FromRow)] derives FromRow for struct
-/// Works only on simple structs without generics etc
 pub use scylla_macros::FromRow;
 
 /// #[derive(FromUserType)] allows to parse struct as a User Defined Type
-/// Works only on simple structs without generics etc
 pub use scylla_macros::FromUserType;
 
 /// #[derive(IntoUserType)] allows to pass struct a User Defined Type Value in queries
-/// Works only on simple structs without generics etc
 pub use scylla_macros::IntoUserType;
 
 /// #[derive(ValueList)] allows to pass struct as a list of values for a query
 pub use scylla_macros::ValueList;
 
+/// #[derive(CqlEnum)] allows to pass and receive an enum as a CQL text or int value
+pub use scylla_macros::CqlEnum;
+
+/// #[derive(CqlNewtype)] allows to pass and receive a single-field struct as the value of its field
+pub use scylla_macros::CqlNewtype;
+
+/// #[derive(Table)] maps a struct to a table and generates statements operating on its rows
+pub use scylla_macros::Table;
+
 // Reexports for derive(IntoUserType)
 pub use bytes::{BufMut, Bytes, BytesMut};
+
+/// Derive macros report invalid attributes as compile errors.
+///
+/// `rename` requires matching by name:
+/// ```compile_fail
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::FromRow;
+/// #[derive(FromRow)]
+/// struct Positional {
+///     #[scylla(rename = "b")]
+///     a: i32,
+/// }
+/// ```
+/// ```
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::FromRow;
+/// #[derive(FromRow)]
+/// #[scylla(match_by_name)]
+/// struct ByName {
+///     #[scylla(rename = "b")]
+///     a: i32,
+/// }
+/// ```
+/// ```compile_fail
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::ValueList;
+/// #[derive(ValueList)]
+/// struct Positional {
+///     #[scylla(rename = "b")]
+///     a: i32,
+/// }
+/// ```
+/// ```
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::ValueList;
+/// #[derive(ValueList)]
+/// #[scylla(named)]
+/// struct Named {
+///     #[scylla(rename = "b")]
+///     a: i32,
+/// }
+/// ```
+///
+/// Fields can't correspond to the same column:
+/// ```compile_fail
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::FromRow;
+/// #[derive(FromRow)]
+/// #[scylla(match_by_name)]
+/// struct Duplicated {
+///     a: i32,
+///     #[scylla(rename = "a")]
+///     b: i32,
+/// }
+/// ```
+///
+/// `flatten` is supported only by the derives of user defined types and named value lists:
+/// ```compile_fail
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::{FromRow, FromUserType};
+/// #[derive(FromRow, FromUserType)]
+/// struct Nested {
+///     b: i32,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct Flattened {
+///     #[scylla(flatten)]
+///     a: Nested,
+/// }
+/// ```
+/// ```compile_fail
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::ValueList;
+/// #[derive(ValueList)]
+/// struct Nested {
+///     b: i32,
+/// }
+///
+/// #[derive(ValueList)]
+/// struct Flattened {
+///     #[scylla(flatten)]
+///     a: Nested,
+/// }
+/// ```
+/// ```
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::{FromUserType, IntoUserType, ValueList};
+/// #[derive(FromUserType, IntoUserType)]
+/// struct NestedUdt {
+///     b: i32,
+/// }
+///
+/// #[derive(FromUserType, IntoUserType)]
+/// struct FlattenedUdt {
+///     #[scylla(flatten)]
+///     a: NestedUdt,
+/// }
+///
+/// #[derive(ValueList)]
+/// #[scylla(named)]
+/// struct NestedValues {
+///     b: i32,
+/// }
+///
+/// #[derive(ValueList)]
+/// #[scylla(named)]
+/// struct FlattenedValues {
+///     #[scylla(flatten)]
+///     a: NestedValues,
+/// }
+/// ```
+///
+/// The catch-all variant of `CqlEnum` has to hold a value of the type representing the enum:
+/// ```compile_fail
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::CqlEnum;
+/// #[derive(CqlEnum)]
+/// #[scylla(repr = "int")]
+/// enum Status {
+///     Active,
+///     Unknown(String),
+/// }
+/// ```
+/// ```
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::CqlEnum;
+/// #[derive(CqlEnum)]
+/// #[scylla(repr = "int")]
+/// enum Status {
+///     Active,
+///     Unknown(i32),
+/// }
+///
+/// #[derive(CqlEnum)]
+/// #[scylla(repr = "text")]
+/// enum Color {
+///     Red,
+///     Unknown(String),
+/// }
+/// ```
+///
+/// Newtypes are derived with `CqlNewtype`, not with the UDT derives:
+/// ```compile_fail
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::IntoUserType;
+/// #[derive(IntoUserType)]
+/// #[scylla(transparent)]
+/// struct UserId(i64);
+/// ```
+/// ```
+/// # use scylla_cql as scylla;
+/// # use scylla::macros::CqlNewtype;
+/// #[derive(CqlNewtype)]
+/// struct UserId(i64);
+/// ```
+#[cfg(doctest)]
+mod compile_errors {}
@@ -0,0 +1,297 @@
+use crate::parser::{parse_lit_str, scylla_attribute_items};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{
+    parse, Data, DeriveInput, Error, Expr, Fields, Ident, Lit, Meta, NestedMeta, Path, Type,
+};
+
+#[derive(PartialEq, Eq)]
+enum Repr {
+    Text,
+    Int,
+}
+
+/// A variant without fields, mapped to a single CQL value
+struct UnitVariant {
+    ident: Ident,
+    name: String,
+    discriminant: i32,
+}
+
+struct EnumInfo {
+    name: Ident,
+    crate_path: Path,
+    repr: Repr,
+    variants: Vec<UnitVariant>,
+    // Variant with a single field, which holds values not matching other variants
+    catch_all: Option<Ident>,
+}
+
+/// #[derive(CqlEnum)] allows to send and receive a Rust enum as a CQL text or int value
+pub fn cql_enum_derive(tokens_input: TokenStream) -> TokenStream {
+    let enum_info = match parse_enum(tokens_input) {
+        Ok(enum_info) => enum_info,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    let enum_name = &enum_info.name;
+    let enum_name_str = enum_name.to_string();
+    let path = &enum_info.crate_path;
+
+    let (repr_type, variant_values) = match enum_info.repr {
+        Repr::Text => (
+            quote! { ::std::string::String },
+            enum_info
+                .variants
+                .iter()
+                .map(|variant| {
+                    let name = &variant.name;
+                    quote! { #name }
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Repr::Int => (
+            quote! { i32 },
+            enum_info
+                .variants
+                .iter()
+                .map(|variant| {
+                    let discriminant = variant.discriminant;
+                    quote! { #discriminant }
+                })
+                .collect::<Vec<_>>(),
+        ),
+    };
+    let variant_idents: Vec<&Ident> = enum_info.variants.iter().map(|v| &v.ident).collect();
+
+    let (serialize_catch_all, parse_unknown) = match &enum_info.catch_all {
+        Some(catch_all) => (
+            quote! { #enum_name::#catch_all(value) => <_ as Value>::serialize(value, buf), },
+            quote! { value => Ok(#enum_name::#catch_all(value.into())), },
+        ),
+        None => (
+            quote! {},
+            quote! {
+                value => Err(FromCqlValError::UnknownEnumValue {
+                    enum_name: #enum_name_str,
+                    value: value.to_string(),
+                }),
+            },
+        ),
+    };
+
+    let parsed_value = match enum_info.repr {
+        Repr::Text => quote! { received.as_str() },
+        Repr::Int => quote! { received },
+    };
+
+    let generated = quote! {
+        impl #path::frame::value::Value for #enum_name {
+            fn serialize(&self, buf: &mut Vec<u8>) -> std::result::Result<(), #path::frame::value::ValueTooBig> {
+                use #path::frame::value::Value;
+
+                match self {
+                    #(#enum_name::#variant_idents => <_ as Value>::serialize(&#variant_values, buf),)*
+                    #serialize_catch_all
+                }
+            }
+
+            fn is_compatible_with(typ: &#path::frame::response::result::ColumnType) -> bool {
+                <#repr_type as #path::frame::value::Value>::is_compatible_with(typ)
+            }
+        }
+
+        impl #path::cql_to_rust::FromCqlVal<#path::frame::response::result::CqlValue> for #enum_name {
+            fn from_cql(cql_val: #path::frame::response::result::CqlValue)
+            -> Result<Self, #path::cql_to_rust::FromCqlValError> {
+                use #path::cql_to_rust::{FromCqlVal, FromCqlValError};
+                use #path::frame::response::result::CqlValue;
+
+                let received = <#repr_type as FromCqlVal<CqlValue>>::from_cql(cql_val)?;
+                match #parsed_value {
+                    #(#variant_values => Ok(#enum_name::#variant_idents),)*
+                    #parse_unknown
+                }
+            }
+
+            fn is_compatible_with(typ: &#path::frame::response::result::ColumnType) -> bool {
+                use #path::cql_to_rust::FromCqlVal;
+                use #path::frame::response::result::CqlValue;
+
+                <#repr_type as FromCqlVal<CqlValue>>::is_compatible_with(typ)
+            }
+        }
+    };
+
+    TokenStream::from(generated)
+}
+
+fn parse_enum(tokens_input: TokenStream) -> Result<EnumInfo, Error> {
+    let input = parse::<DeriveInput>(tokens_input)?;
+
+    let mut crate_path: Option<Path> = None;
+    let mut repr: Option<Repr> = None;
+    for item in scylla_attribute_items(&input.attrs)? {
+        match &item {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("crate") => {
+                crate_path = Some(parse_lit_str(&name_value.lit)?.parse()?);
+            }
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("repr") => {
+                let repr_str = parse_lit_str(&name_value.lit)?;
+                repr = Some(match repr_str.value().as_str() {
+                    "text" => Repr::Text,
+                    "int" => Repr::Int,
+                    _ => {
+                        return Err(Error::new(
+                            repr_str.span(),
+                            "expected `repr = \"text\"` or `repr = \"int\"`",
+                        ))
+                    }
+                });
+            }
+            _ => return Err(Error::new(item.span(), "unknown enum attribute")),
+        }
+    }
+    let repr = repr.ok_or_else(|| {
+        Error::new(
+            input.ident.span(),
+            "derive(CqlEnum) requires `#[scylla(repr = \"text\")]` or `#[scylla(repr = \"int\")]`",
+        )
+    })?;
+
+    let data = match input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "derive(CqlEnum) works only on enums!",
+            ))
+        }
+    };
+
+    let mut variants = Vec::new();
+    let mut catch_all: Option<Ident> = None;
+    let mut next_discriminant: i32 = 0;
+    for variant in data.variants {
+        match &variant.fields {
+            Fields::Unit => {}
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                if catch_all.is_some() {
+                    return Err(Error::new(
+                        variant.span(),
+                        "derive(CqlEnum) supports only one variant with a field",
+                    ));
+                }
+                check_catch_all_type(&fields.unnamed[0].ty, &repr)?;
+                catch_all = Some(variant.ident);
+                continue;
+            }
+            _ => {
+                return Err(Error::new(
+                    variant.span(),
+                    "derive(CqlEnum) supports only unit variants and a single \
+                    catch-all variant with one field, e.g. `Unknown(String)`",
+                ))
+            }
+        }
+
+        let mut name = variant.ident.unraw().to_string();
+        for item in scylla_attribute_items(&variant.attrs)? {
+            match &item {
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("rename") =>
+                {
+                    if repr != Repr::Text {
+                        return Err(Error::new(
+                            item.span(),
+                            "`rename` is supported only with `repr = \"text\"`, \
+                            use discriminants with `repr = \"int\"`",
+                        ));
+                    }
+                    name = parse_lit_str(&name_value.lit)?.value();
+                }
+                _ => return Err(Error::new(item.span(), "unknown variant attribute")),
+            }
+        }
+
+        let discriminant = match &variant.discriminant {
+            Some((_, expr)) => parse_discriminant(expr)?,
+            None => next_discriminant,
+        };
+        next_discriminant = discriminant.wrapping_add(1);
+
+        let is_duplicate = variants.iter().any(|other: &UnitVariant| match repr {
+            Repr::Text => other.name == name,
+            Repr::Int => other.discriminant == discriminant,
+        });
+        if is_duplicate {
+            return Err(Error::new(
+                variant.span(),
+                "the variant is mapped to the same value as another variant",
+            ));
+        }
+
+        variants.push(UnitVariant {
+            ident: variant.ident,
+            name,
+            discriminant,
+        });
+    }
+
+    Ok(EnumInfo {
+        name: input.ident,
+        crate_path: crate_path.unwrap_or_else(|| syn::parse_quote!(scylla)),
+        repr,
+        variants,
+        catch_all,
+    })
+}
+
+// The catch-all variant holds the received value, so its field has to be
+// of the type used to represent the enum in CQL
+fn check_catch_all_type(ty: &Type, repr: &Repr) -> Result<(), Error> {
+    let (expected, repr_name) = match repr {
+        Repr::Text => ("String", "text"),
+        Repr::Int => ("i32", "int"),
+    };
+    let is_expected = match ty {
+        Type::Path(type_path) => matches!(
+            type_path.path.segments.last(),
+            Some(segment) if segment.ident == expected && segment.arguments.is_empty()
+        ),
+        _ => false,
+    };
+    if is_expected {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ty.span(),
+            format!(
+                "the catch-all variant of an enum with `repr = \"{}\"` has to hold a `{}`",
+                repr_name, expected
+            ),
+        ))
+    }
+}
+
+// Discriminants have to be integer literals, possibly negated
+fn parse_discriminant(expr: &Expr) -> Result<i32, Error> {
+    match expr {
+        Expr::Lit(expr_lit) => match &expr_lit.lit {
+            Lit::Int(int) => int.base10_parse::<i32>(),
+            lit => Err(Error::new(lit.span(), "expected an integer discriminant")),
+        },
+        Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => parse_discriminant(expr)?
+            .checked_neg()
+            .ok_or_else(|| Error::new(expr.span(), "discriminant out of range")),
+        _ => Err(Error::new(
+            expr.span(),
+            "derive(CqlEnum) supports only integer literals as discriminants",
+        )),
+    }
+}
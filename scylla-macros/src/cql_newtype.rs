@@ -0,0 +1,59 @@
+use proc_macro::TokenStream;
+use quote::quote;
+
+/// #[derive(CqlNewtype)] allows to send and receive a struct with a single field
+/// as the value of this field
+pub fn cql_newtype_derive(tokens_input: TokenStream) -> TokenStream {
+    let struct_info = match crate::parser::parse_struct(tokens_input, "CqlNewtype") {
+        Ok(struct_info) => struct_info,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    let struct_name = &struct_info.name;
+    let path = &struct_info.crate_path;
+    let field_name = &struct_info.fields[0].member;
+    let field_type = &struct_info.fields[0].ty;
+
+    let (impl_generics, ty_generics, where_clause) =
+        struct_info.split_generics_with_bound(quote! { #path::frame::value::Value });
+    let value_impl = quote! {
+        impl #impl_generics #path::frame::value::Value for #struct_name #ty_generics #where_clause {
+            fn serialize(&self, buf: &mut Vec<u8>) -> std::result::Result<(), #path::frame::value::ValueTooBig> {
+                #path::frame::value::Value::serialize(&self.#field_name, buf)
+            }
+
+            fn is_compatible_with(typ: &#path::frame::response::result::ColumnType) -> bool {
+                <#field_type as #path::frame::value::Value>::is_compatible_with(typ)
+            }
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = struct_info.split_generics_with_bound(
+        quote! { #path::cql_to_rust::FromCqlVal<#path::frame::response::result::CqlValue> },
+    );
+    let from_cql_val_impl = quote! {
+        impl #impl_generics #path::cql_to_rust::FromCqlVal<#path::frame::response::result::CqlValue>
+        for #struct_name #ty_generics #where_clause {
+            fn from_cql(cql_val: #path::frame::response::result::CqlValue)
+            -> Result<Self, #path::cql_to_rust::FromCqlValError> {
+                use #path::cql_to_rust::FromCqlVal;
+                use #path::frame::response::result::CqlValue;
+
+                Ok(#struct_name {
+                    #field_name: <#field_type as FromCqlVal<CqlValue>>::from_cql(cql_val)?,
+                })
+            }
+
+            fn is_compatible_with(typ: &#path::frame::response::result::ColumnType) -> bool {
+                use #path::cql_to_rust::FromCqlVal;
+                use #path::frame::response::result::CqlValue;
+
+                <#field_type as FromCqlVal<CqlValue>>::is_compatible_with(typ)
+            }
+        }
+    };
+
+    TokenStream::from(quote! {
+        #value_impl
+        #from_cql_val_impl
+    })
+}
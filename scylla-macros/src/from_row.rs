@@ -1,19 +1,56 @@
+use crate::parser::StructInfo;
 use proc_macro::TokenStream;
-use quote::{quote, quote_spanned};
-use syn::spanned::Spanned;
+use quote::{format_ident, quote, quote_spanned};
 
 /// #[derive(FromRow)] derives FromRow for struct
-/// Works only on simple structs without generics etc
 pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
-    let (struct_name, struct_fields) =
-        crate::parser::parse_struct_with_named_fields(tokens_input, "FromRow");
+    let struct_info = match crate::parser::parse_struct(tokens_input, "FromRow") {
+        Ok(struct_info) => struct_info,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    TokenStream::from(generate_from_row(&struct_info))
+}
+
+/// Generates FromRow implementation for the struct
+pub(crate) fn generate_from_row(struct_info: &StructInfo) -> proc_macro2::TokenStream {
+    if struct_info.match_by_name {
+        return from_row_by_name(struct_info);
+    }
+
+    let struct_name = &struct_info.name;
+    let path = &struct_info.crate_path;
+    let (impl_generics, ty_generics, where_clause) = struct_info.split_generics_with_bound(
+        quote! { #path::cql_to_rust::FromCqlVal<#path::frame::response::result::CqlValue> },
+    );
 
     // Generates tokens for field_name: field_type::from_cql(vals_iter.next().ok_or(...)?), ...
-    let set_fields_code = struct_fields.named.iter().map(|field| {
-        let field_name = &field.ident;
+    let set_fields_code = struct_info.fields.iter().map(|field| {
+        let field_name = &field.member;
         let field_type = &field.ty;
 
-        quote_spanned! {field.span() =>
+        if field.skip {
+            return quote_spanned! {field.span =>
+                #field_name: ::std::default::Default::default(),
+            };
+        }
+
+        if field.default {
+            // The column might be missing at the end of the row
+            return quote_spanned! {field.span =>
+                #field_name: match vals_iter.next() {
+                    Some((col_ix, Some(col_value))) => {
+                        <#field_type as FromCqlVal<Option<CqlValue>>>::from_cql(Some(col_value))
+                            .map_err(|e| FromRowError::BadCqlVal {
+                                err: e,
+                                column: col_ix,
+                            })?
+                    }
+                    Some((_, None)) | None => ::std::default::Default::default(),
+                },
+            };
+        }
+
+        quote_spanned! {field.span =>
             #field_name: {
                 let (col_ix, col_value) = vals_iter
                     .next()
@@ -29,15 +66,44 @@ pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
         }
     });
 
-    let fields_count = struct_fields.named.len();
+    let fields_count = struct_info.used_fields().count();
+    // Trailing fields with #[scylla(default)] can be missing in the row
+    let required_fields_count = struct_info
+        .used_fields()
+        .enumerate()
+        .filter(|(_, field)| !field.default)
+        .map(|(ix, _)| ix + 1)
+        .last()
+        .unwrap_or(0);
+    let wrong_row_size_condition = |len: proc_macro2::TokenStream| {
+        if required_fields_count == fields_count {
+            quote! { #fields_count != #len }
+        } else if required_fields_count == 0 {
+            quote! { #len > #fields_count }
+        } else {
+            quote! { #len < #required_fields_count || #len > #fields_count }
+        }
+    };
+    let wrong_row_size = wrong_row_size_condition(quote! { row.columns.len() });
+    let wrong_col_specs_size = wrong_row_size_condition(quote! { col_specs.len() });
+
+    let type_check_code = struct_info.used_fields().map(|field| {
+        let field_type = &field.ty;
+
+        quote_spanned! {field.span =>
+            if let Some(col_spec) = col_specs_iter.next() {
+                check_column_type::<#field_type>(col_spec)?;
+            }
+        }
+    });
     let generated = quote! {
-        impl scylla::cql_to_rust::FromRow for #struct_name {
-            fn from_row(row: scylla::frame::response::result::Row)
-            -> Result<Self, scylla::cql_to_rust::FromRowError> {
-                use scylla::frame::response::result::CqlValue;
-                use scylla::cql_to_rust::{FromCqlVal, FromRow, FromRowError};
+        impl #impl_generics #path::cql_to_rust::FromRow for #struct_name #ty_generics #where_clause {
+            fn from_row(row: #path::frame::response::result::Row)
+            -> Result<Self, #path::cql_to_rust::FromRowError> {
+                use #path::frame::response::result::CqlValue;
+                use #path::cql_to_rust::{FromCqlVal, FromRow, FromRowError};
 
-                if #fields_count != row.columns.len() {
+                if #wrong_row_size {
                     return Err(FromRowError::WrongRowSize {
                         expected: #fields_count,
                         actual: row.columns.len(),
@@ -49,8 +115,179 @@ pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
                     #(#set_fields_code)*
                 })
             }
+
+            fn type_check(col_specs: &[#path::frame::response::result::ColumnSpec])
+            -> Result<(), #path::frame::value::TypeCheckError> {
+                use #path::cql_to_rust::check_column_type;
+                use #path::frame::value::TypeCheckError;
+
+                if #wrong_col_specs_size {
+                    return Err(TypeCheckError::WrongRowSize {
+                        expected: #fields_count,
+                        actual: col_specs.len(),
+                    });
+                }
+                let mut col_specs_iter = col_specs.iter();
+
+                #(#type_check_code)*
+                Ok(())
+            }
         }
     };
 
-    TokenStream::from(generated)
+    generated
+}
+
+/// Generates FromRow implementation which uses column specifications
+/// to find the column corresponding to each field
+fn from_row_by_name(struct_info: &StructInfo) -> proc_macro2::TokenStream {
+    let struct_name = &struct_info.name;
+    let path = &struct_info.crate_path;
+    let (impl_generics, ty_generics, where_clause) = struct_info.split_generics_with_bound(
+        quote! { #path::cql_to_rust::FromCqlVal<#path::frame::response::result::CqlValue> },
+    );
+
+    // Variables holding the column index and value found for each used field
+    let value_var = |field_ix: usize| format_ident!("__field_{}_value", field_ix);
+    let used_fields_with_ix = || {
+        struct_info
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.is_used())
+    };
+
+    let declare_values_code = used_fields_with_ix().map(|(field_ix, _)| {
+        let var = value_var(field_ix);
+        quote! {
+            let mut #var: Option<(usize, Option<CqlValue>)> = None;
+        }
+    });
+
+    let match_columns_code = used_fields_with_ix().map(|(field_ix, field)| {
+        let var = value_var(field_ix);
+        let cql_name = &field.cql_name;
+        quote! {
+            #cql_name => #var = Some((col_ix, col_value)),
+        }
+    });
+
+    let set_fields_code = struct_info
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(field_ix, field)| {
+            let field_name = &field.member;
+            let field_type = &field.ty;
+            let cql_name = &field.cql_name;
+
+            if field.skip {
+                return quote_spanned! {field.span =>
+                    #field_name: ::std::default::Default::default(),
+                };
+            }
+
+            let var = value_var(field_ix);
+            let missing_value_code = if field.default {
+                quote! { ::std::default::Default::default() }
+            } else {
+                quote! {
+                    return Err(FromRowError::MissingColumn {
+                        column: #cql_name.to_string(),
+                    })
+                }
+            };
+            let null_value_code = if field.default {
+                quote! { Some((_, None)) => ::std::default::Default::default(), }
+            } else {
+                quote! {}
+            };
+
+            quote_spanned! {field.span =>
+                #field_name: match #var {
+                    #null_value_code
+                    Some((col_ix, col_value)) => {
+                        <#field_type as FromCqlVal<Option<CqlValue>>>::from_cql(col_value)
+                            .map_err(|e| FromRowError::BadCqlVal {
+                                err: e,
+                                column: col_ix,
+                            })?
+                    }
+                    None => #missing_value_code,
+                },
+            }
+        });
+
+    let type_check_code = struct_info.used_fields().map(|field| {
+        let field_type = &field.ty;
+        let cql_name = &field.cql_name;
+        let missing_column_code = if field.default {
+            quote! {}
+        } else {
+            quote! {
+                return Err(TypeCheckError::MissingColumn {
+                    column: #cql_name.to_string(),
+                });
+            }
+        };
+
+        quote_spanned! {field.span =>
+            match col_specs.iter().find(|col_spec| col_spec.name == #cql_name) {
+                Some(col_spec) => check_column_type::<#field_type>(col_spec)?,
+                None => { #missing_column_code }
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics #path::cql_to_rust::FromRow for #struct_name #ty_generics #where_clause {
+            fn from_row(row: #path::frame::response::result::Row)
+            -> Result<Self, #path::cql_to_rust::FromRowError> {
+                Self::from_row_with_specs(row, &[])
+            }
+
+            fn type_check(col_specs: &[#path::frame::response::result::ColumnSpec])
+            -> Result<(), #path::frame::value::TypeCheckError> {
+                use #path::cql_to_rust::check_column_type;
+                use #path::frame::value::TypeCheckError;
+
+                #(#type_check_code)*
+                Ok(())
+            }
+
+            fn from_row_with_specs(
+                row: #path::frame::response::result::Row,
+                col_specs: &[#path::frame::response::result::ColumnSpec],
+            ) -> Result<Self, #path::cql_to_rust::FromRowError> {
+                use #path::frame::response::result::CqlValue;
+                use #path::cql_to_rust::{FromCqlVal, FromRow, FromRowError};
+
+                if col_specs.len() != row.columns.len() {
+                    if col_specs.is_empty() {
+                        return Err(FromRowError::ColumnSpecsRequired);
+                    }
+                    return Err(FromRowError::WrongRowSize {
+                        expected: col_specs.len(),
+                        actual: row.columns.len(),
+                    });
+                }
+
+                #(#declare_values_code)*
+
+                // Columns which don't correspond to any field are ignored
+                for (col_ix, (col_spec, col_value)) in
+                    col_specs.iter().zip(row.columns.into_iter()).enumerate()
+                {
+                    match col_spec.name.as_str() {
+                        #(#match_columns_code)*
+                        _ => {}
+                    }
+                }
+
+                Ok(#struct_name {
+                    #(#set_fields_code)*
+                })
+            }
+        }
+    }
 }
@@ -1,52 +1,137 @@
 use proc_macro::TokenStream;
 use quote::{quote, quote_spanned};
-use syn::spanned::Spanned;
 
 /// #[derive(FromUserType)] allows to parse a struct as User Defined Type
-/// Works only on simple structs without generics etc
 pub fn from_user_type_derive(tokens_input: TokenStream) -> TokenStream {
-    let (struct_name, struct_fields) =
-        crate::parser::parse_struct_with_named_fields(tokens_input, "FromUserType");
+    let struct_info = match crate::parser::parse_struct(tokens_input, "FromUserType") {
+        Ok(struct_info) => struct_info,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    let struct_name = &struct_info.name;
+    let path = &struct_info.crate_path;
+    let (impl_generics, ty_generics, where_clause) = struct_info.split_generics_with_bound(
+        quote! { #path::cql_to_rust::FromCqlVal<#path::frame::response::result::CqlValue> },
+    );
 
     // Generates tokens for field_name: field_type::from_cql(fields.remove(stringify!(#field_name)).unwrap_or(None)) ?, ...
-    let set_fields_code = struct_fields.named.iter().map(|field| {
-        let field_name = &field.ident;
+    let set_fields_code = struct_info.fields.iter().map(|field| {
+        let field_name = &field.member;
         let field_type = &field.ty;
+        let cql_name = &field.cql_name;
+
+        if field.skip {
+            return quote_spanned! {field.span =>
+                #field_name: ::std::default::Default::default(),
+            };
+        }
+
+        if field.flatten {
+            return quote_spanned! {field.span =>
+                #field_name: <#field_type as #path::cql_to_rust::FromUserTypeFields>::from_udt_fields(fields_iter) ?,
+            };
+        }
 
-        quote_spanned! {field.span() =>
-            #field_name: <#field_type as FromCqlVal<Option<CqlValue>>>::from_cql(
-                {
-                    let received_field_name: Option<&String> = fields_iter
-                        .peek()
-                        .map(|(ref name, _)| name);
-
-                    // Order of received fields is the same as the order of processed struct's
-                    // fields. There cannot be an extra received field present, so it is safe to
-                    // assign subsequent received fields, to processed struct's fields (inserting
-                    // None if there is no received field corresponding to processed struct's
-                    // field)
-                    if let Some(received_field_name) = received_field_name {
-                        if received_field_name == stringify!(#field_name) {
-                            let (_, value) = fields_iter.next().unwrap();
-                            value
-                        } else {
-                            None
-                        }
+        let received_value = quote_spanned! {field.span =>
+            {
+                let received_field_name: Option<&String> = fields_iter
+                    .peek()
+                    .map(|(ref name, _)| name);
+
+                // Order of received fields is the same as the order of processed struct's
+                // fields. There cannot be an extra received field present, so it is safe to
+                // assign subsequent received fields, to processed struct's fields (inserting
+                // None if there is no received field corresponding to processed struct's
+                // field)
+                if let Some(received_field_name) = received_field_name {
+                    if received_field_name == #cql_name {
+                        let (_, value) = fields_iter.next().unwrap();
+                        value
                     } else {
                         None
                     }
+                } else {
+                    None
+                }
+            }
+        };
+
+        if field.default {
+            return quote_spanned! {field.span =>
+                #field_name: match #received_value {
+                    Some(value) => <#field_type as FromCqlVal<Option<CqlValue>>>::from_cql(Some(value)) ?,
+                    None => ::std::default::Default::default(),
+                },
+            };
+        }
+
+        quote_spanned! {field.span =>
+            #field_name: <#field_type as FromCqlVal<Option<CqlValue>>>::from_cql(#received_value) ?,
+        }
+    });
+
+    // Checks fields of the type definition the same way received fields are processed
+    let type_check_code = struct_info.used_fields().map(|field| {
+        let field_type = &field.ty;
+        let cql_name = &field.cql_name;
+
+        if field.flatten {
+            return quote_spanned! {field.span =>
+                if !<#field_type as #path::cql_to_rust::FromUserTypeFields>::udt_fields_compatible_with(field_types_iter) {
+                    return false;
+                }
+            };
+        }
+        let missing_field_code = if field.default {
+            quote! {}
+        } else {
+            quote! { return false; }
+        };
+
+        quote_spanned! {field.span =>
+            match field_types_iter.peek() {
+                Some((name, field_type)) if name == #cql_name => {
+                    if !<#field_type as FromCqlVal<Option<CqlValue>>>::is_compatible_with(field_type) {
+                        return false;
+                    }
+                    field_types_iter.next();
                 }
-            ) ?,
+                _ => { #missing_field_code }
+            }
         }
     });
 
+    let udt_name_check = struct_info.udt_name_check();
+
     let generated = quote! {
-        impl FromCqlVal<scylla::frame::response::result::CqlValue> for #struct_name {
-            fn from_cql(cql_val: scylla::frame::response::result::CqlValue)
-            -> Result<Self, scylla::cql_to_rust::FromCqlValError> {
-                use std::collections::BTreeMap;
-                use scylla::cql_to_rust::{FromCqlVal, FromCqlValError};
-                use scylla::frame::response::result::CqlValue;
+        impl #impl_generics #path::cql_to_rust::FromUserTypeFields for #struct_name #ty_generics #where_clause {
+            fn from_udt_fields(
+                fields_iter: &mut ::std::iter::Peekable<::std::vec::IntoIter<(String, Option<#path::frame::response::result::CqlValue>)>>,
+            ) -> Result<Self, #path::cql_to_rust::FromCqlValError> {
+                use #path::cql_to_rust::FromCqlVal;
+                use #path::frame::response::result::CqlValue;
+
+                Ok(#struct_name {
+                    #(#set_fields_code)*
+                })
+            }
+
+            fn udt_fields_compatible_with(
+                field_types_iter: &mut ::std::iter::Peekable<::std::slice::Iter<'_, (String, #path::frame::response::result::ColumnType)>>,
+            ) -> bool {
+                use #path::cql_to_rust::FromCqlVal;
+                use #path::frame::response::result::CqlValue;
+
+                #(#type_check_code)*
+                true
+            }
+        }
+
+        impl #impl_generics #path::cql_to_rust::FromCqlVal<#path::frame::response::result::CqlValue>
+        for #struct_name #ty_generics #where_clause {
+            fn from_cql(cql_val: #path::frame::response::result::CqlValue)
+            -> Result<Self, #path::cql_to_rust::FromCqlValError> {
+                use #path::cql_to_rust::{FromCqlValError, FromUserTypeFields};
+                use #path::frame::response::result::CqlValue;
 
                 // Interpret CqlValue as CQlValue::UserDefinedType
                 let mut fields_iter = match cql_val {
@@ -55,9 +140,7 @@ pub fn from_user_type_derive(tokens_input: TokenStream) -> TokenStream {
                 };
 
                 // Parse struct using values from fields
-                let result = #struct_name {
-                    #(#set_fields_code)*
-                };
+                let result = <Self as FromUserTypeFields>::from_udt_fields(&mut fields_iter)?;
 
                 // There should be no unused fields when reading user defined type
                 if fields_iter.next().is_some() {
@@ -66,6 +149,23 @@ pub fn from_user_type_derive(tokens_input: TokenStream) -> TokenStream {
 
                 return Ok(result);
             }
+
+            fn is_compatible_with(typ: &#path::frame::response::result::ColumnType) -> bool {
+                use #path::cql_to_rust::FromUserTypeFields;
+                use #path::frame::response::result::ColumnType;
+
+                let mut field_types_iter = match typ {
+                    ColumnType::UserDefinedType { field_types, keyspace, type_name } => {
+                        #udt_name_check
+                        field_types.iter().peekable()
+                    }
+                    _ => return false,
+                };
+
+                // Every field of the type has to be read
+                <Self as FromUserTypeFields>::udt_fields_compatible_with(&mut field_types_iter)
+                    && field_types_iter.next().is_none()
+            }
         }
     };
 
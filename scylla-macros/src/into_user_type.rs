@@ -1,26 +1,113 @@
 use proc_macro::TokenStream;
 use quote::{quote, quote_spanned};
-use syn::spanned::Spanned;
 
 /// #[derive(IntoUserType)] allows to parse a struct as User Defined Type
-/// Works only on simple structs without generics etc
 pub fn into_user_type_derive(tokens_input: TokenStream) -> TokenStream {
-    let (struct_name, struct_fields) =
-        crate::parser::parse_struct_with_named_fields(tokens_input, "IntoUserType");
+    let struct_info = match crate::parser::parse_struct(tokens_input, "IntoUserType") {
+        Ok(struct_info) => struct_info,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+    let struct_name = &struct_info.name;
+    let path = &struct_info.crate_path;
+    let (impl_generics, ty_generics, where_clause) =
+        struct_info.split_generics_with_bound(quote! { #path::frame::value::Value });
+
+    let serialize_code = struct_info.used_fields().map(|field| {
+        let field_name = &field.member;
+
+        if field.flatten {
+            return quote_spanned! {field.span =>
+                <_ as #path::frame::value::SerializeUserTypeFields>::serialize_udt_fields(&self.#field_name, buf) ?;
+            };
+        }
+
+        quote_spanned! {field.span =>
+            <_ as #path::frame::value::Value>::serialize(&self.#field_name, buf) ?;
+        }
+    });
+
+    // Fields are sent in the order of the struct, which has to match the type definition.
+    // Trailing fields of the type can be missing, they are null then.
+    let type_check_code = struct_info.used_fields().map(|field| {
+        let field_type = &field.ty;
+        let cql_name = &field.cql_name;
+
+        if field.flatten {
+            return quote_spanned! {field.span =>
+                if !<#field_type as #path::frame::value::SerializeUserTypeFields>::udt_fields_compatible_with(field_types_iter) {
+                    return false;
+                }
+            };
+        }
+
+        quote_spanned! {field.span =>
+            match field_types_iter.next() {
+                Some((name, field_type)) if name == #cql_name => {
+                    if !<#field_type as #path::frame::value::Value>::is_compatible_with(field_type) {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+        }
+    });
 
-    let serialize_code = struct_fields.named.iter().map(|field| {
-        let field_name = &field.ident;
+    // Names of flattened structs' fields are known only by their implementations
+    let add_field_names_code = struct_info.used_fields().map(|field| {
+        let field_name = &field.member;
+        let cql_name = &field.cql_name;
 
-        quote_spanned! {field.span() =>
-            <_ as Value>::serialize(&self.#field_name, buf) ?;
+        if field.flatten {
+            quote_spanned! {field.span =>
+                names.extend(<_ as #path::frame::value::UserTypeFields>::field_names(&self.#field_name));
+            }
+        } else {
+            quote_spanned! {field.span =>
+                names.push(#cql_name);
+            }
+        }
+    });
+
+    let udt_name_check = struct_info.udt_name_check();
+
+    let serialize_field_code = struct_info.used_fields().map(|field| {
+        let field_name = &field.member;
+        let cql_name = &field.cql_name;
+
+        if field.flatten {
+            return quote_spanned! {field.span =>
+                if <_ as #path::frame::value::UserTypeFields>::serialize_field(&self.#field_name, name, buf) ? {
+                    return Ok(true);
+                }
+            };
+        }
+
+        quote_spanned! {field.span =>
+            if name == #cql_name {
+                return <_ as #path::frame::value::Value>::serialize(&self.#field_name, buf).map(|()| true);
+            }
         }
     });
 
     let generated = quote! {
-        impl scylla::frame::value::Value for #struct_name {
-            fn serialize(&self, buf: &mut Vec<u8>) -> std::result::Result<(), scylla::frame::value::ValueTooBig> {
-                use scylla::frame::value::{Value, ValueTooBig};
-                use scylla::macros::BufMut;
+        impl #impl_generics #path::frame::value::SerializeUserTypeFields for #struct_name #ty_generics #where_clause {
+            fn serialize_udt_fields(&self, buf: &mut Vec<u8>) -> std::result::Result<(), #path::frame::value::ValueTooBig> {
+                #(#serialize_code)*
+                Ok(())
+            }
+
+            fn udt_fields_compatible_with(
+                field_types_iter: &mut ::std::slice::Iter<'_, (String, #path::frame::response::result::ColumnType)>,
+            ) -> bool {
+                #(#type_check_code)*
+                true
+            }
+        }
+
+        impl #impl_generics #path::frame::value::Value for #struct_name #ty_generics #where_clause {
+            fn serialize(&self, buf: &mut Vec<u8>) -> std::result::Result<(), #path::frame::value::ValueTooBig> {
+                use #path::frame::value::{SerializeUserTypeFields, ValueTooBig};
+                use #path::macros::BufMut;
                 use ::std::convert::TryInto;
 
 
@@ -31,7 +118,7 @@ pub fn into_user_type_derive(tokens_input: TokenStream) -> TokenStream {
                 let len_before_serialize = buf.len();
 
                 // Serialize fields
-                #(#serialize_code)*
+                <Self as SerializeUserTypeFields>::serialize_udt_fields(self, buf) ?;
 
                 // Put serialized size in its place
                 let total_size : usize = buf.len() - len_before_serialize;
@@ -40,6 +127,39 @@ pub fn into_user_type_derive(tokens_input: TokenStream) -> TokenStream {
 
                 Ok(())
             }
+
+            fn is_compatible_with(typ: &#path::frame::response::result::ColumnType) -> bool {
+                use #path::frame::value::SerializeUserTypeFields;
+                use #path::frame::response::result::ColumnType;
+
+                let mut field_types_iter = match typ {
+                    ColumnType::UserDefinedType { field_types, keyspace, type_name } => {
+                        #udt_name_check
+                        field_types.iter()
+                    }
+                    _ => return false,
+                };
+
+                <Self as SerializeUserTypeFields>::udt_fields_compatible_with(&mut field_types_iter)
+            }
+        }
+
+        impl #impl_generics #path::frame::value::UserTypeFields for #struct_name #ty_generics #where_clause {
+            fn field_names(&self) -> Vec<&str> {
+                let mut names: Vec<&str> = Vec::new();
+                #(#add_field_names_code)*
+                names
+            }
+
+            fn is_user_type(keyspace: &str, type_name: &str) -> bool {
+                #udt_name_check
+                true
+            }
+
+            fn serialize_field(&self, name: &str, buf: &mut Vec<u8>) -> std::result::Result<bool, #path::frame::value::ValueTooBig> {
+                #(#serialize_field_code)*
+                Ok(false)
+            }
         }
     };
 
@@ -1,35 +1,129 @@
 use proc_macro::TokenStream;
 
+mod cql_enum;
+mod cql_newtype;
 mod from_row;
 mod from_user_type;
 mod into_user_type;
 mod parser;
+mod table;
 mod value_list;
 
 /// #[derive(FromRow)] derives FromRow for struct
-/// Works only on simple structs without generics etc
-#[proc_macro_derive(FromRow)]
+/// Generic structs are supported, their type parameters are required to implement `FromCqlVal`
+///
+/// Supported attributes:
+/// * `#[scylla(crate = "path")]` on the struct - path to the `scylla` crate, if it's re-exported
+/// * `#[scylla(match_by_name)]` on the struct - columns are matched with fields by their names
+///   instead of positions. Columns which don't correspond to any field are ignored.
+///   It requires specifications of the columns, which are passed by `QueryResult::rows_typed`
+///   and similar methods, but not by `rows.into_typed()` or `FromRow::from_row` -
+///   those fail with `FromRowError::ColumnSpecsRequired`.
+/// * `#[scylla(skip)]` on a field - the field doesn't correspond to any column
+///   and is set to `Default::default()`
+/// * `#[scylla(default)]` on a field - null or missing column is read as `Default::default()`.
+///   Unless matching by name, columns can be missing only at the end of the row.
+/// * `#[scylla(rename = "name")]` on a field - name of the corresponding column,
+///   allowed only when matching by name
+#[proc_macro_derive(FromRow, attributes(scylla))]
 pub fn from_row_derive(tokens_input: TokenStream) -> TokenStream {
     from_row::from_row_derive(tokens_input)
 }
 
 /// #[derive(FromUserType)] allows to parse a struct as User Defined Type
-/// Works only on simple structs without generics etc
-#[proc_macro_derive(FromUserType)]
+///
+/// Supports the same field attributes as `#[derive(FromRow)]`,
+/// `#[scylla(rename = "name")]` sets the name of the corresponding UDT field.\
+/// `#[scylla(flatten)]` on a field reads consecutive UDT fields into a nested struct
+/// deriving `FromUserType`, as if its fields were declared in place of the field.\
+/// With `#[scylla(udt = "name")]` and `#[scylla(keyspace = "name")]` on the struct
+/// the type check accepts only the user defined type with the given name and keyspace.
+#[proc_macro_derive(FromUserType, attributes(scylla))]
 pub fn from_user_type_derive(tokens_input: TokenStream) -> TokenStream {
     from_user_type::from_user_type_derive(tokens_input)
 }
 
 /// #[derive(IntoUserType)] allows to parse a struct as User Defined Type
-/// Works only on simple structs without generics etc
-#[proc_macro_derive(IntoUserType)]
+///
+/// Supports `#[scylla(crate = "path")]` on the struct and `#[scylla(skip)]` on fields.
+///
+/// The generated `Value` implementation is positional: fields are written in the order
+/// of the struct, which has to match the order of the type definition.
+/// The type check verifies the order - executing a prepared statement fails if it doesn't match,
+/// but values sent in unprepared queries and batches are not checked.\
+/// The derive also implements `UserTypeFields`, which allows to send the struct with fields matched
+/// by name with the type definition using `UdtByName`. `#[scylla(rename = "name")]`
+/// sets the name of the corresponding UDT field.
+/// `#[scylla(flatten)]` on a field writes the fields of a nested struct deriving `IntoUserType`
+/// in place of the field.
+///
+/// With `#[scylla(udt = "name")]` and `#[scylla(keyspace = "name")]` on the struct
+/// the type check and `UdtByName` accept only the user defined type with the given name and keyspace.
+#[proc_macro_derive(IntoUserType, attributes(scylla))]
 pub fn into_user_type_derive(tokens_input: TokenStream) -> TokenStream {
     into_user_type::into_user_type_derive(tokens_input)
 }
 
 /// #[derive(ValueList)] derives ValueList for struct
-/// Works only on simple structs without generics etc
-#[proc_macro_derive(ValueList)]
+///
+/// Supports `#[scylla(crate = "path")]` on the struct and `#[scylla(skip)]` on fields.\
+/// With `#[scylla(named)]` on the struct values are serialized together with names of the fields,
+/// which can be changed with `#[scylla(rename = "name")]`, and bound to markers by names.
+/// `#[scylla(flatten)]` on a field adds the values of a nested struct deriving named `ValueList`.
+/// Without `named` values are bound by position and `rename` and `flatten` are compile errors.
+/// With `#[scylla(none_as_unset)]` on the struct `None` values are serialized as unset instead of null.
+#[proc_macro_derive(ValueList, attributes(scylla))]
 pub fn value_list_derive(tokens_input: TokenStream) -> TokenStream {
     value_list::value_list_derive(tokens_input)
 }
+
+/// #[derive(CqlEnum)] allows to send and receive an enum as a CQL `text` or `int` value
+///
+/// Supported attributes:
+/// * `#[scylla(repr = "text")]` or `#[scylla(repr = "int")]` on the enum (required) -
+///   variants are represented by their names or discriminants
+/// * `#[scylla(crate = "path")]` on the enum - path to the `scylla` crate, if it's re-exported
+/// * `#[scylla(rename = "name")]` on a variant - text representing the variant
+///
+/// Only unit variants are supported, except for an optional catch-all variant
+/// with a single field, which holds values that don't match any other variant.
+/// The field has to be a `String` with `repr = "text"` (e.g. `Unknown(String)`)
+/// and an `i32` with `repr = "int"` (e.g. `Unknown(i32)`).
+#[proc_macro_derive(CqlEnum, attributes(scylla))]
+pub fn cql_enum_derive(tokens_input: TokenStream) -> TokenStream {
+    cql_enum::cql_enum_derive(tokens_input)
+}
+
+/// #[derive(CqlNewtype)] allows to send and receive a struct with a single field,
+/// e.g. `struct UserId(Uuid)`, exactly as the value of this field.
+/// Implements `Value` and `FromCqlVal` delegating to the field.
+///
+/// Generic structs are supported, their type parameters are required to implement
+/// `Value` and `FromCqlVal`. `#[scylla(crate = "path")]` on the struct sets
+/// the path to the `scylla` crate, if it's re-exported.
+#[proc_macro_derive(CqlNewtype, attributes(scylla))]
+pub fn cql_newtype_derive(tokens_input: TokenStream) -> TokenStream {
+    cql_newtype::cql_newtype_derive(tokens_input)
+}
+
+/// #[derive(Table)] maps a struct to a table, generating statements which insert, select,
+/// update and delete its rows. Implements `Table`, `ValueList` and `FromRow` for the struct.
+///
+/// Supported attributes:
+/// * `#[scylla(table = "name")]` on the struct (required) - name of the table
+/// * `#[scylla(keyspace = "name")]` on the struct - keyspace of the table,
+///   without it the keyspace used by the session applies
+/// * `#[scylla(crate = "path")]` on the struct - path to the `scylla` crate, if it's re-exported
+/// * `#[scylla(none_as_unset)]` on the struct - `None` values are written as unset instead of null,
+///   so inserts and updates don't overwrite existing values with nulls
+/// * `#[partition_key]` on a field (at least one is required) - the column is a part of the partition key
+/// * `#[clustering_key]` on a field - the column is a part of the clustering key
+/// * `#[scylla(rename = "name")]` on a field - name of the corresponding column
+/// * `#[scylla(skip)]` on a field - the field doesn't correspond to any column
+///
+/// Key columns are used in the order of fields.
+/// Names of the keyspace, the table and the columns are quoted in the statements, so they are case sensitive.
+#[proc_macro_derive(Table, attributes(scylla, partition_key, clustering_key))]
+pub fn table_derive(tokens_input: TokenStream) -> TokenStream {
+    table::table_derive(tokens_input)
+}
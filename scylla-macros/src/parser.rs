@@ -1,24 +1,440 @@
 use proc_macro::TokenStream;
-use syn::{parse, Data, DeriveInput, Fields, FieldsNamed, Ident};
+use proc_macro2::Span;
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{
+    parse, Attribute, Data, DeriveInput, Error, Fields, Generics, Ident, ImplGenerics, Lit, Member,
+    Meta, NestedMeta, Path, Type, TypeGenerics, WhereClause,
+};
 
-/// Parses the tokens_input to a DeriveInput and returns the struct name from which it derives and
-/// the named fields
-pub(crate) fn parse_struct_with_named_fields(
+/// Information about a struct, gathered from the derive input
+pub(crate) struct StructInfo {
+    pub name: Ident,
+    pub generics: Generics,
+    /// Path to the `scylla` crate, set with `#[scylla(crate = "...")]`
+    pub crate_path: Path,
+    /// Values are matched with fields by name, `#[scylla(match_by_name)]`
+    pub match_by_name: bool,
+    /// Values are serialized together with their names, `#[scylla(named)]`
+    pub named: bool,
+    /// `None` values are serialized as unset instead of null, `#[scylla(none_as_unset)]`
+    pub none_as_unset: bool,
+    /// Keyspace of the table or the user defined type mapped to the struct,
+    /// `#[scylla(keyspace = "...")]`
+    pub keyspace: Option<String>,
+    /// User defined type mapped to the struct, `#[scylla(udt = "...")]`
+    pub udt: Option<String>,
+    /// Table mapped to the struct, `#[scylla(table = "...")]`
+    pub table: Option<String>,
+    pub fields: Vec<FieldInfo>,
+}
+
+/// Information about a single field together with its `#[scylla(...)]` attributes
+pub(crate) struct FieldInfo {
+    /// Field name, or index in case of tuple structs
+    pub member: Member,
+    pub ty: Type,
+    pub span: Span,
+    /// Name of the corresponding CQL column or UDT field,
+    /// set with `#[scylla(rename = "...")]`
+    pub cql_name: String,
+    /// The name was set with `#[scylla(rename = "...")]`
+    pub renamed: bool,
+    /// The field is not read nor written, `#[scylla(skip)]`
+    pub skip: bool,
+    /// Missing or null value is replaced with `Default::default()`, `#[scylla(default)]`
+    pub default: bool,
+    /// Fields of the nested struct are included in place of the field, `#[scylla(flatten)]`
+    pub flatten: bool,
+    /// The column is a part of the partition key, `#[partition_key]`
+    pub partition_key: bool,
+    /// The column is a part of the clustering key, `#[clustering_key]`
+    pub clustering_key: bool,
+}
+
+impl FieldInfo {
+    /// Fields which are not skipped correspond to CQL values
+    pub fn is_used(&self) -> bool {
+        !self.skip
+    }
+}
+
+impl StructInfo {
+    /// Generates code checking that variables `keyspace` and `type_name` match
+    /// the user defined type set with `#[scylla(keyspace = "...", udt = "...")]`,
+    /// which returns false otherwise
+    pub fn udt_name_check(&self) -> proc_macro2::TokenStream {
+        let keyspace_check = self.keyspace.as_ref().map(|keyspace| {
+            quote::quote! {
+                if keyspace != #keyspace {
+                    return false;
+                }
+            }
+        });
+        let type_name_check = self.udt.as_ref().map(|udt| {
+            quote::quote! {
+                if type_name != #udt {
+                    return false;
+                }
+            }
+        });
+        quote::quote! {
+            let _ = (keyspace, type_name);
+            #keyspace_check
+            #type_name_check
+        }
+    }
+
+    pub fn used_fields(&self) -> impl Iterator<Item = &FieldInfo> {
+        self.fields.iter().filter(|field| field.is_used())
+    }
+
+    /// Splits generics of the struct for use in the generated impl,
+    /// requiring every type parameter to implement `bound`
+    pub fn split_generics_with_bound(
+        &self,
+        bound: proc_macro2::TokenStream,
+    ) -> (ImplGenerics<'_>, TypeGenerics<'_>, Option<WhereClause>) {
+        let mut where_clause = self.generics.clone().make_where_clause().clone();
+        for param in self.generics.type_params() {
+            let param_ident = &param.ident;
+            where_clause
+                .predicates
+                .push(syn::parse_quote!(#param_ident: #bound));
+        }
+        let where_clause = (!where_clause.predicates.is_empty()).then(|| where_clause);
+
+        let (impl_generics, ty_generics, _) = self.generics.split_for_impl();
+        (impl_generics, ty_generics, where_clause)
+    }
+}
+
+/// Parses the tokens_input to a DeriveInput and returns the struct name from which it derives,
+/// its generics, the path to the scylla crate and the fields along with their attributes.
+/// Fields have to be named, unless the struct is derived as `CqlNewtype`.
+pub(crate) fn parse_struct(
     tokens_input: TokenStream,
     current_derive: &str,
-) -> (Ident, FieldsNamed) {
-    let input = parse::<DeriveInput>(tokens_input).expect("No DeriveInput");
+) -> Result<StructInfo, Error> {
+    let input = parse::<DeriveInput>(tokens_input)?;
+    let attrs = parse_struct_attributes(&input.attrs, current_derive)?;
+    let transparent = current_derive == "CqlNewtype";
     let struct_name = input.ident;
     let struct_fields = match input.data {
         Data::Struct(data) => match data.fields {
-            Fields::Named(named_fields) => named_fields,
-            _ => panic!(
-                "derive({}) works only for structs with named fields. Tuples don't need derive.",
-                current_derive
-            ),
+            Fields::Named(named_fields) => named_fields.named,
+            Fields::Unnamed(unnamed_fields) if transparent => unnamed_fields.unnamed,
+            fields => {
+                return Err(Error::new(
+                    fields.span(),
+                    format!(
+                        "derive({}) works only for structs with named fields. Tuples don't need derive.",
+                        current_derive
+                    ),
+                ))
+            }
         },
-        _ => panic!("derive({}) works only on structs!", current_derive),
+        _ => {
+            return Err(Error::new(
+                struct_name.span(),
+                format!("derive({}) works only on structs!", current_derive),
+            ))
+        }
     };
 
-    (struct_name, struct_fields)
+    let fields = struct_fields
+        .into_iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let span = field.span();
+            let (member, cql_name) = match field.ident {
+                Some(ident) => {
+                    let cql_name = ident.unraw().to_string();
+                    (Member::Named(ident), cql_name)
+                }
+                None => (Member::Unnamed(index.into()), index.to_string()),
+            };
+            let mut info = FieldInfo {
+                member,
+                ty: field.ty,
+                span,
+                cql_name,
+                renamed: false,
+                skip: false,
+                default: false,
+                flatten: false,
+                partition_key: false,
+                clustering_key: false,
+            };
+            parse_field_attributes(&field.attrs, &mut info)?;
+            Ok(info)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // Values of positional structs are matched with fields by their order,
+    // so names of the fields aren't used
+    let is_positional = transparent
+        || (current_derive == "FromRow" && !attrs.match_by_name)
+        || (current_derive == "ValueList" && !attrs.named);
+    if is_positional {
+        if let Some(field) = fields.iter().find(|field| field.renamed) {
+            let required_attribute = match current_derive {
+                "FromRow" => "`#[scylla(match_by_name)]` on the struct",
+                "ValueList" => "`#[scylla(named)]` on the struct",
+                _ => "a derive which matches fields by name",
+            };
+            return Err(Error::new(
+                field.span,
+                format!(
+                    "`rename` has no effect on fields matched by position, it requires {}",
+                    required_attribute
+                ),
+            ));
+        }
+    }
+
+    // Values are matched with fields by their CQL names, which have to be unique
+    for (ix, field) in fields.iter().enumerate() {
+        if !field.is_used() || field.flatten {
+            continue;
+        }
+        let is_duplicate = fields[..ix].iter().any(|previous| {
+            previous.is_used() && !previous.flatten && previous.cql_name == field.cql_name
+        });
+        if is_duplicate {
+            return Err(Error::new(
+                field.span,
+                format!("duplicate CQL name `{}`", field.cql_name),
+            ));
+        }
+    }
+
+    // Flattened structs are read and written using traits implemented by the derives
+    // of user defined types and named value lists
+    let supports_flatten = current_derive == "FromUserType"
+        || current_derive == "IntoUserType"
+        || (current_derive == "ValueList" && attrs.named);
+    if !supports_flatten {
+        if let Some(field) = fields.iter().find(|field| field.flatten) {
+            return Err(Error::new(
+                field.span,
+                format!(
+                    "derive({}) doesn't support `flatten`, it's supported by derive(FromUserType), \
+                     derive(IntoUserType) and derive(ValueList) with `#[scylla(named)]`",
+                    current_derive
+                ),
+            ));
+        }
+    }
+
+    if transparent && (fields.len() != 1 || fields[0].skip) {
+        return Err(Error::new(
+            struct_name.span(),
+            "derive(CqlNewtype) requires the struct to have exactly one field",
+        ));
+    }
+
+    Ok(StructInfo {
+        name: struct_name,
+        generics: input.generics,
+        crate_path: attrs.crate_path,
+        match_by_name: attrs.match_by_name,
+        named: attrs.named,
+        none_as_unset: attrs.none_as_unset,
+        keyspace: attrs.keyspace,
+        udt: attrs.udt,
+        table: attrs.table,
+        fields,
+    })
+}
+
+// Returns the contents of all `#[scylla(...)]` attributes
+pub(crate) fn scylla_attribute_items(attrs: &[Attribute]) -> Result<Vec<NestedMeta>, Error> {
+    let mut items = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("scylla")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => items.extend(list.nested),
+            meta => {
+                return Err(Error::new(
+                    meta.span(),
+                    "expected attribute arguments in parentheses: #[scylla(...)]",
+                ))
+            }
+        }
+    }
+    Ok(items)
+}
+
+struct StructAttrs {
+    crate_path: Path,
+    match_by_name: bool,
+    named: bool,
+    none_as_unset: bool,
+    keyspace: Option<String>,
+    udt: Option<String>,
+    table: Option<String>,
+}
+
+fn parse_struct_attributes(
+    attrs: &[Attribute],
+    current_derive: &str,
+) -> Result<StructAttrs, Error> {
+    let mut crate_path: Option<Path> = None;
+    let mut match_by_name = false;
+    let mut named = false;
+    let mut none_as_unset = false;
+    let mut keyspace: Option<String> = None;
+    let mut udt: Option<String> = None;
+    let mut table: Option<String> = None;
+
+    for item in scylla_attribute_items(attrs)? {
+        match &item {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("crate") => {
+                if crate_path.is_some() {
+                    return Err(Error::new(item.span(), "duplicate `crate` attribute"));
+                }
+                crate_path = Some(parse_lit_str(&name_value.lit)?.parse()?);
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("match_by_name") => {
+                if current_derive != "FromRow" {
+                    return Err(Error::new(
+                        item.span(),
+                        format!("derive({}) doesn't support `match_by_name`", current_derive),
+                    ));
+                }
+                match_by_name = true;
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("named") => {
+                if current_derive != "ValueList" {
+                    return Err(Error::new(
+                        item.span(),
+                        format!("derive({}) doesn't support `named`", current_derive),
+                    ));
+                }
+                named = true;
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("none_as_unset") => {
+                if current_derive != "ValueList" && current_derive != "Table" {
+                    return Err(Error::new(
+                        item.span(),
+                        format!("derive({}) doesn't support `none_as_unset`", current_derive),
+                    ));
+                }
+                none_as_unset = true;
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("transparent") => {
+                return Err(Error::new(
+                    item.span(),
+                    "`transparent` is not an attribute, use derive(CqlNewtype) instead",
+                ));
+            }
+            NestedMeta::Meta(Meta::NameValue(name_value))
+                if ["keyspace", "table", "udt"]
+                    .iter()
+                    .any(|name| name_value.path.is_ident(name)) =>
+            {
+                let is_udt_derive =
+                    current_derive == "IntoUserType" || current_derive == "FromUserType";
+                let (target, supported) = if name_value.path.is_ident("keyspace") {
+                    (&mut keyspace, current_derive == "Table" || is_udt_derive)
+                } else if name_value.path.is_ident("table") {
+                    (&mut table, current_derive == "Table")
+                } else {
+                    (&mut udt, is_udt_derive)
+                };
+                if !supported {
+                    return Err(Error::new(
+                        item.span(),
+                        format!(
+                            "derive({}) doesn't support `{}`",
+                            current_derive,
+                            name_value.path.get_ident().unwrap()
+                        ),
+                    ));
+                }
+                if target.is_some() {
+                    return Err(Error::new(item.span(), "duplicate attribute"));
+                }
+                *target = Some(parse_lit_str(&name_value.lit)?.value());
+            }
+            _ => return Err(Error::new(item.span(), "unknown struct attribute")),
+        }
+    }
+
+    Ok(StructAttrs {
+        crate_path: crate_path.unwrap_or_else(|| syn::parse_quote!(scylla)),
+        match_by_name,
+        named,
+        none_as_unset,
+        keyspace,
+        udt,
+        table,
+    })
+}
+
+fn parse_field_attributes(attrs: &[Attribute], info: &mut FieldInfo) -> Result<(), Error> {
+    // Primary key columns of a table, used by derive(Table)
+    for attr in attrs {
+        let is_partition_key = attr.path.is_ident("partition_key");
+        if !is_partition_key && !attr.path.is_ident("clustering_key") {
+            continue;
+        }
+        if !attr.tokens.is_empty() {
+            return Err(Error::new(
+                attr.span(),
+                "expected an attribute without arguments",
+            ));
+        }
+        if is_partition_key {
+            info.partition_key = true;
+        } else {
+            info.clustering_key = true;
+        }
+    }
+    if info.partition_key && info.clustering_key {
+        return Err(Error::new(
+            info.span,
+            "a column can't be a part of both partition key and clustering key",
+        ));
+    }
+
+    for item in scylla_attribute_items(attrs)? {
+        match &item {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("rename") => {
+                if info.renamed {
+                    return Err(Error::new(item.span(), "duplicate `rename` attribute"));
+                }
+                info.cql_name = parse_lit_str(&name_value.lit)?.value();
+                info.renamed = true;
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => info.skip = true,
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => info.default = true,
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => info.flatten = true,
+            _ => return Err(Error::new(item.span(), "unknown field attribute")),
+        }
+    }
+
+    if info.skip && (info.renamed || info.default || info.partition_key || info.clustering_key) {
+        return Err(Error::new(
+            info.span,
+            "`skip` cannot be combined with other field attributes",
+        ));
+    }
+    if info.flatten
+        && (info.skip || info.renamed || info.default || info.partition_key || info.clustering_key)
+    {
+        return Err(Error::new(
+            info.span,
+            "`flatten` cannot be combined with other field attributes",
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn parse_lit_str(lit: &Lit) -> Result<&syn::LitStr, Error> {
+    match lit {
+        Lit::Str(lit_str) => Ok(lit_str),
+        _ => Err(Error::new(lit.span(), "expected a string literal")),
+    }
 }
@@ -0,0 +1,183 @@
+use crate::parser::{FieldInfo, StructInfo};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::Error;
+
+/// #[derive(Table)] maps a struct to a table, generating statements operating on its rows
+/// together with ValueList and FromRow implementations
+pub fn table_derive(tokens_input: TokenStream) -> TokenStream {
+    let struct_info = match crate::parser::parse_struct(tokens_input, "Table") {
+        Ok(struct_info) => struct_info,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+
+    match generate_table(&struct_info) {
+        Ok(table_impl) => {
+            let value_list_impl = crate::value_list::generate_value_list(&struct_info);
+            let from_row_impl = crate::from_row::generate_from_row(&struct_info);
+
+            TokenStream::from(quote! {
+                #table_impl
+                #value_list_impl
+                #from_row_impl
+            })
+        }
+        Err(e) => TokenStream::from(e.to_compile_error()),
+    }
+}
+
+fn generate_table(struct_info: &StructInfo) -> Result<proc_macro2::TokenStream, Error> {
+    let struct_name = &struct_info.name;
+    let path = &struct_info.crate_path;
+
+    let table = match &struct_info.table {
+        Some(table) => table,
+        None => {
+            return Err(Error::new(
+                struct_name.span(),
+                "derive(Table) requires the table name: #[scylla(table = \"...\")]",
+            ))
+        }
+    };
+    let table = match &struct_info.keyspace {
+        Some(keyspace) => format!("{}.{}", quote_identifier(keyspace), quote_identifier(table)),
+        None => quote_identifier(table),
+    };
+
+    let partition_key: Vec<&FieldInfo> = struct_info
+        .used_fields()
+        .filter(|field| field.partition_key)
+        .collect();
+    let clustering_key: Vec<&FieldInfo> = struct_info
+        .used_fields()
+        .filter(|field| field.clustering_key)
+        .collect();
+    let regular: Vec<&FieldInfo> = struct_info
+        .used_fields()
+        .filter(|field| !field.partition_key && !field.clustering_key)
+        .collect();
+    let primary_key: Vec<&FieldInfo> = partition_key
+        .iter()
+        .chain(clustering_key.iter())
+        .copied()
+        .collect();
+
+    if partition_key.is_empty() {
+        return Err(Error::new(
+            struct_name.span(),
+            "derive(Table) requires at least one field marked with #[partition_key]",
+        ));
+    }
+
+    // Statements are generated here, so that they are string literals in the generated code
+    let columns: Vec<String> = struct_info
+        .used_fields()
+        .map(|field| quote_identifier(&field.cql_name))
+        .collect();
+    let conditions = |key: &[&FieldInfo]| {
+        key.iter()
+            .map(|field| format!("{} = ?", quote_identifier(&field.cql_name)))
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    };
+
+    let insert = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        table,
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+    let select_by_primary_key = format!(
+        "SELECT {} FROM {} WHERE {}",
+        columns.join(", "),
+        table,
+        conditions(&primary_key)
+    );
+    let select_by_partition_key = format!(
+        "SELECT {} FROM {} WHERE {}",
+        columns.join(", "),
+        table,
+        conditions(&partition_key)
+    );
+    let delete = format!("DELETE FROM {} WHERE {}", table, conditions(&primary_key));
+    // Rows without regular columns can't be updated
+    let update = if regular.is_empty() {
+        quote! { None }
+    } else {
+        let update = format!(
+            "UPDATE {} SET {} WHERE {}",
+            table,
+            regular
+                .iter()
+                .map(|field| format!("{} = ?", quote_identifier(&field.cql_name)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            conditions(&primary_key)
+        );
+        quote! { Some(#update) }
+    };
+
+    let field_types = |fields: &[&FieldInfo]| -> Vec<syn::Type> {
+        fields.iter().map(|field| field.ty.clone()).collect()
+    };
+    let field_members = |fields: &[&FieldInfo]| -> Vec<syn::Member> {
+        fields.iter().map(|field| field.member.clone()).collect()
+    };
+    let primary_key_types = field_types(&primary_key);
+    let primary_key_members = field_members(&primary_key);
+    let partition_key_types = field_types(&partition_key);
+    let partition_key_members = field_members(&partition_key);
+    let update_members = field_members(&regular)
+        .into_iter()
+        .chain(primary_key_members.iter().cloned());
+    let update_values_len = regular.len() + primary_key.len();
+    let replace_nulls = struct_info.none_as_unset.then(|| {
+        quote! {
+            result.replace_nulls_with_unset();
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) =
+        struct_info.split_generics_with_bound(quote! {
+            #path::frame::value::Value
+            + #path::cql_to_rust::FromCqlVal<#path::frame::response::result::CqlValue>
+            + ::std::clone::Clone
+        });
+
+    Ok(quote! {
+        impl #impl_generics #path::statement::table::Table for #struct_name #ty_generics #where_clause {
+            type PrimaryKey = (#(#primary_key_types,)*);
+            type PartitionKey = (#(#partition_key_types,)*);
+
+            const INSERT: &'static str = #insert;
+            const SELECT_BY_PRIMARY_KEY: &'static str = #select_by_primary_key;
+            const SELECT_BY_PARTITION_KEY: &'static str = #select_by_partition_key;
+            const UPDATE: Option<&'static str> = #update;
+            const DELETE: &'static str = #delete;
+
+            fn primary_key(&self) -> Self::PrimaryKey {
+                (#(::std::clone::Clone::clone(&self.#primary_key_members),)*)
+            }
+
+            fn partition_key(&self) -> Self::PartitionKey {
+                (#(::std::clone::Clone::clone(&self.#partition_key_members),)*)
+            }
+
+            fn update_values(&self) -> #path::frame::value::SerializedResult<'_> {
+                let mut result = #path::frame::value::SerializedValues::with_capacity(#update_values_len);
+                #(
+                    result.add_value(&self.#update_members)?;
+                )*
+                #replace_nulls
+
+                Ok(std::borrow::Cow::Owned(result))
+            }
+        }
+    })
+}
+
+// Names are quoted, so that they are case sensitive and can be keywords.
+// Same as `scylla::statement::query_builder::quote_identifier`.
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
@@ -1,27 +1,149 @@
+use crate::parser::StructInfo;
 use proc_macro::TokenStream;
 use quote::quote;
 
 /// #[derive(ValueList)] allows to parse a struct as a list of values,
 /// which can be fed to the query directly.
-/// Works only on simple structs without generics etc
 pub fn value_list_derive(tokens_input: TokenStream) -> TokenStream {
-    let (struct_name, struct_fields) =
-        crate::parser::parse_struct_with_named_fields(tokens_input, "ValueList");
+    let struct_info = match crate::parser::parse_struct(tokens_input, "ValueList") {
+        Ok(struct_info) => struct_info,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+
+    TokenStream::from(generate_value_list(&struct_info))
+}
+
+/// Generates ValueList implementation for the struct
+pub(crate) fn generate_value_list(struct_info: &StructInfo) -> proc_macro2::TokenStream {
+    let struct_name = &struct_info.name;
+    let path = &struct_info.crate_path;
+    let (impl_generics, ty_generics, where_clause) =
+        struct_info.split_generics_with_bound(quote! { #path::frame::value::Value });
+
+    let values_len = struct_info.used_fields().count();
+    let field_name = struct_info.used_fields().map(|field| &field.member);
+    let field_type = struct_info.used_fields().map(|field| &field.ty);
+
+    let (add_values, type_check) = if struct_info.named {
+        let add_values = quote! {
+            <Self as #path::frame::value::NamedValues>::add_named_values(self, &mut result)?;
+        };
+        // Named values are bound to markers with the same names, in any order
+        let type_check = quote! {
+            use #path::frame::value::{check_named_values_present, NamedValues};
+
+            <Self as NamedValues>::check_named_value_types(col_specs)?;
+            check_named_values_present(col_specs, &<Self as NamedValues>::value_names())
+        };
+        (add_values, type_check)
+    } else {
+        let field_ix = 0..values_len;
+        let add_values = quote! {
+            #(
+                result.add_value(&self.#field_name)?;
+            )*
+        };
+        let type_check = quote! {
+            use #path::frame::value::{check_value_type, check_values_count};
+
+            check_values_count(col_specs, #values_len)?;
+            #(
+                check_value_type::<#field_type>(&col_specs[#field_ix])?;
+            )*
+            Ok(())
+        };
+        (add_values, type_check)
+    };
+
+    let replace_nulls = struct_info.none_as_unset.then(|| {
+        quote! {
+            result.replace_nulls_with_unset();
+        }
+    });
+
+    let named_values_impl = struct_info
+        .named
+        .then(|| generate_named_values(struct_info));
 
-    let values_len = struct_fields.named.len();
-    let field_name = struct_fields.named.iter().map(|field| &field.ident);
     let generated = quote! {
-        impl scylla::frame::value::ValueList for #struct_name {
-            fn serialized(&self) -> scylla::frame::value::SerializedResult {
-                let mut result = scylla::frame::value::SerializedValues::with_capacity(#values_len);
-                #(
-                    result.add_value(&self.#field_name)?;
-                )*
+        #named_values_impl
+
+        impl #impl_generics #path::frame::value::ValueList for #struct_name #ty_generics #where_clause {
+            fn serialized(&self) -> #path::frame::value::SerializedResult {
+                let mut result = #path::frame::value::SerializedValues::with_capacity(#values_len);
+                #add_values
+                #replace_nulls
 
                 Ok(std::borrow::Cow::Owned(result))
             }
+
+            fn type_check(col_specs: &[#path::frame::response::result::ColumnSpec])
+            -> Result<(), #path::frame::value::TypeCheckError> {
+                #type_check
+            }
         }
     };
 
-    TokenStream::from(generated)
+    generated
+}
+
+/// Generates NamedValues implementation for the struct, which allows to flatten it into other structs
+fn generate_named_values(struct_info: &StructInfo) -> proc_macro2::TokenStream {
+    let struct_name = &struct_info.name;
+    let path = &struct_info.crate_path;
+    let (impl_generics, ty_generics, where_clause) =
+        struct_info.split_generics_with_bound(quote! { #path::frame::value::Value });
+
+    let mut add_values = Vec::new();
+    let mut add_names = Vec::new();
+    let mut type_checks = Vec::new();
+    for field in struct_info.used_fields() {
+        let field_name = &field.member;
+        let field_type = &field.ty;
+        let cql_name = &field.cql_name;
+
+        if field.flatten {
+            add_values.push(quote! {
+                <#field_type as #path::frame::value::NamedValues>::add_named_values(&self.#field_name, values)?;
+            });
+            add_names.push(quote! {
+                names.extend(<#field_type as #path::frame::value::NamedValues>::value_names());
+            });
+            type_checks.push(quote! {
+                <#field_type as #path::frame::value::NamedValues>::check_named_value_types(col_specs)?;
+            });
+        } else {
+            add_values.push(quote! {
+                values.add_named_value(#cql_name, &self.#field_name)?;
+            });
+            add_names.push(quote! {
+                names.push(#cql_name);
+            });
+            type_checks.push(quote! {
+                #path::frame::value::check_named_value_type::<#field_type>(col_specs, #cql_name)?;
+            });
+        }
+    }
+
+    quote! {
+        impl #impl_generics #path::frame::value::NamedValues for #struct_name #ty_generics #where_clause {
+            fn add_named_values(&self, values: &mut #path::frame::value::SerializedValues)
+            -> Result<(), #path::frame::value::SerializeValuesError> {
+                #(#add_values)*
+                Ok(())
+            }
+
+            fn value_names() -> Vec<&'static str> {
+                let mut names: Vec<&'static str> = Vec::new();
+                #(#add_names)*
+                names
+            }
+
+            fn check_named_value_types(col_specs: &[#path::frame::response::result::ColumnSpec])
+            -> Result<(), #path::frame::value::TypeCheckError> {
+                #(#type_checks)*
+                Ok(())
+            }
+        }
+    }
 }
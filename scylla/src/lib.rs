@@ -107,6 +107,8 @@ pub(crate) mod utils;
 pub use statement::batch;
 pub use statement::prepared_statement;
 pub use statement::query;
+pub use statement::query_builder;
+pub use statement::table::Table;
 
 pub use frame::response::cql_to_rust;
 pub use frame::response::cql_to_rust::FromRow;
@@ -11,6 +11,10 @@ pub use crate::frame::request::batch::BatchType;
 /// CQL batch statement.
 ///
 /// This represents a CQL batch that can be executed on a server.
+///
+/// A batch has no server-side timeout and server timeouts of its statements are not applied,
+/// because the `BATCH` message has no place for them. A `BEGIN BATCH` [`Query`](crate::query::Query)
+/// can be used instead.
 #[derive(Clone)]
 pub struct Batch {
     pub(crate) config: StatementConfig,
@@ -77,6 +81,7 @@ impl Batch {
     /// This is used in [`RetryPolicy`] to decide if retrying a query is safe
     pub fn set_is_idempotent(&mut self, is_idempotent: bool) {
         self.config.is_idempotent = is_idempotent;
+        self.config.is_idempotent_explicit = true;
     }
 
     /// Gets the idempotence of this batch
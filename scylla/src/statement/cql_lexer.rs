@@ -0,0 +1,166 @@
+//! Minimal CQL tokenizer used to inspect statement text on the client side
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// Keyword, unquoted or quoted identifier
+    Word,
+    /// String, number or other constant
+    Literal,
+    /// Any other character, e.g. an operator, a bracket or a bind marker
+    Symbol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token<'a> {
+    pub(crate) offset: usize,
+    pub(crate) text: &'a str,
+    pub(crate) kind: TokenKind,
+    /// How many brackets enclose the token. Brackets themselves are at the depth outside of them.
+    pub(crate) depth: usize,
+}
+
+impl<'a> Token<'a> {
+    /// Checks whether the token is an unquoted word equal to `keyword`, ignoring case
+    pub(crate) fn is_word(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    pub(crate) fn is_symbol(&self, symbol: &str) -> bool {
+        self.kind == TokenKind::Symbol && self.text == symbol
+    }
+}
+
+/// Splits CQL statement text into tokens. Whitespace and comments are skipped.
+pub(crate) fn tokenize(contents: &str) -> Vec<Token<'_>> {
+    let bytes = contents.as_bytes();
+    let mut tokens = Vec::new();
+    let mut depth: usize = 0;
+    let mut i = 0;
+
+    // Returns the position after the end of a quoted part starting at `start`
+    let skip_quoted = |start: usize, quote: u8| {
+        let mut i = start + 1;
+        while i < bytes.len() {
+            if bytes[i] == quote {
+                // A doubled quote is an escaped quote
+                if bytes.get(i + 1) == Some(&quote) {
+                    i += 2;
+                    continue;
+                }
+                return i + 1;
+            }
+            i += 1;
+        }
+        bytes.len()
+    };
+    let skip_to = |start: usize, end_marker: &str| match contents[start..].find(end_marker) {
+        Some(end) => start + end + end_marker.len(),
+        None => bytes.len(),
+    };
+
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        let kind = match c {
+            _ if c.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = skip_to(i, "\n");
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = skip_to(i, "\n");
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_to(i + 2, "*/");
+                continue;
+            }
+            b'\'' => {
+                i = skip_quoted(i, c);
+                TokenKind::Literal
+            }
+            b'"' => {
+                i = skip_quoted(i, c);
+                TokenKind::Word
+            }
+            b'$' if bytes.get(i + 1) == Some(&b'$') => {
+                i = skip_to(i + 2, "$$");
+                TokenKind::Literal
+            }
+            _ if c.is_ascii_alphabetic() || c == b'_' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                TokenKind::Word
+            }
+            _ if c.is_ascii_digit() => {
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || bytes[i] == b'_')
+                {
+                    i += 1;
+                }
+                TokenKind::Literal
+            }
+            _ => {
+                // Multi-byte characters are not a part of CQL syntax outside of literals and identifiers
+                i += contents[i..].chars().next().map_or(1, char::len_utf8);
+                TokenKind::Symbol
+            }
+        };
+
+        if matches!(c, b')' | b']' | b'}') {
+            depth = depth.saturating_sub(1);
+        }
+        tokens.push(Token {
+            offset: start,
+            text: &contents[start..i],
+            kind,
+            depth,
+        });
+        if matches!(c, b'(' | b'[' | b'{') {
+            depth += 1;
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, TokenKind};
+
+    #[test]
+    fn tokenize_statement() {
+        let contents =
+            "UPDATE \"Tab\" SET m = m + {'a''b': 1.5} -- comment\n WHERE /* x */ k = ? // end";
+        let tokens: Vec<(&str, TokenKind, usize)> = tokenize(contents)
+            .into_iter()
+            .map(|token| (token.text, token.kind, token.depth))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                ("UPDATE", TokenKind::Word, 0),
+                ("\"Tab\"", TokenKind::Word, 0),
+                ("SET", TokenKind::Word, 0),
+                ("m", TokenKind::Word, 0),
+                ("=", TokenKind::Symbol, 0),
+                ("m", TokenKind::Word, 0),
+                ("+", TokenKind::Symbol, 0),
+                ("{", TokenKind::Symbol, 0),
+                ("'a''b'", TokenKind::Literal, 1),
+                (":", TokenKind::Symbol, 1),
+                ("1.5", TokenKind::Literal, 1),
+                ("}", TokenKind::Symbol, 0),
+                ("WHERE", TokenKind::Word, 0),
+                ("k", TokenKind::Word, 0),
+                ("=", TokenKind::Symbol, 0),
+                ("?", TokenKind::Symbol, 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Inferring idempotence of statements from their CQL text.
+//!
+//! The analysis is conservative: a statement is considered idempotent only if it is a `SELECT`,
+//! or an `INSERT`, `UPDATE`, `DELETE` or a `BATCH` of those that contains none of:
+//! * lightweight transaction conditions (`IF ...`),
+//! * counter updates, list appends and prepends (`c = c + ?`, `l = ? + l`),
+//! * removal of list elements by index (`DELETE l[0] FROM ...`),
+//! * non-deterministic functions, like `now()` or `uuid()`.
+//!
+//! Adding to and removing from a collection is considered idempotent only for collection literals
+//! which can't be a list append (`s = s + {1}`, `l = l - [1]`). With a bind marker (`s = s + ?`)
+//! the statement might just as well be a counter update, so it is not considered idempotent.
+
+use super::cql_lexer::{tokenize, Token, TokenKind};
+
+/// Functions which return a different value every time they are called
+const NON_DETERMINISTIC_FUNCTIONS: &[&str] = &[
+    "now",
+    "uuid",
+    "currenttimestamp",
+    "currentdate",
+    "currenttime",
+    "currenttimeuuid",
+];
+
+/// Returns true if the statement can be safely applied multiple times, judging by its CQL text.
+/// Returns false if it might not be idempotent or its kind isn't recognized.
+///
+/// # Example
+/// ```
+/// # use scylla::statement::idempotence::infer_is_idempotent;
+/// assert!(infer_is_idempotent("UPDATE ks.tab SET a = ? WHERE pk = ?"));
+/// assert!(!infer_is_idempotent("UPDATE ks.tab SET c = c + 1 WHERE pk = ?"));
+/// assert!(!infer_is_idempotent("INSERT INTO ks.tab (pk, t) VALUES (?, now())"));
+/// ```
+pub fn infer_is_idempotent(contents: &str) -> bool {
+    let tokens = tokenize(contents);
+
+    match tokens.first() {
+        Some(first) if first.is_word("SELECT") => return true,
+        Some(first) if first.is_word("BEGIN") => {
+            if matches!(tokens.get(1), Some(token) if token.is_word("COUNTER")) {
+                return false;
+            }
+        }
+        Some(first)
+            if ["INSERT", "UPDATE", "DELETE"]
+                .iter()
+                .any(|k| first.is_word(k)) => {}
+        _ => return false,
+    }
+
+    (0..tokens.len()).all(|position| !breaks_idempotence(&tokens, position))
+}
+
+/// Checks whether the token at `position` starts a construct which isn't idempotent
+fn breaks_idempotence(tokens: &[Token], position: usize) -> bool {
+    let token = &tokens[position];
+    let next = tokens.get(position + 1);
+
+    if token.is_word("IF") && token.depth == 0 {
+        return true;
+    }
+
+    if token.kind == TokenKind::Word
+        && matches!(next, Some(next) if next.is_symbol("("))
+        && NON_DETERMINISTIC_FUNCTIONS
+            .iter()
+            .any(|function| token.is_word(function))
+    {
+        return true;
+    }
+
+    if token.is_word("DELETE") {
+        return tokens[position + 1..]
+            .iter()
+            .take_while(|token| !token.is_word("FROM"))
+            .any(|token| token.is_symbol("[") && token.depth == 0);
+    }
+
+    if token.is_symbol("=") && position > 0 && tokens[position - 1].kind == TokenKind::Word {
+        return modifies_in_place(&tokens[position - 1], &tokens[position + 1..]);
+    }
+
+    false
+}
+
+/// Checks whether assigning the value of `rhs` to `column` is an append, a prepend or a counter update
+fn modifies_in_place(column: &Token, rhs: &[Token]) -> bool {
+    let depth = column.depth;
+    let rhs: Vec<&Token> = rhs
+        .iter()
+        .take_while(|token| {
+            token.depth >= depth
+                && !(token.depth == depth
+                    && (token.is_symbol(",")
+                        || token.is_symbol(";")
+                        || ["WHERE", "IF", "AND", "APPLY"]
+                            .iter()
+                            .any(|keyword| token.is_word(keyword))))
+        })
+        .collect();
+
+    let is_column = |token: &Token| {
+        token.kind == TokenKind::Word
+            && token.depth == depth
+            && if column.text.starts_with('"') {
+                token.text == column.text
+            } else {
+                token.text.eq_ignore_ascii_case(column.text)
+            }
+    };
+    let is_symbol = |index: usize, symbol: &str| matches!(rhs.get(index), Some(token) if token.is_symbol(symbol));
+
+    match rhs.iter().position(|token| is_column(token)) {
+        // column = column + operand
+        Some(0) if is_symbol(1, "+") => !is_symbol(2, "{"),
+        Some(0) if is_symbol(1, "-") => !is_symbol(2, "{") && !is_symbol(2, "["),
+        // column = operand + column
+        Some(column_position) if column_position > 0 => {
+            is_symbol(column_position - 1, "+") && !is_symbol(0, "{")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::infer_is_idempotent;
+
+    #[test]
+    fn idempotent_statements() {
+        let idempotent = [
+            "SELECT now() FROM ks.tab",
+            "INSERT INTO ks.tab (a, b) VALUES (?, ?) USING TTL 10",
+            "insert into tab (a, b) values (1, 'if now()')",
+            "UPDATE ks.tab SET a = ?, b = b WHERE pk = ?",
+            "UPDATE tab SET s = s + {1, 2}, m = m + {'k': 1}, l = l - [1] WHERE pk = 1 AND ck = 2",
+            "UPDATE tab SET l[1] = 5, m['key'] = 'value' WHERE pk = 1",
+            "DELETE m FROM tab WHERE pk = ?",
+            "DELETE FROM tab WHERE pk IN (1, 2) AND ck = ?",
+            "BEGIN BATCH INSERT INTO tab (a) VALUES (1); UPDATE tab SET b = 2 WHERE a = 1; APPLY BATCH",
+        ];
+        for contents in idempotent {
+            assert!(infer_is_idempotent(contents), "{}", contents);
+        }
+    }
+
+    #[test]
+    fn non_idempotent_statements() {
+        let non_idempotent = [
+            "INSERT INTO tab (a, b) VALUES (?, ?) IF NOT EXISTS",
+            "UPDATE tab SET b = ? WHERE a = ? IF b = ?",
+            "DELETE FROM tab WHERE a = ? IF EXISTS",
+            "UPDATE tab SET c = c + 1 WHERE a = ?",
+            "UPDATE tab SET c = c - ? WHERE a = ?",
+            "UPDATE tab SET \"Cnt\" = \"Cnt\" + 1 WHERE a = ?",
+            "UPDATE tab SET b = 1, l = l + [1] WHERE a = ?",
+            "UPDATE tab SET l = [0] + l WHERE a = ?",
+            "UPDATE tab SET l = ? + l WHERE a = ?",
+            "UPDATE tab SET s = s + ? WHERE a = ?",
+            "DELETE l[0] FROM tab WHERE a = ?",
+            "INSERT INTO tab (a, t) VALUES (?, now())",
+            "INSERT INTO tab (a, u) VALUES (uuid(), ?)",
+            "UPDATE tab SET t = currentTimestamp() WHERE a = ?",
+            "BEGIN COUNTER BATCH UPDATE tab SET c = c + 1 WHERE a = 1; APPLY BATCH",
+            "BEGIN BATCH INSERT INTO tab (a) VALUES (1) IF NOT EXISTS; APPLY BATCH",
+            "TRUNCATE tab",
+            "CREATE TABLE IF NOT EXISTS tab (a int PRIMARY KEY)",
+            "",
+        ];
+        for contents in non_idempotent {
+            assert!(!infer_is_idempotent(contents), "{}", contents);
+        }
+    }
+}
@@ -1,11 +1,18 @@
 use std::{sync::Arc, time::Duration};
 
+use crate::routing::Shard;
 use crate::transport::speculative_execution::SpeculativeExecutionPolicy;
+use crate::transport::Node;
 use crate::{history::HistoryListener, transport::retry_policy::RetryPolicy};
 
 pub mod batch;
+pub(crate) mod cql_lexer;
+pub mod idempotence;
 pub mod prepared_statement;
 pub mod query;
+pub mod query_builder;
+pub mod server_timeout;
+pub mod table;
 
 pub use crate::frame::types::{Consistency, SerialConsistency};
 
@@ -15,6 +22,9 @@ pub struct StatementConfig {
     pub serial_consistency: Option<SerialConsistency>,
 
     pub is_idempotent: bool,
+    // Set when idempotence was chosen by the user, so that it is not overwritten by inference
+    pub is_idempotent_explicit: bool,
+    pub none_as_unset: bool,
 
     pub retry_policy: Option<Box<dyn RetryPolicy>>,
     pub speculative_execution_policy: Option<Arc<dyn SpeculativeExecutionPolicy>>,
@@ -22,8 +32,16 @@ pub struct StatementConfig {
     pub tracing: bool,
     pub timestamp: Option<i64>,
     pub request_timeout: Option<Duration>,
+    pub server_timeout: Option<Duration>,
+
+    pub prefetch_pages: usize,
+    pub page_buffer_limit: Option<usize>,
+    pub page_timeout: Option<Duration>,
 
     pub history_listener: Option<Arc<dyn HistoryListener>>,
+
+    pub target_node: Option<Arc<Node>>,
+    pub target_shard: Option<Shard>,
 }
 
 impl Default for StatementConfig {
@@ -32,12 +50,20 @@ impl Default for StatementConfig {
             consistency: Default::default(),
             serial_consistency: Some(SerialConsistency::LocalSerial),
             is_idempotent: false,
+            is_idempotent_explicit: false,
+            none_as_unset: false,
             retry_policy: None,
             speculative_execution_policy: None,
             tracing: false,
             timestamp: None,
             request_timeout: None,
+            server_timeout: None,
+            prefetch_pages: 1,
+            page_buffer_limit: None,
+            page_timeout: None,
             history_listener: None,
+            target_node: None,
+            target_shard: None,
         }
     }
 }
@@ -51,6 +77,7 @@ impl Clone for StatementConfig {
                 .map(|policy| policy.clone_boxed()),
             speculative_execution_policy: self.speculative_execution_policy.clone(),
             history_listener: self.history_listener.clone(),
+            target_node: self.target_node.clone(),
             ..*self
         }
     }
@@ -61,4 +88,10 @@ impl StatementConfig {
     pub fn determine_consistency(&self, default_consistency: Consistency) -> Consistency {
         self.consistency.unwrap_or(default_consistency)
     }
+
+    /// Shard to which the statement is sent. The target shard is ignored without a target node,
+    /// because the same shard number means different shards on different nodes.
+    pub(crate) fn determine_target_shard(&self) -> Option<Shard> {
+        self.target_node.as_ref().and(self.target_shard)
+    }
 }
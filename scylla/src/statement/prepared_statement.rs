@@ -1,18 +1,23 @@
+use arc_swap::ArcSwap;
 use bytes::{BufMut, Bytes, BytesMut};
 use smallvec::{smallvec, SmallVec};
 use std::convert::TryInto;
+use std::marker::PhantomData;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 use uuid::Uuid;
 
 use super::StatementConfig;
-use crate::frame::response::result::PreparedMetadata;
+use crate::frame::response::cql_to_rust::FromRow;
+use crate::frame::response::result::{PreparedMetadata, ResultMetadata};
 use crate::frame::types::{Consistency, SerialConsistency};
-use crate::frame::value::SerializedValues;
+use crate::frame::value::{SerializedValues, TypeCheckError, ValueList};
 use crate::history::HistoryListener;
+use crate::routing::Shard;
 use crate::transport::partitioner::PartitionerName;
 use crate::transport::retry_policy::RetryPolicy;
+use crate::transport::Node;
 
 /// Represents a statement prepared on the server.
 #[derive(Debug)]
@@ -22,9 +27,13 @@ pub struct PreparedStatement {
 
     id: Bytes,
     metadata: PreparedMetadata,
+    // Shared between clones, so that a detected change of the result metadata
+    // is visible to all copies of the statement
+    result_metadata: Arc<ArcSwap<ResultMetadata>>,
     statement: String,
     page_size: Option<i32>,
     partitioner_name: PartitionerName,
+    is_confirmed_lwt: bool,
 }
 
 impl Clone for PreparedStatement {
@@ -34,9 +43,11 @@ impl Clone for PreparedStatement {
             prepare_tracing_ids: Vec::new(),
             id: self.id.clone(),
             metadata: self.metadata.clone(),
+            result_metadata: self.result_metadata.clone(),
             statement: self.statement.clone(),
             page_size: self.page_size,
             partitioner_name: self.partitioner_name.clone(),
+            is_confirmed_lwt: self.is_confirmed_lwt,
         }
     }
 }
@@ -45,6 +56,7 @@ impl PreparedStatement {
     pub(crate) fn new(
         id: Bytes,
         metadata: PreparedMetadata,
+        result_metadata: ResultMetadata,
         statement: String,
         page_size: Option<i32>,
         config: StatementConfig,
@@ -52,11 +64,13 @@ impl PreparedStatement {
         Self {
             id,
             metadata,
+            result_metadata: Arc::new(ArcSwap::from_pointee(result_metadata)),
             statement,
             prepare_tracing_ids: Vec::new(),
             page_size,
             config,
             partitioner_name: Default::default(),
+            is_confirmed_lwt: false,
         }
     }
 
@@ -161,6 +175,18 @@ impl PreparedStatement {
         Ok(buf.into())
     }
 
+    /// Returns true if the database marked the statement as a lightweight transaction when preparing it.\
+    /// Only Scylla marks LWT statements, for other databases it is always false.
+    /// Statements marked as LWT are sent to replicas in the same order every time,
+    /// which reduces contention between Paxos rounds.
+    pub fn is_confirmed_lwt(&self) -> bool {
+        self.is_confirmed_lwt
+    }
+
+    pub(crate) fn set_is_confirmed_lwt(&mut self, is_confirmed_lwt: bool) {
+        self.is_confirmed_lwt = is_confirmed_lwt;
+    }
+
     /// Returns the name of the keyspace this statement is operating on.
     pub fn get_keyspace_name(&self) -> Option<&str> {
         self.metadata
@@ -205,8 +231,11 @@ impl PreparedStatement {
     /// If set to `true` we can be sure that it is idempotent
     /// If set to `false` it is unknown whether it is idempotent
     /// This is used in [`RetryPolicy`] to decide if retrying a query is safe
+    /// The value set here takes precedence over the idempotence inferred by the session,
+    /// see [`SessionBuilder::infer_idempotence`](crate::SessionBuilder::infer_idempotence)
     pub fn set_is_idempotent(&mut self, is_idempotent: bool) {
         self.config.is_idempotent = is_idempotent;
+        self.config.is_idempotent_explicit = true;
     }
 
     /// Gets the idempotence of this statement
@@ -237,6 +266,56 @@ impl PreparedStatement {
         self.config.tracing
     }
 
+    /// Sets the number of pages fetched ahead of the page being consumed
+    /// when iterating with `Session::execute_iter`. With 0 the next page is fetched
+    /// only after the current one is consumed. Defaults to 1.
+    pub fn set_prefetch_pages(&mut self, prefetch_pages: usize) {
+        self.config.prefetch_pages = prefetch_pages;
+    }
+
+    /// Gets the number of pages fetched ahead when iterating over this statement
+    pub fn get_prefetch_pages(&self) -> usize {
+        self.config.prefetch_pages
+    }
+
+    /// Limits the total size in bytes of pages buffered by `Session::execute_iter`,
+    /// fetching is paused when it's exceeded until pages are consumed.
+    /// The page being consumed is counted as well, so at least one page is always buffered.
+    /// `None` (default) means no limit other than [`set_prefetch_pages`](Self::set_prefetch_pages).
+    pub fn set_page_buffer_limit(&mut self, limit: Option<usize>) {
+        self.config.page_buffer_limit = limit;
+    }
+
+    /// Gets the limit of bytes buffered when iterating over this statement
+    pub fn get_page_buffer_limit(&self) -> Option<usize> {
+        self.config.page_buffer_limit
+    }
+
+    /// Sets the timeout of fetching a single page by `Session::execute_iter`.
+    /// A page which isn't received in time fails with `QueryError::RequestTimeout`,
+    /// which is handled by the retry policy. `None` (default) means no timeout.
+    pub fn set_page_timeout(&mut self, timeout: Option<Duration>) {
+        self.config.page_timeout = timeout;
+    }
+
+    /// Gets the timeout of fetching a single page of this statement
+    pub fn get_page_timeout(&self) -> Option<Duration> {
+        self.config.page_timeout
+    }
+
+    /// Enable or disable sending `None` values as unset instead of null.
+    /// Unset values leave the existing data untouched, while null values
+    /// delete it and create tombstones. Disabled by default.\
+    /// Applies to all null values bound to this statement, also when it is executed in a batch.
+    pub fn set_none_as_unset(&mut self, none_as_unset: bool) {
+        self.config.none_as_unset = none_as_unset;
+    }
+
+    /// Gets whether `None` values are sent as unset for this statement
+    pub fn get_none_as_unset(&self) -> bool {
+        self.config.none_as_unset
+    }
+
     /// Sets the default timestamp for this statement in microseconds.
     /// If not None, it will replace the server side assigned timestamp as default timestamp
     /// If a statement contains a `USING TIMESTAMP` clause, calling this method won't change
@@ -263,6 +342,38 @@ impl PreparedStatement {
         self.config.request_timeout
     }
 
+    /// Sets the node to which this statement is sent, bypassing the load balancing policy.
+    /// Useful for querying node-local tables, like `system.local` or virtual tables.
+    /// If the node can't be reached, the request fails instead of being sent to another node.
+    /// Nodes can be obtained from [`ClusterData::get_nodes_info`](crate::transport::ClusterData::get_nodes_info).
+    pub fn set_target_node(&mut self, node: Option<Arc<Node>>) {
+        self.config.target_node = node;
+    }
+
+    /// Gets the node to which this statement is sent
+    pub fn get_target_node(&self) -> Option<&Arc<Node>> {
+        self.config.target_node.as_ref()
+    }
+
+    /// Sets the shard to which this statement is sent. Only used together with a target node,
+    /// see [`set_target_node`](Self::set_target_node). If there is no working connection to the shard,
+    /// the request fails instead of being sent to another shard.
+    pub fn set_target_shard(&mut self, shard: Option<Shard>) {
+        self.config.target_shard = shard;
+    }
+
+    /// Gets the shard to which this statement is sent
+    pub fn get_target_shard(&self) -> Option<Shard> {
+        self.config.target_shard
+    }
+
+    /// Gets server timeout associated with this statement.
+    /// It is taken from the [`Query`](crate::query::Query) this statement was prepared from
+    /// and is already included in the statement text as `USING TIMEOUT`.
+    pub fn get_server_timeout(&self) -> Option<Duration> {
+        self.config.server_timeout
+    }
+
     /// Sets the name of the partitioner used for this statement.
     pub(crate) fn set_partitioner_name(&mut self, partitioner_name: Option<&str>) {
         self.partitioner_name = partitioner_name
@@ -275,6 +386,37 @@ impl PreparedStatement {
         &self.metadata
     }
 
+    /// Access metadata about the result of this prepared statement as returned by the database.
+    ///
+    /// The metadata is updated when the driver detects that the columns returned
+    /// by the statement have changed, e.g. after an `ALTER TABLE` on a `SELECT *` statement.
+    pub fn get_result_metadata(&self) -> Arc<ResultMetadata> {
+        self.result_metadata.load_full()
+    }
+
+    /// Replaces the result metadata of this statement and all of its clones.
+    pub(crate) fn update_result_metadata(&self, result_metadata: Arc<ResultMetadata>) {
+        self.result_metadata.store(result_metadata);
+    }
+
+    /// Checks whether columns described by the metadata received along with
+    /// the result of this statement differ from the ones remembered at prepare time.
+    pub(crate) fn is_result_metadata_outdated(&self, received: &ResultMetadata) -> bool {
+        // The metadata might be omitted by the server, there's nothing to compare then
+        if received.col_specs.is_empty() {
+            return false;
+        }
+
+        // Results of LWT statements are prepared without metadata, and the returned columns
+        // depend on whether the condition was applied - they aren't a schema change
+        let current = self.result_metadata.load();
+        if current.col_specs.is_empty() || received.col_specs[0].name == "[applied]" {
+            return false;
+        }
+
+        current.col_specs != received.col_specs
+    }
+
     /// Get the name of the partitioner used for this statement.
     pub(crate) fn get_partitioner_name(&self) -> &PartitionerName {
         &self.partitioner_name
@@ -291,6 +433,56 @@ impl PreparedStatement {
     }
 }
 
+/// Prepared statement tied to the type of its bound values `V` and the type of its result rows `R`.
+///
+/// The types are checked against metadata of the statement when it's created,
+/// so a mismatch is detected once the statement is prepared instead of when it's executed.\
+/// Created with [`Session::prepare_typed`](crate::Session::prepare_typed),
+/// statements which don't return rows can use `()` as `R`.
+#[derive(Debug)]
+pub struct TypedPreparedStatement<V, R> {
+    prepared: PreparedStatement,
+    _types: PhantomData<fn(V) -> R>,
+}
+
+impl<V, R> Clone for TypedPreparedStatement<V, R> {
+    fn clone(&self) -> Self {
+        Self {
+            prepared: self.prepared.clone(),
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<V: ValueList, R: FromRow> TypedPreparedStatement<V, R> {
+    /// Checks that `V` matches the bound values and `R` matches the result columns of the statement
+    pub fn new(prepared: PreparedStatement) -> Result<Self, TypeCheckError> {
+        V::type_check(&prepared.get_prepared_metadata().col_specs)?;
+        R::type_check(&prepared.get_result_metadata().col_specs)?;
+
+        Ok(Self {
+            prepared,
+            _types: PhantomData,
+        })
+    }
+}
+
+impl<V, R> TypedPreparedStatement<V, R> {
+    /// Returns the underlying untyped statement
+    pub fn get_prepared(&self) -> &PreparedStatement {
+        &self.prepared
+    }
+
+    /// Allows to change configuration of the statement, e.g. consistency or page size
+    pub fn get_prepared_mut(&mut self) -> &mut PreparedStatement {
+        &mut self.prepared
+    }
+
+    pub fn into_prepared(self) -> PreparedStatement {
+        self.prepared
+    }
+}
+
 #[derive(Debug, Error, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PartitionKeyError {
     #[error("No value with given pk_index! pk_index: {0}, values.len(): {1}")]
@@ -298,3 +490,132 @@ pub enum PartitionKeyError {
     #[error("Value bytes too long to create partition key, max 65 535 allowed! value.len(): {0}")]
     ValueTooLong(usize),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PreparedStatement, TypedPreparedStatement};
+    use crate::frame::response::result::{
+        ColumnSpec, ColumnType, PreparedMetadata, ResultMetadata, TableSpec,
+    };
+    use crate::frame::value::TypeCheckError;
+    use bytes::Bytes;
+    use std::sync::Arc;
+
+    fn make_result_metadata(columns: &[(&str, ColumnType)]) -> ResultMetadata {
+        let mut metadata = ResultMetadata::default();
+        metadata.col_specs = columns
+            .iter()
+            .map(|(name, typ)| ColumnSpec {
+                table_spec: TableSpec {
+                    ks_name: "ks".to_owned(),
+                    table_name: "t".to_owned(),
+                },
+                name: name.to_string(),
+                typ: typ.clone(),
+            })
+            .collect();
+        metadata
+    }
+
+    fn make_prepared(result_metadata: ResultMetadata) -> PreparedStatement {
+        PreparedStatement::new(
+            Bytes::from_static(b"id"),
+            PreparedMetadata::new(0, vec![], vec![]),
+            result_metadata,
+            "SELECT * FROM ks.t".to_owned(),
+            None,
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn detects_result_metadata_change() {
+        let prepared = make_prepared(make_result_metadata(&[("a", ColumnType::Int)]));
+
+        let same = make_result_metadata(&[("a", ColumnType::Int)]);
+        assert!(!prepared.is_result_metadata_outdated(&same));
+
+        // The server may omit the metadata, it's not a change
+        assert!(!prepared.is_result_metadata_outdated(&ResultMetadata::default()));
+
+        let added_column = make_result_metadata(&[("a", ColumnType::Int), ("b", ColumnType::Text)]);
+        assert!(prepared.is_result_metadata_outdated(&added_column));
+
+        let changed_type = make_result_metadata(&[("a", ColumnType::BigInt)]);
+        assert!(prepared.is_result_metadata_outdated(&changed_type));
+    }
+
+    #[test]
+    fn lwt_result_is_not_a_metadata_change() {
+        let prepared = make_prepared(ResultMetadata::default());
+
+        let not_applied = make_result_metadata(&[
+            ("[applied]", ColumnType::Boolean),
+            ("a", ColumnType::Int),
+            ("b", ColumnType::Text),
+        ]);
+        assert!(!prepared.is_result_metadata_outdated(&not_applied));
+
+        let applied = make_result_metadata(&[("[applied]", ColumnType::Boolean)]);
+        assert!(!prepared.is_result_metadata_outdated(&applied));
+
+        // Conditional statements prepared with metadata, e.g. by some other server versions
+        let prepared = make_prepared(applied);
+        assert!(!prepared.is_result_metadata_outdated(&not_applied));
+    }
+
+    #[test]
+    fn result_metadata_update_is_shared_between_clones() {
+        let prepared = make_prepared(make_result_metadata(&[("a", ColumnType::Int)]));
+        let cloned = prepared.clone();
+
+        let new_metadata = make_result_metadata(&[("a", ColumnType::Int), ("b", ColumnType::Text)]);
+        prepared.update_result_metadata(Arc::new(new_metadata.clone()));
+
+        assert_eq!(
+            cloned.get_result_metadata().col_specs,
+            new_metadata.col_specs
+        );
+        assert!(!cloned.is_result_metadata_outdated(&new_metadata));
+    }
+
+    #[test]
+    fn typed_statement_checks_types() {
+        let bound = make_result_metadata(&[("a", ColumnType::Int)]).col_specs;
+        let prepared = PreparedStatement::new(
+            Bytes::from_static(b"id"),
+            PreparedMetadata::new(1, vec![], bound),
+            make_result_metadata(&[("a", ColumnType::Int), ("b", ColumnType::Text)]),
+            "SELECT a, b FROM ks.t WHERE a = ?".to_owned(),
+            None,
+            Default::default(),
+        );
+
+        assert!(
+            TypedPreparedStatement::<(i32,), (i32, Option<String>)>::new(prepared.clone()).is_ok()
+        );
+
+        assert_eq!(
+            TypedPreparedStatement::<(i32, i32), (i32, String)>::new(prepared.clone()).err(),
+            Some(TypeCheckError::WrongValuesCount {
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert!(matches!(
+            TypedPreparedStatement::<(String,), (i32, String)>::new(prepared.clone()),
+            Err(TypeCheckError::IncompatibleType { name, .. }) if name == "a"
+        ));
+        assert_eq!(
+            TypedPreparedStatement::<(i32,), (i32,)>::new(prepared.clone()).err(),
+            Some(TypeCheckError::WrongRowSize {
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert!(matches!(
+            TypedPreparedStatement::<(i32,), (i32, i64)>::new(prepared),
+            Err(TypeCheckError::IncompatibleType { name, .. }) if name == "b"
+        ));
+    }
+}
@@ -1,7 +1,9 @@
 use super::StatementConfig;
 use crate::frame::types::{Consistency, SerialConsistency};
 use crate::history::HistoryListener;
+use crate::routing::Shard;
 use crate::transport::retry_policy::RetryPolicy;
+use crate::transport::Node;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -76,8 +78,11 @@ impl Query {
     /// If set to `true` we can be sure that it is idempotent
     /// If set to `false` it is unknown whether it is idempotent
     /// This is used in [`RetryPolicy`] to decide if retrying a query is safe
+    /// The value set here takes precedence over the idempotence inferred by the session,
+    /// see [`SessionBuilder::infer_idempotence`](crate::SessionBuilder::infer_idempotence)
     pub fn set_is_idempotent(&mut self, is_idempotent: bool) {
         self.config.is_idempotent = is_idempotent;
+        self.config.is_idempotent_explicit = true;
     }
 
     /// Gets the idempotence of this statement
@@ -108,6 +113,56 @@ impl Query {
         self.config.tracing
     }
 
+    /// Sets the number of pages fetched ahead of the page being consumed
+    /// when iterating with `Session::query_iter`. With 0 the next page is fetched
+    /// only after the current one is consumed. Defaults to 1.
+    pub fn set_prefetch_pages(&mut self, prefetch_pages: usize) {
+        self.config.prefetch_pages = prefetch_pages;
+    }
+
+    /// Gets the number of pages fetched ahead when iterating over this statement
+    pub fn get_prefetch_pages(&self) -> usize {
+        self.config.prefetch_pages
+    }
+
+    /// Limits the total size in bytes of pages buffered by `Session::query_iter`,
+    /// fetching is paused when it's exceeded until pages are consumed.
+    /// The page being consumed is counted as well, so at least one page is always buffered.
+    /// `None` (default) means no limit other than [`set_prefetch_pages`](Self::set_prefetch_pages).
+    pub fn set_page_buffer_limit(&mut self, limit: Option<usize>) {
+        self.config.page_buffer_limit = limit;
+    }
+
+    /// Gets the limit of bytes buffered when iterating over this statement
+    pub fn get_page_buffer_limit(&self) -> Option<usize> {
+        self.config.page_buffer_limit
+    }
+
+    /// Sets the timeout of fetching a single page by `Session::query_iter`.
+    /// A page which isn't received in time fails with `QueryError::RequestTimeout`,
+    /// which is handled by the retry policy. `None` (default) means no timeout.
+    pub fn set_page_timeout(&mut self, timeout: Option<Duration>) {
+        self.config.page_timeout = timeout;
+    }
+
+    /// Gets the timeout of fetching a single page of this statement
+    pub fn get_page_timeout(&self) -> Option<Duration> {
+        self.config.page_timeout
+    }
+
+    /// Enable or disable sending `None` values as unset instead of null.
+    /// Unset values leave the existing data untouched, while null values
+    /// delete it and create tombstones. Disabled by default.\
+    /// Applies to all null values bound to this statement, also when it is executed in a batch.
+    pub fn set_none_as_unset(&mut self, none_as_unset: bool) {
+        self.config.none_as_unset = none_as_unset;
+    }
+
+    /// Gets whether `None` values are sent as unset for this statement
+    pub fn get_none_as_unset(&self) -> bool {
+        self.config.none_as_unset
+    }
+
     /// Sets the default timestamp for this statement in microseconds.
     /// If not None, it will replace the server side assigned timestamp as default timestamp
     /// If a statement contains a `USING TIMESTAMP` clause, calling this method won't change
@@ -134,6 +189,47 @@ impl Query {
         self.config.request_timeout
     }
 
+    /// Sets the node to which this statement is sent, bypassing the load balancing policy.
+    /// Useful for querying node-local tables, like `system.local` or virtual tables.
+    /// If the node can't be reached, the request fails instead of being sent to another node.
+    /// Nodes can be obtained from [`ClusterData::get_nodes_info`](crate::transport::ClusterData::get_nodes_info).
+    pub fn set_target_node(&mut self, node: Option<Arc<Node>>) {
+        self.config.target_node = node;
+    }
+
+    /// Gets the node to which this statement is sent
+    pub fn get_target_node(&self) -> Option<&Arc<Node>> {
+        self.config.target_node.as_ref()
+    }
+
+    /// Sets the shard to which this statement is sent. Only used together with a target node,
+    /// see [`set_target_node`](Self::set_target_node). If there is no working connection to the shard,
+    /// the request fails instead of being sent to another shard.
+    pub fn set_target_shard(&mut self, shard: Option<Shard>) {
+        self.config.target_shard = shard;
+    }
+
+    /// Gets the shard to which this statement is sent
+    pub fn get_target_shard(&self) -> Option<Shard> {
+        self.config.target_shard
+    }
+
+    /// Sets the server-side timeout for this statement.
+    /// If not None, the driver appends `USING TIMEOUT` to `SELECT`, `INSERT`, `UPDATE`, `DELETE`
+    /// and `BEGIN BATCH` statements, so that Scylla stops working on the request after `timeout`.
+    /// The client-side timeout is extended if needed, so that the server gives up first.
+    /// When the query is prepared, the timeout becomes a part of the prepared statement.\
+    /// The timeout is not applied when the query is a part of a [`Batch`](crate::batch::Batch) -
+    /// a batch request can't carry a server-side timeout, use a `BEGIN BATCH` query instead.
+    pub fn set_server_timeout(&mut self, timeout: Option<Duration>) {
+        self.config.server_timeout = timeout
+    }
+
+    /// Gets server timeout associated with this query
+    pub fn get_server_timeout(&self) -> Option<Duration> {
+        self.config.server_timeout
+    }
+
     /// Sets the listener capable of listening what happens during query execution.
     pub fn set_history_listener(&mut self, history_listener: Arc<dyn HistoryListener>) {
         self.config.history_listener = Some(history_listener);
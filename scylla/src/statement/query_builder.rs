@@ -0,0 +1,981 @@
+//! Builders for CQL statements with typed clauses
+//!
+//! Builders produce a [`BuiltQuery`] - a [`Query`] containing bind markers
+//! together with [`SerializedValues`] bound to them, which can be passed to [`Session::query`](crate::Session::query).\
+//! All identifiers are quoted, so they are case sensitive and can't be used to inject CQL.
+//!
+//! ```rust
+//! # use scylla::Session;
+//! # use std::error::Error;
+//! # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+//! use scylla::query_builder::{Column, Select};
+//!
+//! let mut select = Select::new("events").keyspace("ks").columns(["id", "payload"]);
+//! select = select.where_(Column::new("user_id").eq(1));
+//! if let Some(days) = Some(vec!["monday", "tuesday"]) {
+//!     // SELECT "id", "payload" FROM "ks"."events" WHERE "user_id" = ? AND "day" IN ?
+//!     select = select.where_(Column::new("day").in_(days));
+//! }
+//!
+//! let built = select.limit(100).build()?;
+//! session.query(built.query, built.values).await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::batch::{Batch, BatchType};
+use crate::frame::value::{SerializeValuesError, SerializedValues, Value, ValueTooBig};
+use crate::query::Query;
+use crate::transport::topology::Table;
+use crate::transport::ClusterData;
+use thiserror::Error;
+
+/// Statement built by one of the builders, ready to be executed
+#[derive(Clone)]
+pub struct BuiltQuery {
+    /// Statement text containing bind markers
+    pub query: Query,
+    /// Values for bind markers of the statement, in order
+    pub values: SerializedValues,
+}
+
+/// Batch built by [`BatchBuilder`], ready to be executed with [`Session::batch`](crate::Session::batch)
+pub struct BuiltBatch {
+    pub batch: Batch,
+    /// Values for each statement of the batch, in order
+    pub values: Vec<SerializedValues>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum QueryBuilderError {
+    #[error("Serializing values failed: {0}")]
+    SerializeValues(#[from] SerializeValuesError),
+    #[error("{statement} statement requires {clause}")]
+    MissingClause {
+        statement: &'static str,
+        clause: &'static str,
+    },
+    #[error("Statement has no keyspace set, it can't be validated against cluster metadata")]
+    NoKeyspace,
+    #[error("Keyspace {0} not found in cluster metadata")]
+    UnknownKeyspace(String),
+    #[error("Table {keyspace}.{table} not found in cluster metadata")]
+    UnknownTable { keyspace: String, table: String },
+    #[error("Column {column} not found in table {keyspace}.{table}")]
+    UnknownColumn {
+        keyspace: String,
+        table: String,
+        column: String,
+    },
+}
+
+/// Quotes a CQL identifier, so that it's case sensitive and can contain any characters
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Value serialized when it's passed to a builder, so that builders don't borrow values
+#[derive(Debug, Clone)]
+struct BoundValue(Result<Vec<u8>, SerializeValuesError>);
+
+impl BoundValue {
+    fn new(value: impl Value) -> Self {
+        let mut buf = Vec::new();
+        BoundValue(
+            value
+                .serialize(&mut buf)
+                .map(|()| buf)
+                .map_err(SerializeValuesError::from),
+        )
+    }
+}
+
+/// Already serialized value, written as is
+struct RawValue<'a>(&'a [u8]);
+
+impl Value for RawValue<'_> {
+    fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), ValueTooBig> {
+        buf.extend_from_slice(self.0);
+        Ok(())
+    }
+}
+
+/// Accumulates statement text and values of its bind markers
+struct StatementWriter {
+    cql: String,
+    values: SerializedValues,
+}
+
+impl StatementWriter {
+    fn new(cql: &str) -> Self {
+        StatementWriter {
+            cql: cql.to_string(),
+            values: SerializedValues::new(),
+        }
+    }
+
+    fn push(&mut self, cql: &str) {
+        self.cql.push_str(cql);
+    }
+
+    fn bind(&mut self, value: &BoundValue) -> Result<(), QueryBuilderError> {
+        let serialized = value.0.as_ref().map_err(|e| *e)?;
+        self.values.add_value(&RawValue(serialized))?;
+        self.cql.push('?');
+        Ok(())
+    }
+
+    fn bind_value(&mut self, value: impl Value) -> Result<(), QueryBuilderError> {
+        self.bind(&BoundValue::new(value))
+    }
+
+    fn relations(
+        &mut self,
+        keyword: &str,
+        relations: &[Relation],
+    ) -> Result<(), QueryBuilderError> {
+        for (i, relation) in relations.iter().enumerate() {
+            self.push(if i == 0 { keyword } else { " AND " });
+            self.push(&quote_identifier(&relation.column));
+            self.push(" ");
+            self.push(relation.operator);
+            self.push(" ");
+            self.bind(&relation.value)?;
+        }
+        Ok(())
+    }
+
+    fn using(&mut self, ttl: Option<i32>, timestamp: Option<i64>) -> Result<(), QueryBuilderError> {
+        if let Some(ttl) = ttl {
+            self.push(" USING TTL ");
+            self.bind_value(ttl)?;
+        }
+        if let Some(timestamp) = timestamp {
+            self.push(if ttl.is_some() {
+                " AND TIMESTAMP "
+            } else {
+                " USING TIMESTAMP "
+            });
+            self.bind_value(timestamp)?;
+        }
+        Ok(())
+    }
+
+    fn conditions(
+        &mut self,
+        if_exists: bool,
+        conditions: &[Relation],
+    ) -> Result<(), QueryBuilderError> {
+        if if_exists {
+            self.push(" IF EXISTS");
+        }
+        self.relations(" IF ", conditions)
+    }
+
+    fn finish(self) -> BuiltQuery {
+        BuiltQuery {
+            query: Query::new(self.cql),
+            values: self.values,
+        }
+    }
+}
+
+/// Table name together with an optional keyspace
+#[derive(Debug, Clone)]
+struct TableRef {
+    keyspace: Option<String>,
+    name: String,
+}
+
+impl TableRef {
+    fn new(name: impl Into<String>) -> Self {
+        TableRef {
+            keyspace: None,
+            name: name.into(),
+        }
+    }
+
+    fn quoted(&self) -> String {
+        match &self.keyspace {
+            Some(keyspace) => format!(
+                "{}.{}",
+                quote_identifier(keyspace),
+                quote_identifier(&self.name)
+            ),
+            None => quote_identifier(&self.name),
+        }
+    }
+
+    fn validate<'a>(
+        &self,
+        cluster_data: &ClusterData,
+        columns: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), QueryBuilderError> {
+        let keyspace_name = self
+            .keyspace
+            .as_ref()
+            .ok_or(QueryBuilderError::NoKeyspace)?;
+        let keyspace = cluster_data
+            .get_keyspace_info()
+            .get(keyspace_name)
+            .ok_or_else(|| QueryBuilderError::UnknownKeyspace(keyspace_name.clone()))?;
+        let table: &Table = keyspace
+            .tables
+            .get(&self.name)
+            .or_else(|| {
+                keyspace
+                    .views
+                    .get(&self.name)
+                    .map(|view| &view.view_metadata)
+            })
+            .ok_or_else(|| QueryBuilderError::UnknownTable {
+                keyspace: keyspace_name.clone(),
+                table: self.name.clone(),
+            })?;
+
+        for column in columns {
+            if !table.columns.contains_key(column) {
+                return Err(QueryBuilderError::UnknownColumn {
+                    keyspace: keyspace_name.clone(),
+                    table: self.name.clone(),
+                    column: column.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Column used to create relations in `WHERE` and `IF` clauses
+#[derive(Debug, Clone)]
+pub struct Column {
+    name: String,
+}
+
+/// Relation between a column and a bound value, e.g. `"a" = ?`
+#[derive(Debug, Clone)]
+pub struct Relation {
+    column: String,
+    operator: &'static str,
+    value: BoundValue,
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>) -> Self {
+        Column { name: name.into() }
+    }
+
+    fn relation(self, operator: &'static str, value: impl Value) -> Relation {
+        Relation {
+            column: self.name,
+            operator,
+            value: BoundValue::new(value),
+        }
+    }
+
+    pub fn eq(self, value: impl Value) -> Relation {
+        self.relation("=", value)
+    }
+
+    /// Allowed only in `IF` conditions
+    pub fn ne(self, value: impl Value) -> Relation {
+        self.relation("!=", value)
+    }
+
+    pub fn lt(self, value: impl Value) -> Relation {
+        self.relation("<", value)
+    }
+
+    pub fn le(self, value: impl Value) -> Relation {
+        self.relation("<=", value)
+    }
+
+    pub fn gt(self, value: impl Value) -> Relation {
+        self.relation(">", value)
+    }
+
+    pub fn ge(self, value: impl Value) -> Relation {
+        self.relation(">=", value)
+    }
+
+    /// `IN` relation, all values are bound as a single list
+    pub fn in_(self, values: impl Value) -> Relation {
+        self.relation("IN", values)
+    }
+
+    /// Checks if a collection contains the value
+    pub fn contains(self, value: impl Value) -> Relation {
+        self.relation("CONTAINS", value)
+    }
+
+    /// Checks if a map contains the key
+    pub fn contains_key(self, key: impl Value) -> Relation {
+        self.relation("CONTAINS KEY", key)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// Builder of a `SELECT` statement
+#[derive(Debug, Clone)]
+pub struct Select {
+    table: TableRef,
+    columns: Vec<String>,
+    relations: Vec<Relation>,
+    order_by: Vec<(String, Order)>,
+    limit: Option<i32>,
+    allow_filtering: bool,
+}
+
+impl Select {
+    /// Selects rows from the table, all columns are selected unless specified with [`columns`](Select::columns)
+    pub fn new(table: impl Into<String>) -> Self {
+        Select {
+            table: TableRef::new(table),
+            columns: Vec::new(),
+            relations: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            allow_filtering: false,
+        }
+    }
+
+    pub fn keyspace(mut self, keyspace: impl Into<String>) -> Self {
+        self.table.keyspace = Some(keyspace.into());
+        self
+    }
+
+    pub fn columns(mut self, columns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.columns.extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds a relation to the `WHERE` clause, relations are joined with `AND`
+    pub fn where_(mut self, relation: Relation) -> Self {
+        self.relations.push(relation);
+        self
+    }
+
+    pub fn order_by(mut self, column: impl Into<String>, order: Order) -> Self {
+        self.order_by.push((column.into(), order));
+        self
+    }
+
+    pub fn limit(mut self, limit: i32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn allow_filtering(mut self) -> Self {
+        self.allow_filtering = true;
+        self
+    }
+
+    /// Checks that the table and all columns used in the statement exist in cluster metadata
+    pub fn validate(&self, cluster_data: &ClusterData) -> Result<(), QueryBuilderError> {
+        let columns = self.columns.iter().map(String::as_str);
+        let relations = self.relations.iter().map(|r| r.column.as_str());
+        let order_by = self.order_by.iter().map(|(column, _)| column.as_str());
+        self.table
+            .validate(cluster_data, columns.chain(relations).chain(order_by))
+    }
+
+    pub fn build(&self) -> Result<BuiltQuery, QueryBuilderError> {
+        let mut writer = StatementWriter::new("SELECT ");
+        if self.columns.is_empty() {
+            writer.push("*");
+        } else {
+            writer.push(&join_quoted(&self.columns));
+        }
+        writer.push(" FROM ");
+        writer.push(&self.table.quoted());
+        writer.relations(" WHERE ", &self.relations)?;
+
+        for (i, (column, order)) in self.order_by.iter().enumerate() {
+            writer.push(if i == 0 { " ORDER BY " } else { ", " });
+            writer.push(&quote_identifier(column));
+            writer.push(match order {
+                Order::Asc => " ASC",
+                Order::Desc => " DESC",
+            });
+        }
+        if let Some(limit) = self.limit {
+            writer.push(" LIMIT ");
+            writer.bind_value(limit)?;
+        }
+        if self.allow_filtering {
+            writer.push(" ALLOW FILTERING");
+        }
+        Ok(writer.finish())
+    }
+}
+
+/// Builder of an `INSERT` statement
+#[derive(Debug, Clone)]
+pub struct Insert {
+    table: TableRef,
+    values: Vec<(String, BoundValue)>,
+    if_not_exists: bool,
+    ttl: Option<i32>,
+    timestamp: Option<i64>,
+}
+
+impl Insert {
+    pub fn new(table: impl Into<String>) -> Self {
+        Insert {
+            table: TableRef::new(table),
+            values: Vec::new(),
+            if_not_exists: false,
+            ttl: None,
+            timestamp: None,
+        }
+    }
+
+    pub fn keyspace(mut self, keyspace: impl Into<String>) -> Self {
+        self.table.keyspace = Some(keyspace.into());
+        self
+    }
+
+    /// Sets value of the column
+    pub fn value(mut self, column: impl Into<String>, value: impl Value) -> Self {
+        self.values.push((column.into(), BoundValue::new(value)));
+        self
+    }
+
+    /// Makes the statement a lightweight transaction inserting only if the row doesn't exist
+    pub fn if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Time to live of the inserted values in seconds
+    pub fn ttl(mut self, ttl: i32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Write timestamp in microseconds
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Checks that the table and all columns used in the statement exist in cluster metadata
+    pub fn validate(&self, cluster_data: &ClusterData) -> Result<(), QueryBuilderError> {
+        self.table.validate(
+            cluster_data,
+            self.values.iter().map(|(column, _)| column.as_str()),
+        )
+    }
+
+    pub fn build(&self) -> Result<BuiltQuery, QueryBuilderError> {
+        if self.values.is_empty() {
+            return Err(QueryBuilderError::MissingClause {
+                statement: "INSERT",
+                clause: "at least one value",
+            });
+        }
+
+        let mut writer = StatementWriter::new("INSERT INTO ");
+        writer.push(&self.table.quoted());
+        writer.push(" (");
+        writer.push(&join_quoted(self.values.iter().map(|(column, _)| column)));
+        writer.push(") VALUES (");
+        for (i, (_, value)) in self.values.iter().enumerate() {
+            if i > 0 {
+                writer.push(", ");
+            }
+            writer.bind(value)?;
+        }
+        writer.push(")");
+        if self.if_not_exists {
+            writer.push(" IF NOT EXISTS");
+        }
+        writer.using(self.ttl, self.timestamp)?;
+        Ok(writer.finish())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Assignment {
+    /// `"c" = ?`
+    Set(BoundValue),
+    /// `"c" = "c" + ?`
+    Add(BoundValue),
+    /// `"c" = ? + "c"`
+    Prepend(BoundValue),
+    /// `"c" = "c" - ?`
+    Remove(BoundValue),
+    /// `"c"[?] = ?`
+    SetElement(BoundValue, BoundValue),
+}
+
+/// Builder of an `UPDATE` statement
+#[derive(Debug, Clone)]
+pub struct Update {
+    table: TableRef,
+    assignments: Vec<(String, Assignment)>,
+    relations: Vec<Relation>,
+    if_exists: bool,
+    conditions: Vec<Relation>,
+    ttl: Option<i32>,
+    timestamp: Option<i64>,
+}
+
+impl Update {
+    pub fn new(table: impl Into<String>) -> Self {
+        Update {
+            table: TableRef::new(table),
+            assignments: Vec::new(),
+            relations: Vec::new(),
+            if_exists: false,
+            conditions: Vec::new(),
+            ttl: None,
+            timestamp: None,
+        }
+    }
+
+    pub fn keyspace(mut self, keyspace: impl Into<String>) -> Self {
+        self.table.keyspace = Some(keyspace.into());
+        self
+    }
+
+    /// Sets value of the column
+    pub fn set(mut self, column: impl Into<String>, value: impl Value) -> Self {
+        self.assignments
+            .push((column.into(), Assignment::Set(BoundValue::new(value))));
+        self
+    }
+
+    /// Appends to a list, adds elements to a set or map, or increments a counter
+    pub fn add(mut self, column: impl Into<String>, value: impl Value) -> Self {
+        self.assignments
+            .push((column.into(), Assignment::Add(BoundValue::new(value))));
+        self
+    }
+
+    /// Prepends elements to a list
+    pub fn prepend(mut self, column: impl Into<String>, value: impl Value) -> Self {
+        self.assignments
+            .push((column.into(), Assignment::Prepend(BoundValue::new(value))));
+        self
+    }
+
+    /// Removes elements from a list or set, keys from a map, or decrements a counter
+    pub fn remove(mut self, column: impl Into<String>, value: impl Value) -> Self {
+        self.assignments
+            .push((column.into(), Assignment::Remove(BoundValue::new(value))));
+        self
+    }
+
+    /// Sets a list element at the index or a map entry with the key
+    pub fn set_element(
+        mut self,
+        column: impl Into<String>,
+        key: impl Value,
+        value: impl Value,
+    ) -> Self {
+        self.assignments.push((
+            column.into(),
+            Assignment::SetElement(BoundValue::new(key), BoundValue::new(value)),
+        ));
+        self
+    }
+
+    /// Adds a relation to the `WHERE` clause, relations are joined with `AND`
+    pub fn where_(mut self, relation: Relation) -> Self {
+        self.relations.push(relation);
+        self
+    }
+
+    /// Adds a condition to the `IF` clause, making the statement a lightweight transaction
+    pub fn if_(mut self, condition: Relation) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Makes the statement a lightweight transaction updating only if the row exists
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+
+    /// Time to live of the updated values in seconds
+    pub fn ttl(mut self, ttl: i32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Write timestamp in microseconds
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Checks that the table and all columns used in the statement exist in cluster metadata
+    pub fn validate(&self, cluster_data: &ClusterData) -> Result<(), QueryBuilderError> {
+        let assignments = self.assignments.iter().map(|(column, _)| column.as_str());
+        let relations = self.relations.iter().chain(self.conditions.iter());
+        self.table.validate(
+            cluster_data,
+            assignments.chain(relations.map(|r| r.column.as_str())),
+        )
+    }
+
+    pub fn build(&self) -> Result<BuiltQuery, QueryBuilderError> {
+        if self.assignments.is_empty() {
+            return Err(QueryBuilderError::MissingClause {
+                statement: "UPDATE",
+                clause: "at least one assignment",
+            });
+        }
+        if self.relations.is_empty() {
+            return Err(QueryBuilderError::MissingClause {
+                statement: "UPDATE",
+                clause: "a WHERE clause",
+            });
+        }
+
+        let mut writer = StatementWriter::new("UPDATE ");
+        writer.push(&self.table.quoted());
+        writer.using(self.ttl, self.timestamp)?;
+        for (i, (column, assignment)) in self.assignments.iter().enumerate() {
+            writer.push(if i == 0 { " SET " } else { ", " });
+            let column = quote_identifier(column);
+            match assignment {
+                Assignment::Set(value) => {
+                    writer.push(&format!("{} = ", column));
+                    writer.bind(value)?;
+                }
+                Assignment::Add(value) => {
+                    writer.push(&format!("{} = {} + ", column, column));
+                    writer.bind(value)?;
+                }
+                Assignment::Prepend(value) => {
+                    writer.push(&format!("{} = ", column));
+                    writer.bind(value)?;
+                    writer.push(&format!(" + {}", column));
+                }
+                Assignment::Remove(value) => {
+                    writer.push(&format!("{} = {} - ", column, column));
+                    writer.bind(value)?;
+                }
+                Assignment::SetElement(key, value) => {
+                    writer.push(&format!("{}[", column));
+                    writer.bind(key)?;
+                    writer.push("] = ");
+                    writer.bind(value)?;
+                }
+            }
+        }
+        writer.relations(" WHERE ", &self.relations)?;
+        writer.conditions(self.if_exists, &self.conditions)?;
+        Ok(writer.finish())
+    }
+}
+
+/// Builder of a `DELETE` statement
+#[derive(Debug, Clone)]
+pub struct Delete {
+    table: TableRef,
+    columns: Vec<String>,
+    relations: Vec<Relation>,
+    if_exists: bool,
+    conditions: Vec<Relation>,
+    timestamp: Option<i64>,
+}
+
+impl Delete {
+    /// Deletes whole rows, unless columns are specified with [`columns`](Delete::columns)
+    pub fn new(table: impl Into<String>) -> Self {
+        Delete {
+            table: TableRef::new(table),
+            columns: Vec::new(),
+            relations: Vec::new(),
+            if_exists: false,
+            conditions: Vec::new(),
+            timestamp: None,
+        }
+    }
+
+    pub fn keyspace(mut self, keyspace: impl Into<String>) -> Self {
+        self.table.keyspace = Some(keyspace.into());
+        self
+    }
+
+    /// Deletes only values of the columns
+    pub fn columns(mut self, columns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.columns.extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds a relation to the `WHERE` clause, relations are joined with `AND`
+    pub fn where_(mut self, relation: Relation) -> Self {
+        self.relations.push(relation);
+        self
+    }
+
+    /// Adds a condition to the `IF` clause, making the statement a lightweight transaction
+    pub fn if_(mut self, condition: Relation) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Makes the statement a lightweight transaction deleting only if the row exists
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+
+    /// Write timestamp in microseconds
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Checks that the table and all columns used in the statement exist in cluster metadata
+    pub fn validate(&self, cluster_data: &ClusterData) -> Result<(), QueryBuilderError> {
+        let columns = self.columns.iter().map(String::as_str);
+        let relations = self.relations.iter().chain(self.conditions.iter());
+        self.table.validate(
+            cluster_data,
+            columns.chain(relations.map(|r| r.column.as_str())),
+        )
+    }
+
+    pub fn build(&self) -> Result<BuiltQuery, QueryBuilderError> {
+        if self.relations.is_empty() {
+            return Err(QueryBuilderError::MissingClause {
+                statement: "DELETE",
+                clause: "a WHERE clause",
+            });
+        }
+
+        let mut writer = StatementWriter::new("DELETE ");
+        if !self.columns.is_empty() {
+            writer.push(&join_quoted(&self.columns));
+            writer.push(" ");
+        }
+        writer.push("FROM ");
+        writer.push(&self.table.quoted());
+        writer.using(None, self.timestamp)?;
+        writer.relations(" WHERE ", &self.relations)?;
+        writer.conditions(self.if_exists, &self.conditions)?;
+        Ok(writer.finish())
+    }
+}
+
+/// Builder of a [`Batch`] consisting of built statements
+pub struct BatchBuilder {
+    batch: Batch,
+    values: Vec<SerializedValues>,
+}
+
+impl BatchBuilder {
+    pub fn new(batch_type: BatchType) -> Self {
+        BatchBuilder {
+            batch: Batch::new(batch_type),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn append(mut self, statement: BuiltQuery) -> Self {
+        self.batch.append_statement(statement.query);
+        self.values.push(statement.values);
+        self
+    }
+
+    pub fn build(self) -> BuiltBatch {
+        BuiltBatch {
+            batch: self.batch,
+            values: self.values,
+        }
+    }
+}
+
+fn join_quoted<'a>(identifiers: impl IntoIterator<Item = &'a String>) -> String {
+    identifiers
+        .into_iter()
+        .map(|identifier| quote_identifier(identifier))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        quote_identifier, BatchBuilder, Column, Delete, Insert, Order, QueryBuilderError, Select,
+        Update,
+    };
+    use crate::batch::{BatchStatement, BatchType};
+    use crate::frame::value::SerializedValues;
+
+    macro_rules! values {
+        ($($value:expr),*) => {{
+            let mut result = SerializedValues::new();
+            $(result.add_value(&$value).unwrap();)*
+            result
+        }};
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote_identifier("a"), "\"a\"");
+        assert_eq!(quote_identifier("MyTable"), "\"MyTable\"");
+        assert_eq!(quote_identifier("a\"; DROP"), "\"a\"\"; DROP\"");
+    }
+
+    #[test]
+    fn select() {
+        let built = Select::new("t").build().unwrap();
+        assert_eq!(built.query.contents, "SELECT * FROM \"t\"");
+        assert!(built.values.is_empty());
+
+        let built = Select::new("t")
+            .keyspace("ks")
+            .columns(["a", "b"])
+            .where_(Column::new("a").eq(1))
+            .where_(Column::new("b").in_(vec![2, 3]))
+            .where_(Column::new("c").contains("x"))
+            .order_by("b", Order::Desc)
+            .limit(10)
+            .allow_filtering()
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.query.contents,
+            "SELECT \"a\", \"b\" FROM \"ks\".\"t\" WHERE \"a\" = ? AND \"b\" IN ? AND \"c\" CONTAINS ? \
+             ORDER BY \"b\" DESC LIMIT ? ALLOW FILTERING"
+        );
+        assert_eq!(built.values, values![1, vec![2, 3], "x", 10]);
+    }
+
+    #[test]
+    fn insert() {
+        let built = Insert::new("t")
+            .value("a", 1)
+            .value("b", "text")
+            .if_not_exists()
+            .ttl(60)
+            .timestamp(1000)
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.query.contents,
+            "INSERT INTO \"t\" (\"a\", \"b\") VALUES (?, ?) IF NOT EXISTS USING TTL ? AND TIMESTAMP ?"
+        );
+        assert_eq!(built.values, values![1, "text", 60, 1000_i64]);
+
+        assert_eq!(
+            Insert::new("t").build().err(),
+            Some(QueryBuilderError::MissingClause {
+                statement: "INSERT",
+                clause: "at least one value"
+            })
+        );
+    }
+
+    #[test]
+    fn update() {
+        let built = Update::new("t")
+            .keyspace("ks")
+            .timestamp(1000)
+            .set("a", 1)
+            .add("tags", vec!["x"])
+            .prepend("list", vec![2])
+            .remove("counter", 3_i64)
+            .set_element("map", "key", "value")
+            .where_(Column::new("pk").eq(4))
+            .if_(Column::new("a").ne(5))
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.query.contents,
+            "UPDATE \"ks\".\"t\" USING TIMESTAMP ? SET \"a\" = ?, \"tags\" = \"tags\" + ?, \
+             \"list\" = ? + \"list\", \"counter\" = \"counter\" - ?, \"map\"[?] = ? \
+             WHERE \"pk\" = ? IF \"a\" != ?"
+        );
+        assert_eq!(
+            built.values,
+            values![1000_i64, 1, vec!["x"], vec![2], 3_i64, "key", "value", 4, 5]
+        );
+
+        let built = Update::new("t")
+            .set("a", 1)
+            .where_(Column::new("pk").eq(2))
+            .if_exists()
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.query.contents,
+            "UPDATE \"t\" SET \"a\" = ? WHERE \"pk\" = ? IF EXISTS"
+        );
+
+        assert!(matches!(
+            Update::new("t").set("a", 1).build(),
+            Err(QueryBuilderError::MissingClause { .. })
+        ));
+    }
+
+    #[test]
+    fn delete() {
+        let built = Delete::new("t")
+            .where_(Column::new("pk").eq(1))
+            .where_(Column::new("ck").ge(2))
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.query.contents,
+            "DELETE FROM \"t\" WHERE \"pk\" = ? AND \"ck\" >= ?"
+        );
+        assert_eq!(built.values, values![1, 2]);
+
+        let built = Delete::new("t")
+            .columns(["a"])
+            .timestamp(1000)
+            .where_(Column::new("pk").eq(1))
+            .if_(Column::new("a").eq(2))
+            .if_(Column::new("b").lt(3))
+            .build()
+            .unwrap();
+        assert_eq!(
+            built.query.contents,
+            "DELETE \"a\" FROM \"t\" USING TIMESTAMP ? WHERE \"pk\" = ? IF \"a\" = ? AND \"b\" < ?"
+        );
+        assert_eq!(built.values, values![1000_i64, 1, 2, 3]);
+    }
+
+    #[test]
+    fn batch() {
+        let built = BatchBuilder::new(BatchType::Logged)
+            .append(Insert::new("t").value("a", 1).build().unwrap())
+            .append(
+                Delete::new("t")
+                    .where_(Column::new("a").eq(2))
+                    .build()
+                    .unwrap(),
+            )
+            .build();
+
+        let statements: Vec<&str> = built
+            .batch
+            .statements
+            .iter()
+            .map(|statement| match statement {
+                BatchStatement::Query(query) => query.contents.as_str(),
+                BatchStatement::PreparedStatement(_) => panic!("unexpected prepared statement"),
+            })
+            .collect();
+        assert_eq!(
+            statements,
+            [
+                "INSERT INTO \"t\" (\"a\") VALUES (?)",
+                "DELETE FROM \"t\" WHERE \"a\" = ?"
+            ]
+        );
+        assert_eq!(built.values, [values![1], values![2]]);
+    }
+}
@@ -0,0 +1,234 @@
+//! Conveying server-side timeouts to Scylla with the `USING TIMEOUT` clause
+
+use super::cql_lexer::{tokenize, TokenKind};
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// How much longer than the server-side timeout the driver waits for a response,
+/// so that the server gives up on a request before the driver does
+pub const SERVER_TIMEOUT_MARGIN: Duration = Duration::from_millis(100);
+
+/// Returns the client-side timeout extended so that it fires after the server-side timeout
+pub(crate) fn client_timeout_for(
+    client_timeout: Duration,
+    server_timeout: Option<Duration>,
+) -> Duration {
+    match server_timeout {
+        Some(server_timeout) => client_timeout.max(server_timeout + SERVER_TIMEOUT_MARGIN),
+        None => client_timeout,
+    }
+}
+
+/// Adds `USING TIMEOUT` to a `SELECT`, `INSERT`, `UPDATE`, `DELETE` or `BATCH` statement.\
+/// Other statements and statements which already specify a timeout are returned unchanged.
+pub(crate) fn with_server_timeout(contents: &str, timeout: Option<Duration>) -> Cow<'_, str> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Cow::Borrowed(contents),
+    };
+    let timeout_literal = format!("{}ms", timeout.as_millis().max(1));
+
+    let words = top_level_words(contents);
+    let find = |keyword: &str| {
+        words
+            .iter()
+            .position(|(_, word)| word.eq_ignore_ascii_case(keyword))
+    };
+    let has_timeout = words.windows(2).any(|pair| {
+        let (_, previous) = pair[0];
+        let (_, word) = pair[1];
+        word.eq_ignore_ascii_case("TIMEOUT")
+            && (previous.eq_ignore_ascii_case("USING") || previous.eq_ignore_ascii_case("AND"))
+    });
+    let first_word = match words.first() {
+        Some((_, word)) if !has_timeout => word.to_ascii_uppercase(),
+        _ => return Cow::Borrowed(contents),
+    };
+
+    let insert_before = |position: usize| {
+        let (offset, _) = words[position];
+        let clause = match find("USING") {
+            Some(using) if using < position => format!("AND TIMEOUT {} ", timeout_literal),
+            _ => format!("USING TIMEOUT {} ", timeout_literal),
+        };
+        Cow::Owned(format!(
+            "{}{}{}",
+            &contents[..offset],
+            clause,
+            &contents[offset..]
+        ))
+    };
+    // Appended clauses go before a trailing semicolon, whitespace and comments
+    let append = |clause: String| {
+        let end = statement_end(contents);
+        Cow::Owned(format!(
+            "{} {}{}",
+            &contents[..end],
+            clause,
+            &contents[end..]
+        ))
+    };
+
+    match first_word.as_str() {
+        "SELECT" => append(format!("USING TIMEOUT {}", timeout_literal)),
+        "INSERT" => match find("USING") {
+            Some(_) => append(format!("AND TIMEOUT {}", timeout_literal)),
+            None => append(format!("USING TIMEOUT {}", timeout_literal)),
+        },
+        "UPDATE" => match find("SET") {
+            Some(set) => insert_before(set),
+            None => Cow::Borrowed(contents),
+        },
+        "DELETE" => match find("WHERE") {
+            Some(where_) => insert_before(where_),
+            None => Cow::Borrowed(contents),
+        },
+        "BEGIN" => match find("BATCH") {
+            Some(batch) => {
+                let (offset, word) = words[batch];
+                let end = offset + word.len();
+                let clause = match words.get(batch + 1) {
+                    Some((_, next)) if next.eq_ignore_ascii_case("USING") => {
+                        let (using_offset, using) = words[batch + 1];
+                        return Cow::Owned(format!(
+                            "{} TIMEOUT {} AND{}",
+                            &contents[..using_offset + using.len()],
+                            timeout_literal,
+                            &contents[using_offset + using.len()..]
+                        ));
+                    }
+                    _ => format!(" USING TIMEOUT {}", timeout_literal),
+                };
+                Cow::Owned(format!(
+                    "{}{}{}",
+                    &contents[..end],
+                    clause,
+                    &contents[end..]
+                ))
+            }
+            None => Cow::Borrowed(contents),
+        },
+        _ => Cow::Borrowed(contents),
+    }
+}
+
+/// Returns the offset right after the last token of the statement, ignoring a trailing semicolon
+fn statement_end(contents: &str) -> usize {
+    tokenize(contents)
+        .into_iter()
+        .rev()
+        .find(|token| !token.is_symbol(";"))
+        .map(|token| token.offset + token.text.len())
+        .unwrap_or(0)
+}
+
+/// Returns keywords and identifiers which are not nested in brackets, together with their offsets
+fn top_level_words(contents: &str) -> Vec<(usize, &str)> {
+    tokenize(contents)
+        .into_iter()
+        .filter(|token| token.kind == TokenKind::Word && token.depth == 0)
+        .map(|token| (token.offset, token.text))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client_timeout_for, with_server_timeout};
+    use std::time::Duration;
+
+    fn rewrite(contents: &str) -> String {
+        with_server_timeout(contents, Some(Duration::from_millis(250))).into_owned()
+    }
+
+    #[test]
+    fn statements_with_server_timeout() {
+        assert_eq!(
+            rewrite("SELECT a, b FROM ks.t WHERE a = ? LIMIT 10;"),
+            "SELECT a, b FROM ks.t WHERE a = ? LIMIT 10 USING TIMEOUT 250ms;"
+        );
+        assert_eq!(
+            rewrite("INSERT INTO ks.t (a, b) VALUES (?, 'using') IF NOT EXISTS"),
+            "INSERT INTO ks.t (a, b) VALUES (?, 'using') IF NOT EXISTS USING TIMEOUT 250ms"
+        );
+        assert_eq!(
+            rewrite("insert into t (a) values (1) using ttl 10"),
+            "insert into t (a) values (1) using ttl 10 AND TIMEOUT 250ms"
+        );
+        assert_eq!(
+            rewrite("UPDATE ks.t SET b = 'set' WHERE a = 1"),
+            "UPDATE ks.t USING TIMEOUT 250ms SET b = 'set' WHERE a = 1"
+        );
+        assert_eq!(
+            rewrite("UPDATE ks.t USING TTL 5 SET b = ? WHERE a = ?"),
+            "UPDATE ks.t USING TTL 5 AND TIMEOUT 250ms SET b = ? WHERE a = ?"
+        );
+        assert_eq!(
+            rewrite("DELETE b FROM ks.t WHERE a = ?"),
+            "DELETE b FROM ks.t USING TIMEOUT 250ms WHERE a = ?"
+        );
+        assert_eq!(
+            rewrite("DELETE FROM ks.t USING TIMESTAMP 10 WHERE a = ?"),
+            "DELETE FROM ks.t USING TIMESTAMP 10 AND TIMEOUT 250ms WHERE a = ?"
+        );
+        assert_eq!(
+            rewrite("BEGIN BATCH INSERT INTO t (a) VALUES (1); APPLY BATCH"),
+            "BEGIN BATCH USING TIMEOUT 250ms INSERT INTO t (a) VALUES (1); APPLY BATCH"
+        );
+        assert_eq!(
+            rewrite("BEGIN UNLOGGED BATCH USING TIMESTAMP 1 INSERT INTO t (a) VALUES (1); APPLY BATCH"),
+            "BEGIN UNLOGGED BATCH USING TIMEOUT 250ms AND TIMESTAMP 1 INSERT INTO t (a) VALUES (1); APPLY BATCH"
+        );
+    }
+
+    #[test]
+    fn statements_without_server_timeout() {
+        let unchanged = [
+            "SELECT a FROM t USING TIMEOUT 1s",
+            "UPDATE t USING TTL 5 AND TIMEOUT 1s SET b = 1 WHERE a = 1",
+            "CREATE TABLE t (a int PRIMARY KEY)",
+            "USE ks",
+            "",
+        ];
+        for contents in unchanged {
+            assert_eq!(rewrite(contents), contents);
+        }
+
+        assert_eq!(
+            with_server_timeout("SELECT a FROM t", None),
+            "SELECT a FROM t"
+        );
+        // Column named `timeout` is not a timeout clause
+        assert_eq!(
+            rewrite("SELECT timeout FROM t"),
+            "SELECT timeout FROM t USING TIMEOUT 250ms"
+        );
+        // Appended clauses don't end up in a trailing comment
+        assert_eq!(
+            rewrite("SELECT a FROM t -- all rows\n"),
+            "SELECT a FROM t USING TIMEOUT 250ms -- all rows\n"
+        );
+        assert_eq!(
+            rewrite("INSERT INTO t (a) VALUES (1) USING TTL 5; /* ttl */"),
+            "INSERT INTO t (a) VALUES (1) USING TTL 5 AND TIMEOUT 250ms; /* ttl */"
+        );
+        // Keywords in literals, quoted identifiers, comments and parentheses are ignored
+        assert_eq!(
+            rewrite("UPDATE t /* SET */ SET \"where\" = 'SET', m = {'set': 1} WHERE a = 1"),
+            "UPDATE t /* SET */ USING TIMEOUT 250ms SET \"where\" = 'SET', m = {'set': 1} WHERE a = 1"
+        );
+    }
+
+    #[test]
+    fn client_timeout_exceeds_server_timeout() {
+        let second = Duration::from_secs(1);
+        assert_eq!(client_timeout_for(second, None), second);
+        assert_eq!(
+            client_timeout_for(second, Some(Duration::from_millis(200))),
+            second
+        );
+        assert_eq!(
+            client_timeout_for(second, Some(second)),
+            Duration::from_millis(1100)
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Mapping of structs to tables
+use crate::frame::response::cql_to_rust::FromRow;
+use crate::frame::value::{SerializedResult, ValueList};
+
+/// Struct mapped to a table, with each field corresponding to a column.\
+/// Provides statements operating on rows of the table, which can be executed
+/// with the helpers of [`CachingSession`](crate::CachingSession), e.g. [`CachingSession::insert`](crate::CachingSession::insert).
+///
+/// Usually implemented with `#[derive(Table)]`. Values of the struct are bound
+/// to the statements in the order of its fields and rows are read in the same order.
+pub trait Table: ValueList + FromRow {
+    /// Values of the partition key columns followed by the clustering key columns
+    type PrimaryKey: ValueList;
+    /// Values of the partition key columns
+    type PartitionKey: ValueList;
+
+    /// Inserts a row, takes values of all columns
+    const INSERT: &'static str;
+    /// Selects a single row, takes [`PrimaryKey`](Table::PrimaryKey)
+    const SELECT_BY_PRIMARY_KEY: &'static str;
+    /// Selects all rows of a partition, takes [`PartitionKey`](Table::PartitionKey)
+    const SELECT_BY_PARTITION_KEY: &'static str;
+    /// Updates regular columns of a row, takes values returned by [`update_values`](Table::update_values).\
+    /// `None` if the table has no columns other than the primary key.
+    const UPDATE: Option<&'static str>;
+    /// Deletes a row, takes [`PrimaryKey`](Table::PrimaryKey)
+    const DELETE: &'static str;
+
+    fn primary_key(&self) -> Self::PrimaryKey;
+
+    fn partition_key(&self) -> Self::PartitionKey;
+
+    /// Values of the regular columns followed by the primary key, as expected by [`UPDATE`](Table::UPDATE)
+    fn update_values(&self) -> SerializedResult<'_>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as scylla;
+    use crate::frame::value::{SerializedValues, ValueList};
+    use crate::Table;
+
+    #[derive(Table, Clone, Debug, PartialEq)]
+    #[scylla(keyspace = "ks", table = "events")]
+    struct Event {
+        #[partition_key]
+        user_id: i32,
+        #[partition_key]
+        day: String,
+        #[clustering_key]
+        #[scylla(rename = "seq")]
+        sequence: i64,
+        payload: Option<String>,
+        #[scylla(skip)]
+        cached: bool,
+    }
+
+    #[derive(Table)]
+    #[scylla(table = "tags")]
+    struct Tag {
+        #[partition_key]
+        name: String,
+    }
+
+    fn event() -> Event {
+        Event {
+            user_id: 1,
+            day: "monday".to_string(),
+            sequence: 7,
+            payload: Some("hello".to_string()),
+            cached: true,
+        }
+    }
+
+    #[test]
+    fn derived_table_statements() {
+        assert_eq!(
+            Event::INSERT,
+            r#"INSERT INTO "ks"."events" ("user_id", "day", "seq", "payload") VALUES (?, ?, ?, ?)"#
+        );
+        assert_eq!(
+            Event::SELECT_BY_PRIMARY_KEY,
+            r#"SELECT "user_id", "day", "seq", "payload" FROM "ks"."events" WHERE "user_id" = ? AND "day" = ? AND "seq" = ?"#
+        );
+        assert_eq!(
+            Event::SELECT_BY_PARTITION_KEY,
+            r#"SELECT "user_id", "day", "seq", "payload" FROM "ks"."events" WHERE "user_id" = ? AND "day" = ?"#
+        );
+        assert_eq!(
+            Event::UPDATE,
+            Some(
+                r#"UPDATE "ks"."events" SET "payload" = ? WHERE "user_id" = ? AND "day" = ? AND "seq" = ?"#
+            )
+        );
+        assert_eq!(
+            Event::DELETE,
+            r#"DELETE FROM "ks"."events" WHERE "user_id" = ? AND "day" = ? AND "seq" = ?"#
+        );
+
+        assert_eq!(Tag::INSERT, r#"INSERT INTO "tags" ("name") VALUES (?)"#);
+        assert_eq!(Tag::UPDATE, None);
+    }
+
+    #[derive(Table)]
+    #[scylla(keyspace = "MyKeyspace", table = "UserProfiles")]
+    struct UserProfile {
+        #[partition_key]
+        #[scylla(rename = "UserName")]
+        user_name: String,
+        #[scylla(rename = "Nick\"name")]
+        nickname: String,
+    }
+
+    #[test]
+    fn derived_table_quotes_names() {
+        assert_eq!(
+            UserProfile::INSERT,
+            r#"INSERT INTO "MyKeyspace"."UserProfiles" ("UserName", "Nick""name") VALUES (?, ?)"#
+        );
+        assert_eq!(
+            UserProfile::UPDATE,
+            Some(r#"UPDATE "MyKeyspace"."UserProfiles" SET "Nick""name" = ? WHERE "UserName" = ?"#)
+        );
+        assert_eq!(
+            UserProfile::DELETE,
+            r#"DELETE FROM "MyKeyspace"."UserProfiles" WHERE "UserName" = ?"#
+        );
+    }
+
+    #[test]
+    fn derived_table_values() {
+        let event = event();
+
+        assert_eq!(event.primary_key(), (1, "monday".to_string(), 7));
+        assert_eq!(event.partition_key(), (1, "monday".to_string()));
+
+        let mut expected = SerializedValues::new();
+        expected.add_value(&event.payload).unwrap();
+        expected.add_value(&event.user_id).unwrap();
+        expected.add_value(&event.day).unwrap();
+        expected.add_value(&event.sequence).unwrap();
+        assert_eq!(event.update_values().unwrap().into_owned(), expected);
+
+        let mut expected = SerializedValues::new();
+        expected.add_value(&event.user_id).unwrap();
+        expected.add_value(&event.day).unwrap();
+        expected.add_value(&event.sequence).unwrap();
+        expected.add_value(&event.payload).unwrap();
+        assert_eq!(event.serialized().unwrap().into_owned(), expected);
+    }
+}
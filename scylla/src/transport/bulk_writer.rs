@@ -0,0 +1,563 @@
+//! Writing large numbers of rows with a single prepared statement
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use futures::stream::FuturesUnordered;
+use futures::{Stream, StreamExt};
+
+use super::errors::QueryError;
+use crate::batch::{Batch, BatchType};
+use crate::frame::value::{SerializedValues, ValueList};
+use crate::routing::Token;
+use crate::statement::prepared_statement::PreparedStatement;
+use crate::transport::cluster::ClusterData;
+use crate::transport::session::{bind_by_name, none_as_unset, Session};
+
+/// Default maximum number of batches executed at the same time
+pub const DEFAULT_BULK_CONCURRENCY: usize = 16;
+
+/// Default maximum number of rows in a single batch
+pub const DEFAULT_MAX_BATCH_ROWS: usize = 100;
+
+/// Default maximum size of serialized values of a single batch
+pub const DEFAULT_MAX_BATCH_BYTES: usize = 64 * 1024;
+
+/// Default maximum number of rows waiting in incomplete batches
+pub const DEFAULT_MAX_BUFFERED_ROWS: usize = 10_000;
+
+/// How rows are grouped into batches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkGrouping {
+    /// Rows with the same partition key (token) are batched together
+    Partition,
+    /// Rows owned by the same primary replica are batched together
+    Replica,
+}
+
+/// Options of a [`BulkWriter`]
+#[derive(Debug, Clone)]
+pub struct BulkWriterOptions {
+    /// Maximum number of batches executed at the same time.\
+    /// Default: [`DEFAULT_BULK_CONCURRENCY`]
+    pub concurrency: usize,
+
+    /// How rows are grouped into batches.\
+    /// Default: [`BulkGrouping::Replica`]
+    pub grouping: BulkGrouping,
+
+    /// Maximum number of rows in a single batch.\
+    /// Default: [`DEFAULT_MAX_BATCH_ROWS`]
+    pub max_batch_rows: usize,
+
+    /// A batch is executed once its serialized values reach this size.\
+    /// Default: [`DEFAULT_MAX_BATCH_BYTES`]
+    pub max_batch_bytes: usize,
+
+    /// Maximum number of rows waiting in incomplete batches.
+    /// When it is reached, the largest incomplete batch is executed.\
+    /// Default: [`DEFAULT_MAX_BUFFERED_ROWS`]
+    pub max_buffered_rows: usize,
+}
+
+impl Default for BulkWriterOptions {
+    fn default() -> Self {
+        BulkWriterOptions {
+            concurrency: DEFAULT_BULK_CONCURRENCY,
+            grouping: BulkGrouping::Replica,
+            max_batch_rows: DEFAULT_MAX_BATCH_ROWS,
+            max_batch_bytes: DEFAULT_MAX_BATCH_BYTES,
+            max_buffered_rows: DEFAULT_MAX_BUFFERED_ROWS,
+        }
+    }
+}
+
+/// A row which could not be written
+#[derive(Debug, Clone)]
+pub struct FailedRow {
+    /// Position of the row in the written stream, starting from 0
+    pub index: usize,
+    /// Error of the row's batch, or of serializing the row
+    pub error: QueryError,
+}
+
+/// Summary of a [`BulkWriter::write`] call
+#[derive(Debug, Clone, Default)]
+pub struct BulkWriteReport {
+    /// Number of rows written successfully
+    pub rows_written: usize,
+    /// Size of serialized values of rows written successfully
+    pub bytes_written: usize,
+    /// Number of executed batches, including failed ones.
+    /// A batch containing a single row is executed as a single statement.
+    pub batches: usize,
+    /// Rows which could not be written
+    pub failed_rows: Vec<FailedRow>,
+    /// Duration of the whole write
+    pub elapsed: Duration,
+}
+
+impl BulkWriteReport {
+    /// Returns true if all rows were written
+    pub fn is_success(&self) -> bool {
+        self.failed_rows.is_empty()
+    }
+
+    /// Average number of rows written per second
+    pub fn rows_per_second(&self) -> f64 {
+        per_second(self.rows_written, self.elapsed)
+    }
+
+    /// Average size of values written per second
+    pub fn bytes_per_second(&self) -> f64 {
+        per_second(self.bytes_written, self.elapsed)
+    }
+
+    fn record(&mut self, outcome: BatchOutcome) {
+        self.batches += 1;
+        match outcome.result {
+            Ok(()) => {
+                self.rows_written += outcome.indexes.len();
+                self.bytes_written += outcome.bytes;
+            }
+            Err(error) => self
+                .failed_rows
+                .extend(outcome.indexes.into_iter().map(|index| FailedRow {
+                    index,
+                    error: error.clone(),
+                })),
+        }
+    }
+}
+
+fn per_second(amount: usize, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 {
+        amount as f64 / seconds
+    } else {
+        0.0
+    }
+}
+
+/// Writes a stream of rows with a single prepared statement.
+///
+/// The token of every row is computed using the statement's partitioner and rows are grouped
+/// by partition or by primary replica (see [`BulkGrouping`]) into unlogged batches.
+/// A batch is executed when it reaches [`BulkWriterOptions::max_batch_rows`] rows
+/// or [`BulkWriterOptions::max_batch_bytes`] bytes, with at most
+/// [`BulkWriterOptions::concurrency`] batches executed at the same time.
+/// Replicas are looked up in the cluster metadata, which is refreshed after every executed batch.
+///
+/// Batches are routed to replicas owning their first row and use the configuration
+/// of the prepared statement, including its consistency and retry policy.
+///
+/// # Example
+/// ```rust
+/// # use scylla::Session;
+/// # use std::error::Error;
+/// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+/// use scylla::transport::bulk_writer::BulkWriter;
+///
+/// let insert = session
+///     .prepare("INSERT INTO ks.tab (a, b) VALUES (?, ?)")
+///     .await?;
+/// let writer = BulkWriter::new(session, insert);
+///
+/// let rows = futures::stream::iter((0..100_000).map(|a: i32| (a, a.to_string())));
+/// let report = writer.write(rows).await;
+///
+/// println!("Written {} rows/s", report.rows_per_second());
+/// for failed in &report.failed_rows {
+///     println!("Row {} failed: {}", failed.index, failed.error);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct BulkWriter<'a> {
+    session: &'a Session,
+    prepared: PreparedStatement,
+    options: BulkWriterOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum GroupKey {
+    Token(i64),
+    Replica(SocketAddr),
+    Unknown,
+}
+
+struct PendingBatch {
+    token: Option<Token>,
+    indexes: Vec<usize>,
+    values: Vec<SerializedValues>,
+    bytes: usize,
+}
+
+// Rows without a known primary replica are grouped by their token
+fn group_key(
+    token: Option<Token>,
+    grouping: BulkGrouping,
+    primary_replica: impl FnOnce(Token) -> Option<SocketAddr>,
+) -> GroupKey {
+    match (token, grouping) {
+        (None, _) => GroupKey::Unknown,
+        (Some(token), BulkGrouping::Partition) => GroupKey::Token(token.value),
+        (Some(token), BulkGrouping::Replica) => primary_replica(token)
+            .map(GroupKey::Replica)
+            .unwrap_or(GroupKey::Token(token.value)),
+    }
+}
+
+/// Incomplete batches, grouped by their keys
+struct BatchBuffer {
+    pending: HashMap<GroupKey, PendingBatch>,
+    buffered_rows: usize,
+    max_batch_rows: usize,
+    max_batch_bytes: usize,
+    max_buffered_rows: usize,
+}
+
+impl BatchBuffer {
+    fn new(options: &BulkWriterOptions) -> Self {
+        BatchBuffer {
+            pending: HashMap::new(),
+            buffered_rows: 0,
+            max_batch_rows: options.max_batch_rows,
+            max_batch_bytes: options.max_batch_bytes,
+            max_buffered_rows: options.max_buffered_rows,
+        }
+    }
+
+    /// Adds the row to the batch of its group. Returns the batch which should be executed now:
+    /// the row's batch if it became full, or the largest batch if too many rows are buffered.
+    fn push(
+        &mut self,
+        key: GroupKey,
+        token: Option<Token>,
+        index: usize,
+        values: SerializedValues,
+    ) -> Option<PendingBatch> {
+        let batch = self.pending.entry(key).or_insert_with(|| PendingBatch {
+            token,
+            indexes: Vec::new(),
+            values: Vec::new(),
+            bytes: 0,
+        });
+        batch.bytes += values.size();
+        batch.indexes.push(index);
+        batch.values.push(values);
+        self.buffered_rows += 1;
+
+        let key_to_execute =
+            if batch.indexes.len() >= self.max_batch_rows || batch.bytes >= self.max_batch_bytes {
+                Some(key)
+            } else if self.buffered_rows >= self.max_buffered_rows {
+                self.pending
+                    .iter()
+                    .max_by_key(|(_, batch)| batch.indexes.len())
+                    .map(|(key, _)| *key)
+            } else {
+                None
+            };
+
+        let batch = self.pending.remove(&key_to_execute?)?;
+        self.buffered_rows -= batch.indexes.len();
+        Some(batch)
+    }
+
+    /// Removes all incomplete batches
+    fn drain(&mut self) -> impl Iterator<Item = PendingBatch> + '_ {
+        self.buffered_rows = 0;
+        self.pending.drain().map(|(_, batch)| batch)
+    }
+}
+
+struct BatchOutcome {
+    indexes: Vec<usize>,
+    bytes: usize,
+    result: Result<(), QueryError>,
+}
+
+impl<'a> BulkWriter<'a> {
+    /// Creates a writer with default [`BulkWriterOptions`]
+    pub fn new(session: &'a Session, prepared: PreparedStatement) -> BulkWriter<'a> {
+        BulkWriter::with_options(session, prepared, BulkWriterOptions::default())
+    }
+
+    pub fn with_options(
+        session: &'a Session,
+        prepared: PreparedStatement,
+        options: BulkWriterOptions,
+    ) -> BulkWriter<'a> {
+        BulkWriter {
+            session,
+            prepared,
+            options,
+        }
+    }
+
+    pub fn get_prepared(&self) -> &PreparedStatement {
+        &self.prepared
+    }
+
+    pub fn get_options(&self) -> &BulkWriterOptions {
+        &self.options
+    }
+
+    /// Writes all rows from the stream and waits for all batches to finish.\
+    /// Failures of single rows or batches don't stop the write, they are returned in the report.
+    pub async fn write<V: ValueList>(&self, rows: impl Stream<Item = V>) -> BulkWriteReport {
+        let start = Instant::now();
+        // Refreshed after every executed batch, so that grouping follows topology changes
+        let mut cluster_data = self.session.get_cluster_data();
+        let concurrency = self.options.concurrency.max(1);
+
+        let mut report = BulkWriteReport::default();
+        let mut buffer = BatchBuffer::new(&self.options);
+        let mut in_flight = FuturesUnordered::new();
+        let mut next_index: usize = 0;
+
+        futures::pin_mut!(rows);
+        loop {
+            if in_flight.len() >= concurrency {
+                report.record(in_flight.next().await.unwrap());
+                continue;
+            }
+
+            // Keep executing batches while waiting for the next row
+            let row = tokio::select! {
+                Some(outcome) = in_flight.next(), if !in_flight.is_empty() => {
+                    report.record(outcome);
+                    continue;
+                }
+                row = rows.next() => row,
+            };
+            let row = match row {
+                Some(row) => row,
+                None => break,
+            };
+
+            let index = next_index;
+            next_index += 1;
+            let (key, token, values) = match self.serialize_row(&cluster_data, row) {
+                Ok(serialized) => serialized,
+                Err(error) => {
+                    report.failed_rows.push(FailedRow { index, error });
+                    continue;
+                }
+            };
+
+            if let Some(batch) = buffer.push(key, token, index, values) {
+                in_flight.push(self.execute_batch(batch));
+                cluster_data = self.session.get_cluster_data();
+            }
+        }
+
+        for batch in buffer.drain() {
+            if in_flight.len() >= concurrency {
+                report.record(in_flight.next().await.unwrap());
+            }
+            in_flight.push(self.execute_batch(batch));
+        }
+        while let Some(outcome) = in_flight.next().await {
+            report.record(outcome);
+        }
+
+        report.failed_rows.sort_by_key(|failed| failed.index);
+        report.elapsed = start.elapsed();
+        report
+    }
+
+    fn serialize_row(
+        &self,
+        cluster_data: &ClusterData,
+        row: impl ValueList,
+    ) -> Result<(GroupKey, Option<Token>, SerializedValues), QueryError> {
+        let serialized = row.serialized()?;
+        let values = none_as_unset(
+            &self.prepared.config,
+            bind_by_name(&self.prepared, &serialized)?,
+        )
+        .into_owned();
+
+        let token = self.session.calculate_token(&self.prepared, &values)?;
+        let key = group_key(token, self.options.grouping, |token| {
+            let keyspace = self.prepared.get_keyspace_name()?;
+            cluster_data
+                .get_token_endpoints(keyspace, token)
+                .first()
+                .map(|node| node.address)
+        });
+
+        Ok((key, token, values))
+    }
+
+    async fn execute_batch(&self, batch: PendingBatch) -> BatchOutcome {
+        let result = if batch.values.len() == 1 {
+            self.session
+                .execute(&self.prepared, &batch.values[0])
+                .await
+                .map(|_| ())
+        } else {
+            let mut cql_batch = Batch::new(BatchType::Unlogged);
+            cql_batch.config = self.prepared.config.clone();
+            for _ in 0..batch.values.len() {
+                cql_batch.append_statement(self.prepared.clone());
+            }
+            self.session
+                .batch_with_token(
+                    &cql_batch,
+                    &batch.values,
+                    batch.token,
+                    self.prepared.get_keyspace_name(),
+                )
+                .await
+                .map(|_| ())
+        };
+
+        BatchOutcome {
+            indexes: batch.indexes,
+            bytes: batch.bytes,
+            result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        group_key, BatchBuffer, BatchOutcome, BulkGrouping, BulkWriteReport, BulkWriterOptions,
+        GroupKey,
+    };
+    use crate::frame::value::SerializedValues;
+    use crate::routing::Token;
+    use crate::transport::errors::QueryError;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    fn values(size: usize) -> SerializedValues {
+        let mut values = SerializedValues::new();
+        values.add_value(&vec![0_u8; size]).unwrap();
+        values
+    }
+
+    #[test]
+    fn rows_are_grouped() {
+        let token = Some(Token { value: 7 });
+        let replica: SocketAddr = "127.0.0.1:9042".parse().unwrap();
+
+        assert_eq!(
+            group_key(token, BulkGrouping::Partition, |_| Some(replica)),
+            GroupKey::Token(7)
+        );
+        assert_eq!(
+            group_key(token, BulkGrouping::Replica, |token| {
+                assert_eq!(token.value, 7);
+                Some(replica)
+            }),
+            GroupKey::Replica(replica)
+        );
+        assert_eq!(
+            group_key(token, BulkGrouping::Replica, |_| None),
+            GroupKey::Token(7)
+        );
+        assert_eq!(
+            group_key(None, BulkGrouping::Replica, |_| Some(replica)),
+            GroupKey::Unknown
+        );
+    }
+
+    #[test]
+    fn batch_is_executed_when_full() {
+        let options = BulkWriterOptions {
+            max_batch_rows: 3,
+            max_batch_bytes: 1000,
+            ..Default::default()
+        };
+        let mut buffer = BatchBuffer::new(&options);
+
+        assert!(buffer
+            .push(GroupKey::Token(1), None, 0, values(1))
+            .is_none());
+        assert!(buffer
+            .push(GroupKey::Token(2), None, 1, values(1))
+            .is_none());
+        assert!(buffer
+            .push(GroupKey::Token(1), None, 2, values(1))
+            .is_none());
+        let batch = buffer.push(GroupKey::Token(1), None, 3, values(1)).unwrap();
+        assert_eq!(batch.indexes, vec![0, 2, 3]);
+        assert_eq!(buffer.buffered_rows, 1);
+
+        // Rows exceeding max_batch_bytes are sent at once
+        let batch = buffer
+            .push(GroupKey::Token(2), None, 4, values(1000))
+            .unwrap();
+        assert_eq!(batch.indexes, vec![1, 4]);
+        assert!(batch.bytes >= 1000);
+        assert_eq!(buffer.buffered_rows, 0);
+        assert_eq!(buffer.drain().count(), 0);
+    }
+
+    #[test]
+    fn largest_batch_is_executed_when_too_many_rows_are_buffered() {
+        let options = BulkWriterOptions {
+            max_batch_rows: 10,
+            max_buffered_rows: 4,
+            ..Default::default()
+        };
+        let mut buffer = BatchBuffer::new(&options);
+
+        assert!(buffer
+            .push(GroupKey::Token(1), None, 0, values(1))
+            .is_none());
+        assert!(buffer
+            .push(GroupKey::Token(2), None, 1, values(1))
+            .is_none());
+        assert!(buffer
+            .push(GroupKey::Token(2), None, 2, values(1))
+            .is_none());
+        let batch = buffer.push(GroupKey::Unknown, None, 3, values(1)).unwrap();
+        assert_eq!(batch.indexes, vec![1, 2]);
+        assert_eq!(buffer.buffered_rows, 2);
+
+        let mut remaining: Vec<Vec<usize>> = buffer.drain().map(|batch| batch.indexes).collect();
+        remaining.sort();
+        assert_eq!(remaining, vec![vec![0], vec![3]]);
+        assert_eq!(buffer.buffered_rows, 0);
+    }
+
+    #[test]
+    fn report_records_batch_outcomes() {
+        let mut report = BulkWriteReport::default();
+        report.record(BatchOutcome {
+            indexes: vec![0, 2, 3],
+            bytes: 30,
+            result: Ok(()),
+        });
+        report.record(BatchOutcome {
+            indexes: vec![1, 4],
+            bytes: 20,
+            result: Err(QueryError::TimeoutError),
+        });
+        report.elapsed = Duration::from_millis(500);
+
+        assert_eq!(report.rows_written, 3);
+        assert_eq!(report.bytes_written, 30);
+        assert_eq!(report.batches, 2);
+        assert!(!report.is_success());
+        assert_eq!(
+            report
+                .failed_rows
+                .iter()
+                .map(|failed| failed.index)
+                .collect::<Vec<_>>(),
+            vec![1, 4]
+        );
+        assert_eq!(report.rows_per_second(), 6.0);
+        assert_eq!(report.bytes_per_second(), 60.0);
+
+        assert_eq!(BulkWriteReport::default().rows_per_second(), 0.0);
+    }
+}
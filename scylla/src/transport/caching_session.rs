@@ -2,8 +2,11 @@ use crate::batch::{Batch, BatchStatement};
 use crate::frame::value::{BatchValues, ValueList};
 use crate::prepared_statement::PreparedStatement;
 use crate::query::Query;
-use crate::transport::errors::QueryError;
-use crate::transport::iterator::RowIterator;
+use crate::statement::server_timeout::with_server_timeout;
+use crate::statement::table::Table;
+use crate::transport::errors::{BadQuery, QueryError};
+use crate::transport::iterator::{PagingState, RowIterator, TypedRowIterator};
+use crate::transport::query_result::TypedQueryError;
 use crate::{QueryResult, Session};
 use bytes::Bytes;
 use dashmap::DashMap;
@@ -76,6 +79,21 @@ where
         self.session.execute_iter(prepared, values.clone()).await
     }
 
+    /// Does the same thing as [`Session::execute_iter_from`] but uses the prepared statement cache
+    pub async fn execute_iter_from(
+        &self,
+        query: impl Into<Query>,
+        values: impl ValueList,
+        paging_state: PagingState,
+    ) -> Result<RowIterator, QueryError> {
+        let query = query.into();
+        let prepared = self.add_prepared_statement(&query).await?;
+        let values = values.serialized()?;
+        self.session
+            .execute_iter_from(prepared, values.clone(), paging_state)
+            .await
+    }
+
     /// Does the same thing as [`Session::execute_paged`] but uses the prepared statement cache
     pub async fn execute_paged(
         &self,
@@ -91,6 +109,52 @@ where
             .await
     }
 
+    /// Inserts the row into its table using [`Table::INSERT`]
+    pub async fn insert<T: Table>(&self, row: &T) -> Result<QueryResult, QueryError> {
+        self.execute(T::INSERT, row).await
+    }
+
+    /// Selects the row with the given primary key using [`Table::SELECT_BY_PRIMARY_KEY`].\
+    /// Returns `None` if there is no such row.
+    pub async fn select_by_primary_key<T: Table>(
+        &self,
+        key: T::PrimaryKey,
+    ) -> Result<Option<T>, TypedQueryError> {
+        let row = self
+            .execute(T::SELECT_BY_PRIMARY_KEY, key)
+            .await?
+            .rows_typed_or_empty::<T>()
+            .next()
+            .transpose()?;
+        Ok(row)
+    }
+
+    /// Selects all rows of the partition with the given key using [`Table::SELECT_BY_PARTITION_KEY`].\
+    /// Returns an async iterator (stream) over the rows, fetching them page by page.
+    pub async fn select_by_partition_key<T: Table>(
+        &self,
+        key: T::PartitionKey,
+    ) -> Result<TypedRowIterator<T>, QueryError> {
+        Ok(self
+            .execute_iter(T::SELECT_BY_PARTITION_KEY, key)
+            .await?
+            .into_typed::<T>())
+    }
+
+    /// Updates regular columns of the row using [`Table::UPDATE`].\
+    /// Fails if the table doesn't have any columns outside of the primary key.
+    pub async fn update<T: Table>(&self, row: &T) -> Result<QueryResult, QueryError> {
+        let update = T::UPDATE.ok_or_else(|| {
+            BadQuery::Other("Table has no columns to update outside of the primary key".to_string())
+        })?;
+        self.execute(update, row.update_values()?).await
+    }
+
+    /// Deletes the row with the given primary key using [`Table::DELETE`]
+    pub async fn delete<T: Table>(&self, key: T::PrimaryKey) -> Result<QueryResult, QueryError> {
+        self.execute(T::DELETE, key).await
+    }
+
     /// Does the same thing as [`Session::batch`] but uses the prepared statement cache\
     /// Prepares batch using CachingSession::prepare_batch if needed and then executes it
     pub async fn batch(
@@ -141,8 +205,10 @@ where
         query: impl Into<&Query>,
     ) -> Result<PreparedStatement, QueryError> {
         let query = query.into();
+        // Statements are cached by the text which is prepared, which includes the server-side timeout
+        let contents = with_server_timeout(&query.contents, query.get_server_timeout());
 
-        if let Some(prepared) = self.cache.get(&query.contents) {
+        if let Some(prepared) = self.cache.get(contents.as_ref()) {
             // Clone, because else the value is mutably borrowed and the execute method gives a compile error
             Ok(prepared.clone())
         } else {
@@ -161,7 +227,7 @@ where
                 }
             }
 
-            self.cache.insert(query.contents.clone(), prepared.clone());
+            self.cache.insert(contents.into_owned(), prepared.clone());
 
             Ok(prepared)
         }
@@ -174,10 +240,12 @@ mod tests {
     use crate::{
         batch::{Batch, BatchStatement},
         prepared_statement::PreparedStatement,
-        CachingSession, Session, SessionBuilder,
+        query::Query,
+        CachingSession, Session, SessionBuilder, Table,
     };
     use futures::TryStreamExt;
     use std::collections::BTreeSet;
+    use std::time::Duration;
 
     async fn new_for_test() -> Session {
         let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
@@ -264,6 +332,30 @@ mod tests {
         assert!(first_query_removed || middle_query_removed);
     }
 
+    /// Checks that queries with different server-side timeouts are prepared separately
+    #[tokio::test]
+    async fn test_server_timeout_cached_separately() {
+        let session = create_caching_session().await;
+
+        let contents = "select * from test_table";
+        let mut with_timeout: Query = contents.into();
+        with_timeout.set_server_timeout(Some(Duration::from_millis(500)));
+
+        let prepared = session
+            .add_prepared_statement(&contents.into())
+            .await
+            .unwrap();
+        let prepared_with_timeout = session.add_prepared_statement(&with_timeout).await.unwrap();
+
+        assert_eq!(2, session.cache.len());
+        assert_eq!(prepared.get_server_timeout(), None);
+        assert_eq!(
+            prepared_with_timeout.get_server_timeout(),
+            Some(Duration::from_millis(500))
+        );
+        assert_ne!(prepared.get_id(), prepared_with_timeout.get_id());
+    }
+
     /// Checks that the same prepared statement is reused when executing the same query twice
     #[tokio::test]
     async fn test_execute_cached() {
@@ -469,4 +561,63 @@ mod tests {
             assert!(session.prepare_batch(&bad_batch).await.is_err());
         }
     }
+
+    #[derive(Table, Clone, Debug, PartialEq)]
+    #[scylla(crate = "crate", table = "test_table_mapping")]
+    struct MappedRow {
+        #[partition_key]
+        a: i32,
+        #[clustering_key]
+        b: i32,
+        c: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_table_helpers() {
+        let session = create_caching_session().await;
+
+        session
+            .execute(
+                "CREATE TABLE IF NOT EXISTS test_table_mapping (a int, b int, c text, primary key (a, b))",
+                &[],
+            )
+            .await
+            .unwrap();
+
+        let rows: Vec<MappedRow> = (1..=3)
+            .map(|b| MappedRow {
+                a: 1,
+                b,
+                c: Some(format!("row {}", b)),
+            })
+            .collect();
+        for row in &rows {
+            session.insert(row).await.unwrap();
+        }
+
+        let selected: Option<MappedRow> = session.select_by_primary_key((1, 2)).await.unwrap();
+        assert_eq!(selected.as_ref(), Some(&rows[1]));
+
+        let partition: Vec<MappedRow> = session
+            .select_by_partition_key::<MappedRow>((1,))
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(partition, rows);
+
+        let mut updated = rows[0].clone();
+        updated.c = None;
+        session.update(&updated).await.unwrap();
+        let selected: Option<MappedRow> = session
+            .select_by_primary_key(updated.primary_key())
+            .await
+            .unwrap();
+        assert_eq!(selected, Some(updated));
+
+        session.delete::<MappedRow>((1, 3)).await.unwrap();
+        let selected: Option<MappedRow> = session.select_by_primary_key((1, 3)).await.unwrap();
+        assert_eq!(selected, None);
+    }
 }
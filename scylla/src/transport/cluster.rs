@@ -10,6 +10,7 @@ use crate::transport::{
     errors::QueryError,
     node::Node,
     partitioner::PartitionerName,
+    prepared_statements_registry::PreparedStatementsRegistry,
     session::AddressTranslator,
     topology::{Keyspace, Metadata, MetadataReader},
 };
@@ -25,6 +26,7 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, warn};
+use uuid::Uuid;
 
 /// Cluster manages up to date information and connections to database nodes.
 /// All data can be accessed by cloning Arc<ClusterData> in the `data` field
@@ -59,8 +61,8 @@ pub struct Datacenter {
 
 #[derive(Clone)]
 pub struct ClusterData {
-    pub(crate) known_peers: HashMap<SocketAddr, Arc<Node>>, // Invariant: nonempty after Cluster::new()
-    pub(crate) ring: BTreeMap<Token, Arc<Node>>, // Invariant: nonempty after Cluster::new()
+    pub(crate) known_peers: HashMap<Uuid, Arc<Node>>, // Invariant: nonempty after Cluster::new()
+    pub(crate) ring: BTreeMap<Token, Arc<Node>>,      // Invariant: nonempty after Cluster::new()
     pub(crate) keyspaces: HashMap<String, Keyspace>,
     pub(crate) all_nodes: Vec<Arc<Node>>,
     pub(crate) datacenters: HashMap<String, Datacenter>,
@@ -115,6 +117,10 @@ struct ClusterWorker {
     // The host filter determines towards which nodes we should open
     // connections
     host_filter: Option<Arc<dyn HostFilter>>,
+
+    // Statements prepared by the session, which should be prepared again
+    // on nodes that join the cluster or come back up
+    prepared_statements: Arc<PreparedStatementsRegistry>,
 }
 
 #[derive(Debug)]
@@ -135,6 +141,7 @@ impl Cluster {
         fetch_schema_metadata: bool,
         address_translator: &Option<Arc<dyn AddressTranslator>>,
         host_filter: &Option<Arc<dyn HostFilter>>,
+        prepared_statements: Arc<PreparedStatementsRegistry>,
     ) -> Result<Cluster, QueryError> {
         let (refresh_sender, refresh_receiver) = tokio::sync::mpsc::channel(32);
         let (use_keyspace_sender, use_keyspace_receiver) = tokio::sync::mpsc::channel(32);
@@ -175,6 +182,8 @@ impl Cluster {
             used_keyspace: None,
 
             host_filter: host_filter.clone(),
+
+            prepared_statements,
         };
 
         let (fut, worker_handle) = worker.work().remote_handle();
@@ -286,39 +295,51 @@ impl ClusterData {
     pub(crate) fn new(
         metadata: Metadata,
         pool_config: &PoolConfig,
-        known_peers: &HashMap<SocketAddr, Arc<Node>>,
+        known_peers: &HashMap<Uuid, Arc<Node>>,
         used_keyspace: &Option<VerifiedKeyspaceName>,
         host_filter: Option<&dyn HostFilter>,
     ) -> Self {
         // Create new updated known_peers and ring
-        let mut new_known_peers: HashMap<SocketAddr, Arc<Node>> =
+        let mut new_known_peers: HashMap<Uuid, Arc<Node>> =
             HashMap::with_capacity(metadata.peers.len());
         let mut ring: BTreeMap<Token, Arc<Node>> = BTreeMap::new();
         let mut datacenters: HashMap<String, Datacenter> = HashMap::new();
         let mut all_nodes: Vec<Arc<Node>> = Vec::with_capacity(metadata.peers.len());
 
         for peer in metadata.peers {
-            // Take existing Arc<Node> if possible, otherwise create new one
-            // Changing rack/datacenter but not ip address seems improbable
-            // so we can just create new node and connections then
-            let node: Arc<Node> = match known_peers.get(&peer.address) {
-                Some(node) if node.datacenter == peer.datacenter && node.rack == peer.rack => {
+            // Nodes are identified by their host id. Take the existing Arc<Node>
+            // if its metadata hasn't changed. Otherwise create the Node with updated
+            // metadata which keeps the connection pool of the existing one, so that
+            // e.g. a changed address doesn't make the driver drop all connections
+            // to the node. The pool itself reconnects to the new address.
+            let is_enabled = host_filter.map(|f| f.accept(&peer)).unwrap_or(true);
+            let node: Arc<Node> = match known_peers.get(&peer.host_id) {
+                Some(node)
+                    if node.address == peer.address
+                        && node.datacenter == peer.datacenter
+                        && node.rack == peer.rack
+                        && node.release_version == peer.release_version
+                        && node.is_enabled() == is_enabled =>
+                {
+                    node.update_schema_version(peer.schema_version);
                     node.clone()
                 }
-                _ => {
-                    let is_enabled = host_filter.map_or(true, |f| f.accept(&peer));
-                    Arc::new(Node::new(
-                        peer.address,
-                        pool_config.clone(),
-                        peer.datacenter,
-                        peer.rack,
-                        used_keyspace.clone(),
-                        is_enabled,
-                    ))
-                }
+                Some(node) => Arc::new(Node::inherit_with_changes(
+                    node,
+                    &peer,
+                    pool_config.clone(),
+                    used_keyspace.clone(),
+                    is_enabled,
+                )),
+                None => Arc::new(Node::new(
+                    &peer,
+                    pool_config.clone(),
+                    used_keyspace.clone(),
+                    is_enabled,
+                )),
             };
 
-            new_known_peers.insert(peer.address, node.clone());
+            new_known_peers.insert(peer.host_id, node.clone());
 
             if let Some(dc) = &node.datacenter {
                 match datacenters.get_mut(dc) {
@@ -515,7 +536,11 @@ impl ClusterWorker {
     fn change_node_down_marker(&mut self, addr: SocketAddr, is_down: bool) {
         let cluster_data = self.cluster_data.load_full();
 
-        let node = match cluster_data.known_peers.get(&addr) {
+        let node = match cluster_data
+            .known_peers
+            .values()
+            .find(|node| node.address == addr)
+        {
             Some(node) => node,
             None => {
                 warn!("Unknown node address {}", addr);
@@ -523,7 +548,25 @@ impl ClusterWorker {
             }
         };
 
+        let was_down = node.is_down();
         node.change_down_marker(is_down);
+
+        if was_down && !is_down {
+            // The node might have been restarted and lost its prepared statements.
+            // Until they are prepared again the node is moved to the end of query plans,
+            // so that it doesn't receive requests which would fail with `Unprepared`.
+            // The pool reconnects in the background, so the statements are prepared
+            // on its connections as they are opened.
+            node.set_repreparing(true);
+            let node = node.clone();
+            let prepared_statements = self.prepared_statements.clone();
+            tokio::spawn(async move {
+                prepared_statements
+                    .reprepare_on_reconnected_node(&node)
+                    .await;
+                node.set_repreparing(false);
+            });
+        }
     }
 
     async fn handle_use_keyspace_request(
@@ -594,6 +637,20 @@ impl ClusterWorker {
             .wait_until_all_pools_are_initialized()
             .await;
 
+        // Prepare known statements on the newly added nodes before they start
+        // receiving requests, so that they don't respond with `Unprepared`
+        let added_nodes = new_cluster_data
+            .known_peers
+            .iter()
+            .filter(
+                |(host_id, node)| match cluster_data.known_peers.get(host_id) {
+                    Some(old_node) => !Arc::ptr_eq(old_node, node),
+                    None => true,
+                },
+            )
+            .map(|(_, node)| self.prepared_statements.reprepare_on_node(node));
+        join_all(added_nodes).await;
+
         self.update_cluster_data(new_cluster_data);
 
         Ok(())
@@ -603,3 +660,62 @@ impl ClusterWorker {
         self.cluster_data.store(new_cluster_data);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ClusterData;
+    use crate::transport::node::Node;
+    use crate::transport::topology::{Metadata, Peer};
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    fn peer(id: u16, address: SocketAddr, datacenter: &str) -> Peer {
+        Peer {
+            address,
+            untranslated_address: Some(address),
+            tokens: Vec::new(),
+            datacenter: Some(datacenter.to_string()),
+            rack: None,
+            host_id: Uuid::from_u128(id as u128),
+            release_version: None,
+            schema_version: None,
+        }
+    }
+
+    fn cluster_data(peers: Vec<Peer>, known_peers: &HashMap<Uuid, Arc<Node>>) -> ClusterData {
+        let metadata = Metadata {
+            peers,
+            keyspaces: HashMap::new(),
+        };
+        ClusterData::new(metadata, &Default::default(), known_peers, &None, None)
+    }
+
+    // ConnectionKeeper (which lives in Node) requires context of Tokio runtime
+    #[tokio::test]
+    async fn nodes_are_reused_by_host_id() {
+        let address = |id: u16| SocketAddr::from(([255, 255, 255, 255], id));
+        let old = cluster_data(
+            vec![peer(1, address(1), "dc1"), peer(2, address(2), "dc1")],
+            &HashMap::new(),
+        );
+        let old_node = |id: u16| old.known_peers[&Uuid::from_u128(id as u128)].clone();
+
+        let new = cluster_data(
+            vec![peer(1, address(1), "dc1"), peer(2, address(3), "dc2")],
+            &old.known_peers,
+        );
+        let new_node = |id: u16| new.known_peers[&Uuid::from_u128(id as u128)].clone();
+
+        // Unchanged node is taken as is
+        assert!(Arc::ptr_eq(&old_node(1), &new_node(1)));
+
+        // Changed node keeps its identity and its connection pool
+        let (old_changed, new_changed) = (old_node(2), new_node(2));
+        assert_eq!(old_changed, new_changed);
+        assert_eq!(new_changed.address, address(3));
+        assert_eq!(new_changed.datacenter.as_deref(), Some("dc2"));
+        assert!(new_changed.shares_pool_with(&old_changed));
+    }
+}
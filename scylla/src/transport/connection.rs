@@ -41,6 +41,7 @@ use crate::frame::{
 use crate::query::Query;
 use crate::routing::ShardInfo;
 use crate::statement::prepared_statement::PreparedStatement;
+use crate::statement::server_timeout::with_server_timeout;
 use crate::statement::Consistency;
 use crate::transport::session::IntoTypedRows;
 use crate::transport::Authenticator;
@@ -90,6 +91,7 @@ pub(crate) struct ConnectionFeatures {
     shard_info: Option<ShardInfo>,
     shard_aware_port: Option<u16>,
     protocol_features: ProtocolFeatures,
+    supported_compression: Vec<String>,
 }
 
 type RequestId = u64;
@@ -147,6 +149,8 @@ pub struct QueryResponse {
     pub response: Response,
     pub tracing_id: Option<Uuid>,
     pub warnings: Vec<String>,
+    /// Size of the decompressed response body in bytes
+    pub body_size: usize,
 }
 
 // A QueryResponse in which response can not be Response::Error
@@ -320,11 +324,10 @@ impl Connection {
     }
 
     pub async fn prepare(&self, query: &Query) -> Result<PreparedStatement, QueryError> {
+        let contents = with_server_timeout(&query.contents, query.get_server_timeout());
         let query_response = self
             .send_request(
-                &request::Prepare {
-                    query: &query.contents,
-                },
+                &request::Prepare { query: &contents },
                 true,
                 query.config.tracing,
             )
@@ -332,13 +335,22 @@ impl Connection {
 
         let mut prepared_statement = match query_response.response {
             Response::Error(err) => return Err(err.into()),
-            Response::Result(result::Result::Prepared(p)) => PreparedStatement::new(
-                p.id,
-                p.prepared_metadata,
-                query.contents.clone(),
-                query.get_page_size(),
-                query.config.clone(),
-            ),
+            Response::Result(result::Result::Prepared(p)) => {
+                let is_confirmed_lwt = self
+                    .features
+                    .protocol_features
+                    .prepared_flags_contain_lwt_mark(p.prepared_metadata.flags as u32);
+                let mut prepared = PreparedStatement::new(
+                    p.id,
+                    p.prepared_metadata,
+                    p.result_metadata,
+                    contents.into_owned(),
+                    query.get_page_size(),
+                    query.config.clone(),
+                );
+                prepared.set_is_confirmed_lwt(is_confirmed_lwt);
+                prepared
+            }
             _ => {
                 return Err(QueryError::ProtocolError(
                     "PREPARE: Unexpected server response",
@@ -356,7 +368,7 @@ impl Connection {
         &self,
         query: impl Into<Query>,
         previous_prepared: &PreparedStatement,
-    ) -> Result<(), QueryError> {
+    ) -> Result<PreparedStatement, QueryError> {
         let reprepare_query: Query = query.into();
         let reprepared = self.prepare(&reprepare_query).await?;
         // Reprepared statement should keep its id - it's the md5 sum
@@ -366,7 +378,7 @@ impl Connection {
                 "Prepared statement Id changed, md5 sum should stay the same",
             ))
         } else {
-            Ok(())
+            Ok(reprepared)
         }
     }
 
@@ -408,7 +420,7 @@ impl Connection {
         consistency: Consistency,
     ) -> Result<QueryResult, QueryError> {
         let query: Query = query.into();
-        self.query_with_consistency(&query, &values, consistency, None)
+        self.query_with_consistency(&query, &values, consistency, None, query.get_timestamp())
             .await?
             .into_query_result()
     }
@@ -426,28 +438,34 @@ impl Connection {
                 .config
                 .determine_consistency(self.config.default_consistency),
             paging_state,
+            query.get_timestamp(),
         )
         .await
     }
 
+    /// Sends the query with the given consistency and timestamp.
+    /// The timestamp is passed separately, so that the session can generate it once for all
+    /// attempts of a request.
     pub async fn query_with_consistency(
         &self,
         query: &Query,
         values: impl ValueList,
         consistency: Consistency,
         paging_state: Option<Bytes>,
+        timestamp: Option<i64>,
     ) -> Result<QueryResponse, QueryError> {
         let serialized_values = values.serialized()?;
+        let contents = with_server_timeout(&query.contents, query.get_server_timeout());
 
         let query_frame = query::Query {
-            contents: &query.contents,
+            contents: &contents,
             parameters: query::QueryParameters {
                 consistency,
                 serial_consistency: query.get_serial_consistency(),
                 values: &serialized_values,
                 page_size: query.get_page_size(),
                 paging_state,
-                timestamp: query.get_timestamp(),
+                timestamp,
             },
         };
 
@@ -496,7 +514,13 @@ impl Connection {
         loop {
             // Send next paged query
             let mut cur_result: QueryResult = self
-                .query_with_consistency(query, &serialized_values, consistency, paging_state)
+                .query_with_consistency(
+                    query,
+                    &serialized_values,
+                    consistency,
+                    paging_state,
+                    query.get_timestamp(),
+                )
                 .await?
                 .into_query_result()?;
 
@@ -537,16 +561,20 @@ impl Connection {
                 .config
                 .determine_consistency(self.config.default_consistency),
             paging_state,
+            prepared_statement.get_timestamp(),
         )
         .await
     }
 
+    /// Executes the statement with the given consistency and timestamp,
+    /// see [`query_with_consistency`](Connection::query_with_consistency).
     pub async fn execute_with_consistency(
         &self,
         prepared_statement: &PreparedStatement,
         values: impl ValueList,
         consistency: Consistency,
         paging_state: Option<Bytes>,
+        timestamp: Option<i64>,
     ) -> Result<QueryResponse, QueryError> {
         let serialized_values = values.serialized()?;
 
@@ -557,7 +585,7 @@ impl Connection {
                 serial_consistency: prepared_statement.get_serial_consistency(),
                 values: &serialized_values,
                 page_size: prepared_statement.get_page_size(),
-                timestamp: prepared_statement.get_timestamp(),
+                timestamp,
                 paging_state,
             },
         };
@@ -566,7 +594,7 @@ impl Connection {
             .send_request(&execute_frame, true, prepared_statement.config.tracing)
             .await?;
 
-        match &query_response.response {
+        let query_response = match &query_response.response {
             Response::Error(frame::response::Error {
                 error: DbError::Unprepared { statement_id },
                 ..
@@ -576,10 +604,53 @@ impl Connection {
                 self.reprepare(prepared_statement.get_statement(), prepared_statement)
                     .await?;
                 self.send_request(&execute_frame, true, prepared_statement.config.tracing)
-                    .await
+                    .await?
+            }
+            _ => query_response,
+        };
+
+        if let Response::Result(result::Result::Rows(rows)) = &query_response.response {
+            if prepared_statement.is_result_metadata_outdated(&rows.metadata) {
+                self.handle_result_metadata_change(prepared_statement, &rows.metadata)
+                    .await;
             }
-            _ => Ok(query_response),
         }
+
+        Ok(query_response)
+    }
+
+    // The schema of the table changed since the statement was prepared (e.g. a column
+    // was added to a table queried with `SELECT *`). Rows in the current response are
+    // described by their own metadata, so they can be used as they are - only the metadata
+    // shared by all copies of the statement has to be refreshed.
+    async fn handle_result_metadata_change(
+        &self,
+        prepared_statement: &PreparedStatement,
+        received_metadata: &result::ResultMetadata,
+    ) {
+        warn!(
+            statement = prepared_statement.get_statement(),
+            "Result metadata of a prepared statement has changed, repreparing the statement"
+        );
+
+        let new_metadata = match self
+            .reprepare(prepared_statement.get_statement(), prepared_statement)
+            .await
+        {
+            Ok(reprepared) => reprepared.get_result_metadata(),
+            Err(err) => {
+                warn!(
+                    error = err.to_string().as_str(),
+                    "Failed to reprepare a statement with changed result metadata, \
+                    using the metadata received with the result instead"
+                );
+                let mut metadata = received_metadata.clone();
+                metadata.paging_state = None;
+                Arc::new(metadata)
+            }
+        };
+
+        prepared_statement.update_result_metadata(new_metadata);
     }
 
     /// Performs execute_single_page multiple times to fetch all available pages
@@ -631,15 +702,19 @@ impl Connection {
             batch
                 .config
                 .determine_consistency(self.config.default_consistency),
+            batch.get_timestamp(),
         )
         .await
     }
 
+    /// Sends the batch with the given consistency and timestamp,
+    /// see [`query_with_consistency`](Connection::query_with_consistency).
     pub async fn batch_with_consistency(
         &self,
         batch: &Batch,
         values: impl BatchValues,
         consistency: Consistency,
+        timestamp: Option<i64>,
     ) -> Result<QueryResult, QueryError> {
         let statements_count = batch.statements.len();
         if statements_count != values.len() {
@@ -663,7 +738,7 @@ impl Connection {
             batch_type: batch.get_type(),
             consistency,
             serial_consistency: batch.get_serial_consistency(),
-            timestamp: batch.get_timestamp(),
+            timestamp,
         };
 
         loop {
@@ -849,6 +924,7 @@ impl Connection {
             response,
             warnings: body_with_ext.warnings,
             tracing_id: body_with_ext.trace_id,
+            body_size: body_with_ext.body.len(),
         })
     }
 
@@ -1144,6 +1220,15 @@ impl Connection {
         self.features.shard_aware_port
     }
 
+    pub fn get_protocol_features(&self) -> &ProtocolFeatures {
+        &self.features.protocol_features
+    }
+
+    /// Returns names of compression algorithms supported by the node
+    pub fn get_supported_compression(&self) -> &[String] {
+        &self.features.supported_compression
+    }
+
     fn set_features(&mut self, features: ConnectionFeatures) {
         self.features = features;
     }
@@ -1212,6 +1297,7 @@ pub async fn open_named_connection(
         shard_info,
         shard_aware_port,
         protocol_features,
+        supported_compression: supported_compression.clone(),
     };
     connection.set_features(features);
 
@@ -1,5 +1,5 @@
 use crate::routing::{Shard, ShardCount, Sharder, Token};
-use crate::transport::errors::QueryError;
+use crate::transport::errors::{BadQuery, QueryError};
 use crate::transport::{
     connection,
     connection::{Connection, ConnectionConfig, ErrorReceiver, VerifiedKeyspaceName},
@@ -15,6 +15,7 @@ use std::num::NonZeroUsize;
 use std::pin::Pin;
 use std::sync::{Arc, Weak};
 use std::time::Duration;
+use tokio::sync::futures::Notified;
 use tokio::sync::{mpsc, Notify};
 use tracing::{debug, trace, warn};
 
@@ -139,6 +140,7 @@ impl std::fmt::Debug for PoolConnections {
 pub struct NodeConnectionPool {
     conns: Arc<ArcSwap<MaybePoolConnections>>,
     use_keyspace_request_sender: mpsc::Sender<UseKeyspaceRequest>,
+    address_change_sender: mpsc::UnboundedSender<SocketAddr>,
     _refiller_handle: RemoteHandle<()>,
     _keepaliver_handle: Option<RemoteHandle<()>>,
     pool_updated_notify: Arc<Notify>,
@@ -160,6 +162,7 @@ impl NodeConnectionPool {
         current_keyspace: Option<VerifiedKeyspaceName>,
     ) -> Self {
         let (use_keyspace_request_sender, use_keyspace_request_receiver) = mpsc::channel(1);
+        let (address_change_sender, address_change_receiver) = mpsc::unbounded_channel();
         let pool_updated_notify = Arc::new(Notify::new());
 
         let keepalive_interval = pool_config.keepalive_interval;
@@ -173,7 +176,9 @@ impl NodeConnectionPool {
         );
 
         let conns = refiller.get_shared_connections();
-        let (fut, refiller_handle) = refiller.run(use_keyspace_request_receiver).remote_handle();
+        let (fut, refiller_handle) = refiller
+            .run(use_keyspace_request_receiver, address_change_receiver)
+            .remote_handle();
         tokio::spawn(fut);
 
         let keepaliver_handle = if let Some(interval) = keepalive_interval {
@@ -194,12 +199,20 @@ impl NodeConnectionPool {
         Self {
             conns,
             use_keyspace_request_sender,
+            address_change_sender,
             _refiller_handle: refiller_handle,
             _keepaliver_handle: keepaliver_handle,
             pool_updated_notify,
         }
     }
 
+    /// Makes the pool reconnect to the node under a new address.
+    /// Connections to the old address are dropped from the pool.
+    pub(crate) fn update_address(&self, address: SocketAddr) {
+        // Other end of this channel is in the Refiller, can't be dropped while we have &self to _refiller_handle
+        let _ = self.address_change_sender.send(address);
+    }
+
     pub fn sharder(&self) -> Option<Sharder> {
         self.with_connections(|pool_conns| match pool_conns {
             PoolConnections::NotSharded(_) => None,
@@ -244,6 +257,35 @@ impl NodeConnectionPool {
         })
     }
 
+    pub fn connection_for_shard_number(&self, shard: Shard) -> Result<Arc<Connection>, QueryError> {
+        trace!(shard = shard, "Selecting connection for shard");
+        self.with_connections(|pool_conns| match pool_conns {
+            PoolConnections::NotSharded(conns) => {
+                Ok(Self::choose_random_connection_from_slice(conns).unwrap())
+            }
+            PoolConnections::Sharded {
+                sharder,
+                connections,
+            } => {
+                if shard >= sharder.nr_shards.get() as Shard {
+                    return Err(QueryError::BadQuery(BadQuery::Other(format!(
+                        "Shard {} doesn't exist, the node has {} shards",
+                        shard, sharder.nr_shards
+                    ))));
+                }
+                // The shard was chosen explicitly, so a connection to another one won't do
+                Self::choose_random_connection_from_slice(&connections[shard as usize]).ok_or_else(
+                    || {
+                        QueryError::IoError(Arc::new(std::io::Error::new(
+                            ErrorKind::Other,
+                            format!("No working connection to shard {}", shard),
+                        )))
+                    },
+                )
+            }
+        })?
+    }
+
     // Tries to get a connection to given shard, if it's broken returns any working connection
     fn connection_for_shard(
         shard: u16,
@@ -312,6 +354,12 @@ impl NodeConnectionPool {
         }
     }
 
+    /// Returns a future which completes on the next change of the pool's connections.
+    /// Changes which happen after this call and before the future is polled are not missed.
+    pub(crate) fn updated(&self) -> Notified<'_> {
+        self.pool_updated_notify.notified()
+    }
+
     pub fn get_working_connections(&self) -> Result<Vec<Arc<Connection>>, QueryError> {
         self.with_connections(|pool_conns| match pool_conns {
             PoolConnections::NotSharded(conns) => conns.clone(),
@@ -480,7 +528,7 @@ impl RefillDelayStrategy {
 }
 
 struct PoolRefiller {
-    // Following information identify the pool and do not change
+    // Following information identify the pool and change only when the node's address changes
     address: IpAddr,
     regular_port: u16,
     pool_config: PoolConfig,
@@ -581,6 +629,7 @@ impl PoolRefiller {
     pub async fn run(
         mut self,
         mut use_keyspace_request_receiver: mpsc::Receiver<UseKeyspaceRequest>,
+        mut address_change_receiver: mpsc::UnboundedReceiver<SocketAddr>,
     ) {
         debug!("[{}] Started asynchronous pool worker", self.address);
 
@@ -615,6 +664,10 @@ impl PoolRefiller {
                     }
                 }
 
+                Some(address) = address_change_receiver.recv() => {
+                    self.change_address(address);
+                }
+
                 req = use_keyspace_request_receiver.recv() => {
                     if let Some(req) = req {
                         debug!("[{}] Requested keyspace change: {}", self.address, req.keyspace_name.as_str());
@@ -956,6 +1009,33 @@ impl PoolRefiller {
         self.excess_connections.clear();
     }
 
+    // Points the pool at the new address of the node. Connections to the old
+    // address, including the ones being opened, are thrown away, so that the pool
+    // is refilled with connections to the new address.
+    fn change_address(&mut self, address: SocketAddr) {
+        if (self.address, self.regular_port) == (address.ip(), address.port()) {
+            return;
+        }
+
+        debug!(
+            "[{}] Node address changed to {}, clearing all connections",
+            self.address, address,
+        );
+
+        self.address = address.ip();
+        self.regular_port = address.port();
+
+        self.ready_connections = FuturesUnordered::new();
+        self.conns.iter_mut().for_each(Vec::clear);
+        self.excess_connections.clear();
+        self.had_error_since_last_refill = false;
+
+        self.update_shared_conns(Some(QueryError::IoError(Arc::new(std::io::Error::new(
+            ErrorKind::Other,
+            format!("Node address changed to {}, reconnecting", address),
+        )))));
+    }
+
     // Updates `shared_conns` based on `conns`.
     // `last_error` must not be `None` if there is a possibility of the pool
     // being empty.
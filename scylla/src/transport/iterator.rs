@@ -1,17 +1,21 @@
 //! Iterators over rows returned by paged queries
 
+use std::fmt;
 use std::future::Future;
 use std::mem;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use bytes::Bytes;
 use futures::Stream;
 use std::result::Result;
+use std::str::FromStr;
 use thiserror::Error;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify, Semaphore};
 
 use super::errors::QueryError;
 use crate::cql_to_rust::{FromRow, FromRowError};
@@ -26,12 +30,14 @@ use crate::frame::{
     value::SerializedValues,
 };
 use crate::history::{self, HistoryListener};
-use crate::routing::Token;
-use crate::statement::Consistency;
+use crate::routing::{Shard, Token};
 use crate::statement::{prepared_statement::PreparedStatement, query::Query};
+use crate::statement::{Consistency, StatementConfig};
 use crate::transport::cluster::ClusterData;
 use crate::transport::connection::{Connection, QueryResponse};
-use crate::transport::load_balancing::{LoadBalancingPolicy, Statement};
+use crate::transport::load_balancing::{
+    defer_repreparing_nodes, LoadBalancingPolicy, Plan, Statement,
+};
 use crate::transport::metrics::Metrics;
 use crate::transport::node::Node;
 use crate::transport::retry_policy::{QueryInfo, RetryDecision, RetrySession};
@@ -59,18 +65,157 @@ const DEFAULT_ITER_PAGE_SIZE: i32 = 5000;
 pub struct RowIterator {
     current_row_idx: usize,
     current_page: Rows,
-    page_receiver: mpsc::Receiver<Result<ReceivedPage, QueryError>>,
+    // Size of the current page, `None` once it's released from the page buffer
+    current_page_size: Option<usize>,
+    page_receiver: mpsc::UnboundedReceiver<Result<ReceivedPage, QueryError>>,
+    page_buffer: Arc<PageBuffer>,
     tracing_ids: Vec<Uuid>,
 }
 
+/// Position in the results of a paged query, allows to resume fetching from the next page.\
+/// Can be saved as a URL-safe string, e.g. to be used as a pagination cursor:
+/// ```rust
+/// # use scylla::transport::iterator::PagingState;
+/// # fn example(paging_state: PagingState) -> Result<(), Box<dyn std::error::Error>> {
+/// let cursor: String = paging_state.to_string();
+/// let restored: PagingState = cursor.parse()?;
+/// assert_eq!(restored, paging_state);
+/// # Ok(())
+/// # }
+/// ```
+/// The contents are opaque and valid only for the query that returned them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PagingState(Bytes);
+
+/// Couldn't parse [`PagingState`] from a string
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid paging state string")]
+pub struct PagingStateParseError;
+
+const BASE64_URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+impl PagingState {
+    /// Raw paging state, as sent by the server
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+impl From<Bytes> for PagingState {
+    fn from(bytes: Bytes) -> Self {
+        PagingState(bytes)
+    }
+}
+
+/// Formats the paging state as unpadded URL-safe base64
+impl fmt::Display for PagingState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut encoded = String::with_capacity(self.0.len() * 4 / 3 + 2);
+        for chunk in self.0.chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0_u32, |bits, (i, byte)| {
+                bits | (*byte as u32) << (16 - 8 * i)
+            });
+            // Each byte is split into 6-bit characters, a chunk of n bytes needs n + 1 of them
+            for i in 0..=chunk.len() {
+                let index = (bits >> (18 - 6 * i)) & 0x3f;
+                encoded.push(BASE64_URL_ALPHABET[index as usize] as char);
+            }
+        }
+        f.write_str(&encoded)
+    }
+}
+
+impl FromStr for PagingState {
+    type Err = PagingStateParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() % 4 == 1 {
+            return Err(PagingStateParseError);
+        }
+
+        let mut decoded = Vec::with_capacity(s.len() * 3 / 4);
+        for chunk in s.as_bytes().chunks(4) {
+            let mut bits = 0_u32;
+            for (i, character) in chunk.iter().enumerate() {
+                let index = BASE64_URL_ALPHABET
+                    .iter()
+                    .position(|c| c == character)
+                    .ok_or(PagingStateParseError)?;
+                bits |= (index as u32) << (18 - 6 * i);
+            }
+            for i in 0..chunk.len() - 1 {
+                decoded.push((bits >> (16 - 8 * i)) as u8);
+            }
+        }
+        Ok(PagingState(decoded.into()))
+    }
+}
+
 struct ReceivedPage {
     pub rows: Rows,
     pub tracing_id: Option<Uuid>,
+    pub size: usize,
+}
+
+/// Limits pages buffered by RowIterator, shared with its worker.\
+/// A page stays in the buffer from the moment the worker starts fetching it
+/// until the iterator consumes all of its rows.
+struct PageBuffer {
+    // One permit for the page being consumed and one for each page fetched ahead of it
+    permits: Semaphore,
+    buffered_bytes: AtomicUsize,
+    bytes_limit: Option<usize>,
+    page_released: Notify,
+}
+
+impl PageBuffer {
+    fn new(prefetch_pages: usize, bytes_limit: Option<usize>) -> Self {
+        PageBuffer {
+            permits: Semaphore::new(prefetch_pages.saturating_add(1).min(Semaphore::MAX_PERMITS)),
+            buffered_bytes: AtomicUsize::new(0),
+            bytes_limit,
+            page_released: Notify::new(),
+        }
+    }
+
+    fn from_config(config: &StatementConfig) -> Self {
+        Self::new(config.prefetch_pages, config.page_buffer_limit)
+    }
+
+    // Waits until there is room in the buffer for another page
+    async fn reserve_page(&self) {
+        if let Ok(permit) = self.permits.acquire().await {
+            permit.forget();
+        }
+        if let Some(bytes_limit) = self.bytes_limit {
+            while self.buffered_bytes.load(Ordering::Acquire) > bytes_limit {
+                self.page_released.notified().await;
+            }
+        }
+    }
+
+    fn page_received(&self, size: usize) {
+        self.buffered_bytes.fetch_add(size, Ordering::AcqRel);
+    }
+
+    fn release_page(&self, size: usize) {
+        self.buffered_bytes.fetch_sub(size, Ordering::AcqRel);
+        self.permits.add_permits(1);
+        self.page_released.notify_one();
+    }
 }
 
 pub(crate) struct PreparedIteratorConfig {
     pub prepared: PreparedStatement,
     pub values: SerializedValues,
+    pub paging_state: Option<PagingState>,
+    // Shared by all pages and their retries
+    pub timestamp: Option<i64>,
     pub default_consistency: Consistency,
     pub token: Option<Token>,
     pub retry_session: Box<dyn RetrySession>,
@@ -88,9 +233,15 @@ impl Stream for RowIterator {
         let mut s = self.as_mut();
 
         if s.is_current_page_exhausted() {
+            // Make room for the next pages
+            if let Some(size) = s.current_page_size.take() {
+                s.page_buffer.release_page(size);
+            }
+
             match Pin::new(&mut s.page_receiver).poll_recv(cx) {
                 Poll::Ready(Some(Ok(received_page))) => {
                     s.current_page = received_page.rows;
+                    s.current_page_size = Some(received_page.size);
                     s.current_row_idx = 0;
 
                     if let Some(tracing_id) = received_page.tracing_id {
@@ -126,9 +277,12 @@ impl RowIterator {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn new_for_query(
         mut query: Query,
         values: SerializedValues,
+        paging_state: Option<PagingState>,
+        timestamp: Option<i64>,
         default_consistency: Consistency,
         retry_session: Box<dyn RetrySession>,
         load_balancer: Arc<dyn LoadBalancingPolicy>,
@@ -138,8 +292,10 @@ impl RowIterator {
         if query.get_page_size().is_none() {
             query.set_page_size(DEFAULT_ITER_PAGE_SIZE);
         }
-        let (sender, mut receiver) = mpsc::channel(1);
+        let (sender, mut receiver) = mpsc::unbounded_channel();
         let consistency = query.config.determine_consistency(default_consistency);
+        let page_buffer = Arc::new(PageBuffer::from_config(&query.config));
+        let worker_page_buffer = page_buffer.clone();
 
         let worker_task = async move {
             let query_ref = &query;
@@ -151,7 +307,13 @@ impl RowIterator {
                               consistency: Consistency,
                               paging_state: Option<Bytes>| async move {
                 connection
-                    .query_with_consistency(query_ref, values_ref, consistency, paging_state)
+                    .query_with_consistency(
+                        query_ref,
+                        values_ref,
+                        consistency,
+                        paging_state,
+                        timestamp,
+                    )
                     .await
             };
 
@@ -160,12 +322,17 @@ impl RowIterator {
                 choose_connection,
                 page_query,
                 statement_info: Statement::default(),
+                target_node: query.config.target_node.clone(),
+                target_shard: query.config.determine_target_shard(),
                 query_is_idempotent: query.config.is_idempotent,
                 query_consistency: consistency,
                 retry_session,
                 load_balancer,
                 metrics,
-                paging_state: None,
+                paging_state: paging_state.map(PagingState::into_bytes),
+                page_buffer: worker_page_buffer,
+                page_reserved: false,
+                page_timeout: query.config.page_timeout,
                 history_listener: query.config.history_listener.clone(),
                 current_query_id: None,
                 current_attempt_id: None,
@@ -181,7 +348,9 @@ impl RowIterator {
         Ok(RowIterator {
             current_row_idx: 0,
             current_page: pages_received.rows,
+            current_page_size: Some(pages_received.size),
             page_receiver: receiver,
+            page_buffer,
             tracing_ids: if let Some(tracing_id) = pages_received.tracing_id {
                 vec![tracing_id]
             } else {
@@ -196,21 +365,25 @@ impl RowIterator {
         if config.prepared.get_page_size().is_none() {
             config.prepared.set_page_size(DEFAULT_ITER_PAGE_SIZE);
         }
-        let (sender, mut receiver) = mpsc::channel(1);
+        let (sender, mut receiver) = mpsc::unbounded_channel();
         let consistency = config
             .prepared
             .config
             .determine_consistency(config.default_consistency);
+        let page_buffer = Arc::new(PageBuffer::from_config(&config.prepared.config));
+        let worker_page_buffer = page_buffer.clone();
 
         let statement_info = Statement {
             token: config.token,
             keyspace: None,
+            is_confirmed_lwt: config.prepared.is_confirmed_lwt(),
         };
 
         let worker_task = async move {
             let prepared_ref = &config.prepared;
             let values_ref = &config.values;
             let token = config.token;
+            let timestamp = config.timestamp;
 
             let choose_connection = |node: Arc<Node>| async move {
                 match token {
@@ -223,7 +396,13 @@ impl RowIterator {
                               consistency: Consistency,
                               paging_state: Option<Bytes>| async move {
                 connection
-                    .execute_with_consistency(prepared_ref, values_ref, consistency, paging_state)
+                    .execute_with_consistency(
+                        prepared_ref,
+                        values_ref,
+                        consistency,
+                        paging_state,
+                        timestamp,
+                    )
                     .await
             };
 
@@ -232,12 +411,17 @@ impl RowIterator {
                 choose_connection,
                 page_query,
                 statement_info,
+                target_node: config.prepared.config.target_node.clone(),
+                target_shard: config.prepared.config.determine_target_shard(),
                 query_is_idempotent: config.prepared.config.is_idempotent,
                 query_consistency: consistency,
                 retry_session: config.retry_session,
                 load_balancer: config.load_balancer,
                 metrics: config.metrics,
-                paging_state: None,
+                paging_state: config.paging_state.map(PagingState::into_bytes),
+                page_buffer: worker_page_buffer,
+                page_reserved: false,
+                page_timeout: config.prepared.config.page_timeout,
                 history_listener: config.prepared.config.history_listener.clone(),
                 current_query_id: None,
                 current_attempt_id: None,
@@ -253,7 +437,9 @@ impl RowIterator {
         Ok(RowIterator {
             current_row_idx: 0,
             current_page: pages_received.rows,
+            current_page_size: Some(pages_received.size),
             page_receiver: receiver,
+            page_buffer,
             tracing_ids: if let Some(tracing_id) = pages_received.tracing_id {
                 vec![tracing_id]
             } else {
@@ -272,6 +458,24 @@ impl RowIterator {
         &self.current_page.metadata.col_specs
     }
 
+    /// Returns the paging state received with the page currently being consumed.\
+    /// Iterating from it with [`Session::query_iter_from`](crate::Session::query_iter_from)
+    /// or [`Session::execute_iter_from`](crate::Session::execute_iter_from) starts at the next page,
+    /// so it should be saved once all rows of the current page are consumed.
+    /// Returns `None` if the current page is the last one.
+    pub fn get_paging_state(&self) -> Option<PagingState> {
+        self.current_page
+            .metadata
+            .paging_state
+            .clone()
+            .map(PagingState)
+    }
+
+    /// Returns the number of rows of the current page which weren't consumed yet
+    pub fn get_remaining_rows_in_page(&self) -> usize {
+        self.current_page.rows.len() - self.current_row_idx
+    }
+
     fn is_current_page_exhausted(&self) -> bool {
         self.current_row_idx >= self.current_page.rows.len()
     }
@@ -280,7 +484,7 @@ impl RowIterator {
 // RowIteratorWorker works in the background to fetch pages
 // RowIterator receives them through a channel
 struct RowIteratorWorker<'a, ConnFunc, QueryFunc> {
-    sender: mpsc::Sender<Result<ReceivedPage, QueryError>>,
+    sender: mpsc::UnboundedSender<Result<ReceivedPage, QueryError>>,
 
     // Closure used to choose a connection from a node
     // AsyncFn(Arc<Node>) -> Result<Arc<Connection>, QueryError>
@@ -291,6 +495,8 @@ struct RowIteratorWorker<'a, ConnFunc, QueryFunc> {
     page_query: QueryFunc,
 
     statement_info: Statement<'a>,
+    target_node: Option<Arc<Node>>,
+    target_shard: Option<Shard>,
     query_is_idempotent: bool,
     query_consistency: Consistency,
 
@@ -300,6 +506,11 @@ struct RowIteratorWorker<'a, ConnFunc, QueryFunc> {
 
     paging_state: Option<Bytes>,
 
+    page_buffer: Arc<PageBuffer>,
+    // Room for the next page is reserved in the buffer, kept between retries of the page
+    page_reserved: bool,
+    page_timeout: Option<Duration>,
+
     history_listener: Option<Arc<dyn HistoryListener>>,
     current_query_id: Option<history::QueryId>,
     current_attempt_id: Option<history::AttemptId>,
@@ -313,7 +524,12 @@ where
     QueryFut: Future<Output = Result<QueryResponse, QueryError>>,
 {
     async fn work(mut self, cluster_data: Arc<ClusterData>) {
-        let query_plan = self.load_balancer.plan(&self.statement_info, &cluster_data);
+        let query_plan: Plan = match &self.target_node {
+            Some(node) => Box::new(std::iter::once(node.clone())),
+            None => defer_repreparing_nodes(
+                self.load_balancer.plan(&self.statement_info, &cluster_data),
+            ),
+        };
 
         let mut last_error: QueryError =
             QueryError::ProtocolError("Empty query plan - driver bug!");
@@ -325,10 +541,19 @@ where
             let span = trace_span!("Executing query", node = node.address.to_string().as_str());
             // For each node in the plan choose a connection to use
             // This connection will be reused for same node retries to preserve paging cache on the shard
-            let connection: Arc<Connection> = match (self.choose_connection)(node)
-                .instrument(span.clone())
-                .await
-            {
+            let connection_result = match self.target_shard {
+                Some(shard) => {
+                    node.connection_for_shard(shard)
+                        .instrument(span.clone())
+                        .await
+                }
+                None => {
+                    (self.choose_connection)(node)
+                        .instrument(span.clone())
+                        .await
+                }
+            };
+            let connection: Arc<Connection> = match connection_result {
                 Ok(connection) => connection,
                 Err(e) => {
                     trace!(
@@ -400,7 +625,7 @@ where
 
         // Send last_error to RowIterator - query failed fully
         self.log_query_error(&last_error);
-        let _ = self.sender.send(Err(last_error)).await;
+        let _ = self.sender.send(Err(last_error));
     }
 
     // Given a working connection query as many pages as possible until the first error
@@ -410,6 +635,16 @@ where
         consistency: Consistency,
     ) -> Result<(), QueryError> {
         loop {
+            if !self.page_reserved {
+                tokio::select! {
+                    _ = self.page_buffer.reserve_page() => self.page_reserved = true,
+                    _ = self.sender.closed() => {
+                        // RowIterator was dropped - should shutdown
+                        return Ok(());
+                    }
+                }
+            }
+
             self.metrics.inc_total_paged_queries();
             let query_start = std::time::Instant::now();
 
@@ -418,27 +653,42 @@ where
                 "Sending"
             );
             self.log_attempt_start(connection.get_connect_address());
-            let query_response: QueryResponse =
-                (self.page_query)(connection.clone(), consistency, self.paging_state.clone())
-                    .await?;
+            let page_query =
+                (self.page_query)(connection.clone(), consistency, self.paging_state.clone());
+            let query_response: QueryResponse = match self.page_timeout {
+                Some(timeout) => {
+                    tokio::time::timeout(timeout, page_query)
+                        .await
+                        .map_err(|_| {
+                            QueryError::RequestTimeout(format!(
+                                "Fetching a page took longer than {}ms",
+                                timeout.as_millis()
+                            ))
+                        })??
+                }
+                None => page_query.await?,
+            };
 
             match query_response.response {
-                Response::Result(result::Result::Rows(mut rows)) => {
+                Response::Result(result::Result::Rows(rows)) => {
                     let _ = self
                         .metrics
                         .log_query_latency(query_start.elapsed().as_millis() as u64);
                     self.log_attempt_success();
                     self.log_query_success();
 
-                    self.paging_state = rows.metadata.paging_state.take();
+                    self.paging_state = rows.metadata.paging_state.clone();
 
                     let received_page = ReceivedPage {
                         rows,
                         tracing_id: query_response.tracing_id,
+                        size: query_response.body_size,
                     };
+                    self.page_buffer.page_received(received_page.size);
+                    self.page_reserved = false;
 
                     // Send next page to RowIterator
-                    if self.sender.send(Ok(received_page)).await.is_err() {
+                    if self.sender.send(Ok(received_page)).is_err() {
                         // channel was closed, RowIterator was dropped - should shutdown
                         return Ok(());
                     }
@@ -566,6 +816,17 @@ impl<RowT> TypedRowIterator<RowT> {
     pub fn get_column_specs(&self) -> &[ColumnSpec] {
         self.row_iterator.get_column_specs()
     }
+
+    /// Returns the paging state received with the page currently being consumed,
+    /// see [`RowIterator::get_paging_state`]
+    pub fn get_paging_state(&self) -> Option<PagingState> {
+        self.row_iterator.get_paging_state()
+    }
+
+    /// Returns the number of rows of the current page which weren't consumed yet
+    pub fn get_remaining_rows_in_page(&self) -> usize {
+        self.row_iterator.get_remaining_rows_in_page()
+    }
 }
 
 /// Couldn't get next typed row from the iterator
@@ -595,7 +856,10 @@ impl<RowT: FromRow> Stream for TypedRowIterator<RowT> {
             };
 
         let next_ready: Option<Self::Item> = match next_elem {
-            Some(Ok(next_row)) => Some(RowT::from_row(next_row).map_err(|e| e.into())),
+            Some(Ok(next_row)) => Some(
+                RowT::from_row_with_specs(next_row, s.row_iterator.get_column_specs())
+                    .map_err(|e| e.into()),
+            ),
             Some(Err(e)) => Some(Err(e.into())),
             None => None,
         };
@@ -606,3 +870,94 @@ impl<RowT: FromRow> Stream for TypedRowIterator<RowT> {
 
 // TypedRowIterator can be moved freely for any RowT so it's Unpin
 impl<RowT> Unpin for TypedRowIterator<RowT> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{PageBuffer, PagingState, PagingStateParseError};
+    use bytes::Bytes;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn paging_state_string() {
+        let cases: &[(&[u8], &str)] = &[
+            (b"", ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg"),
+            (&[0xfb, 0xff, 0x00, 0x3e], "-_8APg"),
+        ];
+        for (bytes, string) in cases {
+            let paging_state = PagingState::from(Bytes::copy_from_slice(bytes));
+            assert_eq!(paging_state.to_string(), *string);
+            assert_eq!(string.parse::<PagingState>(), Ok(paging_state));
+        }
+
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        let paging_state = PagingState::from(Bytes::from(all_bytes));
+        assert_eq!(
+            paging_state.to_string().parse::<PagingState>(),
+            Ok(paging_state)
+        );
+
+        assert_eq!("Zm9vY".parse::<PagingState>(), Err(PagingStateParseError));
+        assert_eq!("Zm9v+g".parse::<PagingState>(), Err(PagingStateParseError));
+        assert_eq!("Zm9v=".parse::<PagingState>(), Err(PagingStateParseError));
+    }
+
+    async fn is_reserved(page_buffer: &PageBuffer) -> bool {
+        tokio::time::timeout(Duration::from_millis(50), page_buffer.reserve_page())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn page_buffer_prefetch() {
+        // Only the page being consumed is buffered
+        let page_buffer = PageBuffer::new(0, None);
+        assert!(is_reserved(&page_buffer).await);
+        page_buffer.page_received(100);
+        assert!(!is_reserved(&page_buffer).await);
+        page_buffer.release_page(100);
+        assert!(is_reserved(&page_buffer).await);
+
+        let page_buffer = PageBuffer::new(2, None);
+        for _ in 0..3 {
+            assert!(is_reserved(&page_buffer).await);
+            page_buffer.page_received(100);
+        }
+        assert!(!is_reserved(&page_buffer).await);
+        page_buffer.release_page(100);
+        assert!(is_reserved(&page_buffer).await);
+    }
+
+    #[tokio::test]
+    async fn page_buffer_bytes_limit() {
+        let page_buffer = Arc::new(PageBuffer::new(10, Some(150)));
+        assert!(is_reserved(&page_buffer).await);
+        page_buffer.page_received(100);
+        assert!(is_reserved(&page_buffer).await);
+        page_buffer.page_received(100);
+
+        // 200 bytes are buffered, fetching waits until a page is released
+        let waiting = tokio::spawn({
+            let page_buffer = page_buffer.clone();
+            async move { page_buffer.reserve_page().await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+        page_buffer.release_page(100);
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The limit doesn't prevent buffering a single page larger than it
+        let page_buffer = PageBuffer::new(10, Some(50));
+        assert!(is_reserved(&page_buffer).await);
+        page_buffer.page_received(100);
+        page_buffer.release_page(100);
+        assert!(is_reserved(&page_buffer).await);
+    }
+}
@@ -135,6 +135,21 @@ impl ChildLoadBalancingPolicy for DcAwareRoundRobinPolicy {
         };
         Box::new(plan.into_iter())
     }
+
+    fn apply_child_policy_for_lwt(
+        &self,
+        plan: Vec<Arc<Node>>,
+    ) -> Box<dyn Iterator<Item = Arc<Node>> + Send + Sync> {
+        let (local_nodes, remote_nodes): (Vec<_>, Vec<_>) = plan
+            .into_iter()
+            .partition(|node| DcAwareRoundRobinPolicy::is_local_node(node, &self.local_dc));
+
+        if self.include_remote_nodes {
+            Box::new(local_nodes.into_iter().chain(remote_nodes))
+        } else {
+            Box::new(local_nodes.into_iter())
+        }
+    }
 }
 
 #[cfg(test)]
@@ -17,23 +17,63 @@ pub use round_robin::RoundRobinPolicy;
 pub use token_aware::TokenAwarePolicy;
 
 /// Represents info about statement that can be used by load balancing policies.
+///
+/// New fields may be added, so outside of the driver it has to be created
+/// with [`Statement::new`] instead of a struct expression.
 #[derive(Default)]
+#[non_exhaustive]
 pub struct Statement<'a> {
     pub token: Option<Token>,
     pub keyspace: Option<&'a str>,
+    /// True if the statement is a lightweight transaction, as reported by the database
+    pub is_confirmed_lwt: bool,
 }
 
 impl<'a> Statement<'a> {
-    fn empty() -> Self {
+    /// Creates info about a statement which isn't a confirmed lightweight transaction
+    pub const fn new(token: Option<Token>, keyspace: Option<&'a str>) -> Self {
         Self {
-            token: None,
-            keyspace: None,
+            token,
+            keyspace,
+            is_confirmed_lwt: false,
         }
     }
+
+    /// Sets whether the statement is a lightweight transaction, as reported by the database
+    pub const fn with_confirmed_lwt(mut self, is_confirmed_lwt: bool) -> Self {
+        self.is_confirmed_lwt = is_confirmed_lwt;
+        self
+    }
+
+    fn empty() -> Self {
+        Self::new(None, None)
+    }
 }
 
 pub type Plan<'a> = Box<dyn Iterator<Item = Arc<Node>> + Send + Sync + 'a>;
 
+/// Moves nodes which are still re-preparing statements after coming back up to the end of the plan,
+/// so that they receive requests only if all the other nodes fail.
+pub(crate) fn defer_repreparing_nodes(plan: Plan<'_>) -> Plan<'_> {
+    let mut deferred: Vec<Arc<Node>> = Vec::new();
+    let mut exhausted = false;
+    let mut plan = plan;
+
+    Box::new(std::iter::from_fn(move || {
+        while !exhausted {
+            match plan.next() {
+                Some(node) if node.is_repreparing() => deferred.push(node),
+                Some(node) => return Some(node),
+                None => {
+                    exhausted = true;
+                    deferred.reverse();
+                }
+            }
+        }
+        deferred.pop()
+    }))
+}
+
 /// Policy that decides which nodes to contact for each query
 pub trait LoadBalancingPolicy: Send + Sync + std::fmt::Debug {
     /// It is used for each query to find which nodes to query first
@@ -51,6 +91,19 @@ pub trait ChildLoadBalancingPolicy: LoadBalancingPolicy {
         &self,
         plan: Vec<Arc<Node>>,
     ) -> Box<dyn Iterator<Item = Arc<Node>> + Send + Sync>;
+
+    /// Applies the policy to replicas of a lightweight transaction.
+    ///
+    /// Unlike [`apply_child_policy`](ChildLoadBalancingPolicy::apply_child_policy),
+    /// the order of nodes must not change between calls, so that all LWTs on a partition
+    /// are coordinated by the same replica, which reduces Paxos contention.
+    /// By default the plan is returned unchanged.
+    fn apply_child_policy_for_lwt(
+        &self,
+        plan: Vec<Arc<Node>>,
+    ) -> Box<dyn Iterator<Item = Arc<Node>> + Send + Sync> {
+        Box::new(plan.into_iter())
+    }
 }
 
 // Hashing round robin's index is a mitigation to problems that occur when a
@@ -98,6 +151,7 @@ mod tests {
     use crate::transport::topology::Peer;
     use std::collections::HashMap;
     use std::net::SocketAddr;
+    use uuid::Uuid;
 
     #[test]
     fn test_slice_rotation() {
@@ -127,10 +181,38 @@ mod tests {
         );
     }
 
+    // ConnectionKeeper (which lives in Node) requires context of Tokio runtime
+    #[tokio::test]
+    async fn test_defer_repreparing_nodes() {
+        let cluster = mock_cluster_data_for_round_robin_tests();
+        let mut nodes = cluster.all_nodes.clone();
+        nodes.sort_by_key(|node| node.address.port());
+        nodes[0].set_repreparing(true);
+        nodes[3].set_repreparing(true);
+
+        let plan: Plan = Box::new(nodes.clone().into_iter());
+        let ports: Vec<u16> = defer_repreparing_nodes(plan)
+            .map(|node| node.address.port())
+            .collect();
+        assert_eq!(ports, vec![2, 3, 5, 1, 4]);
+
+        nodes[0].set_repreparing(false);
+        nodes[3].set_repreparing(false);
+        let plan: Plan = Box::new(nodes.into_iter());
+        let ports: Vec<u16> = defer_repreparing_nodes(plan)
+            .map(|node| node.address.port())
+            .collect();
+        assert_eq!(ports, vec![1, 2, 3, 4, 5]);
+    }
+
     pub fn id_to_invalid_addr(id: u16) -> SocketAddr {
         SocketAddr::from(([255, 255, 255, 255], id))
     }
 
+    pub fn id_to_host_id(id: u16) -> Uuid {
+        Uuid::from_u128(id as u128)
+    }
+
     // creates ClusterData with info about 5 nodes living in 2 different datacenters
     // ring field is empty
     pub fn mock_cluster_data_for_round_robin_tests() -> ClusterData {
@@ -142,6 +224,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(*id),
                 tokens: Vec::new(),
                 untranslated_address: Some(tests::id_to_invalid_addr(*id)),
+                host_id: tests::id_to_host_id(*id),
+                release_version: None,
+                schema_version: None,
             })
             .collect::<Vec<_>>();
 
@@ -153,10 +238,7 @@ mod tests {
         ClusterData::new(info, &Default::default(), &HashMap::new(), &None, None)
     }
 
-    pub const EMPTY_STATEMENT: Statement = Statement {
-        token: None,
-        keyspace: None,
-    };
+    pub const EMPTY_STATEMENT: Statement = Statement::new(None, None);
 
     pub fn get_plan_and_collect_node_identifiers<L: LoadBalancingPolicy>(
         policy: &L,
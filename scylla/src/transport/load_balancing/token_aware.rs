@@ -154,10 +154,13 @@ impl LoadBalancingPolicy for TokenAwarePolicy {
                         .filter(move |node| !replicas_set.contains(&node.address))
                 };
 
-                let plan = self
-                    .child_policy
-                    .apply_child_policy(replicas)
-                    .chain(fallback_plan);
+                // LWTs are sent to replicas in the same order every time to avoid Paxos contention
+                let replicas_plan = if statement.is_confirmed_lwt {
+                    self.child_policy.apply_child_policy_for_lwt(replicas)
+                } else {
+                    self.child_policy.apply_child_policy(replicas)
+                };
+                let plan = replicas_plan.chain(fallback_plan);
                 Box::new(plan)
             }
             // fallback to child policy
@@ -202,6 +205,7 @@ mod tests {
                 statement: Statement {
                     token: Some(Token { value: 160 }),
                     keyspace: Some("keyspace_with_simple_strategy_replication_factor_2"),
+                    is_confirmed_lwt: false,
                 },
                 expected_plan: vec![3, 1],
             },
@@ -209,6 +213,7 @@ mod tests {
                 statement: Statement {
                     token: Some(Token { value: 60 }),
                     keyspace: Some("keyspace_with_simple_strategy_replication_factor_3"),
+                    is_confirmed_lwt: false,
                 },
                 expected_plan: vec![1, 2, 3],
             },
@@ -216,6 +221,7 @@ mod tests {
                 statement: Statement {
                     token: Some(Token { value: 500 }),
                     keyspace: Some("keyspace_with_simple_strategy_replication_factor_3"),
+                    is_confirmed_lwt: false,
                 },
                 expected_plan: vec![1, 2, 3],
             },
@@ -223,6 +229,7 @@ mod tests {
                 statement: Statement {
                     token: Some(Token { value: 60 }),
                     keyspace: Some("invalid"),
+                    is_confirmed_lwt: false,
                 },
                 expected_plan: vec![1],
             },
@@ -230,6 +237,7 @@ mod tests {
                 statement: Statement {
                     token: Some(Token { value: 60 }),
                     keyspace: None,
+                    is_confirmed_lwt: false,
                 },
                 expected_plan: vec![1],
             },
@@ -253,6 +261,7 @@ mod tests {
         let statement = Statement {
             token: Some(Token { value: 0 }),
             keyspace: Some("keyspace_with_nts"),
+            is_confirmed_lwt: false,
         };
 
         let plan = tests::get_plan_and_collect_node_identifiers(&policy, &statement, &cluster);
@@ -260,6 +269,27 @@ mod tests {
         assert_eq!(plan, expected_plan);
     }
 
+    #[tokio::test]
+    async fn test_token_aware_policy_with_lwt() {
+        use crate::transport::load_balancing::RoundRobinPolicy;
+
+        let cluster = mock_cluster_data_for_token_aware_tests();
+
+        let policy = TokenAwarePolicy::new(Box::new(RoundRobinPolicy::new()));
+
+        let statement = Statement::new(
+            Some(Token { value: 60 }),
+            Some("keyspace_with_simple_strategy_replication_factor_3"),
+        )
+        .with_confirmed_lwt(true);
+
+        // Replicas are always returned in ring order
+        for _ in 0..10 {
+            let plan = tests::get_plan_and_collect_node_identifiers(&policy, &statement, &cluster);
+            assert_eq!(plan, vec![1, 2, 3]);
+        }
+    }
+
     #[tokio::test]
     async fn test_token_aware_fallback_policy() {
         let cluster = mock_cluster_data_for_token_aware_tests();
@@ -291,6 +321,9 @@ mod tests {
                     Token { value: 500 },
                 ],
                 untranslated_address: Some(tests::id_to_invalid_addr(1)),
+                host_id: tests::id_to_host_id(1),
+                release_version: None,
+                schema_version: None,
             },
             Peer {
                 datacenter: Some("eu".into()),
@@ -302,6 +335,9 @@ mod tests {
                     Token { value: 300 },
                 ],
                 untranslated_address: Some(tests::id_to_invalid_addr(2)),
+                host_id: tests::id_to_host_id(2),
+                release_version: None,
+                schema_version: None,
             },
             Peer {
                 datacenter: Some("us".into()),
@@ -309,6 +345,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(3),
                 tokens: vec![Token { value: 200 }, Token { value: 400 }],
                 untranslated_address: Some(tests::id_to_invalid_addr(3)),
+                host_id: tests::id_to_host_id(3),
+                release_version: None,
+                schema_version: None,
             },
         ];
 
@@ -369,6 +408,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(1),
                 tokens: vec![Token { value: 50 }, Token { value: 200 }],
                 untranslated_address: Some(tests::id_to_invalid_addr(1)),
+                host_id: tests::id_to_host_id(1),
+                release_version: None,
+                schema_version: None,
             },
             Peer {
                 datacenter: Some("waw".into()),
@@ -376,6 +418,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(2),
                 tokens: vec![Token { value: 150 }],
                 untranslated_address: Some(tests::id_to_invalid_addr(2)),
+                host_id: tests::id_to_host_id(2),
+                release_version: None,
+                schema_version: None,
             },
             Peer {
                 datacenter: Some("waw".into()),
@@ -383,6 +428,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(3),
                 tokens: vec![Token { value: 510 }],
                 untranslated_address: Some(tests::id_to_invalid_addr(3)),
+                host_id: tests::id_to_host_id(3),
+                release_version: None,
+                schema_version: None,
             },
             Peer {
                 datacenter: Some("waw".into()),
@@ -390,6 +438,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(4),
                 tokens: vec![Token { value: 300 }],
                 untranslated_address: Some(tests::id_to_invalid_addr(4)),
+                host_id: tests::id_to_host_id(4),
+                release_version: None,
+                schema_version: None,
             },
             Peer {
                 datacenter: Some("her".into()),
@@ -397,6 +448,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(5),
                 tokens: vec![Token { value: 100 }],
                 untranslated_address: Some(tests::id_to_invalid_addr(5)),
+                host_id: tests::id_to_host_id(5),
+                release_version: None,
+                schema_version: None,
             },
             Peer {
                 datacenter: Some("her".into()),
@@ -404,6 +458,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(6),
                 tokens: vec![Token { value: 250 }],
                 untranslated_address: Some(tests::id_to_invalid_addr(6)),
+                host_id: tests::id_to_host_id(6),
+                release_version: None,
+                schema_version: None,
             },
             Peer {
                 datacenter: Some("her".into()),
@@ -411,6 +468,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(7),
                 tokens: vec![Token { value: 500 }],
                 untranslated_address: Some(tests::id_to_invalid_addr(7)),
+                host_id: tests::id_to_host_id(7),
+                release_version: None,
+                schema_version: None,
             },
             Peer {
                 datacenter: Some("her".into()),
@@ -418,6 +478,9 @@ mod tests {
                 address: tests::id_to_invalid_addr(8),
                 tokens: vec![Token { value: 400 }],
                 untranslated_address: Some(tests::id_to_invalid_addr(8)),
+                host_id: tests::id_to_host_id(8),
+                release_version: None,
+                schema_version: None,
             },
         ];
 
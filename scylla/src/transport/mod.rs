@@ -1,3 +1,4 @@
+pub mod bulk_writer;
 pub(crate) mod caching_session;
 mod cluster;
 pub(crate) mod connection;
@@ -9,11 +10,14 @@ pub mod load_balancing;
 pub(crate) mod metrics;
 mod node;
 pub mod partitioner;
+mod prepared_statements_registry;
 pub mod query_result;
 pub mod retry_policy;
 pub mod session;
 pub mod session_builder;
 pub mod speculative_execution;
+pub mod table_scan;
+pub mod timestamp_generator;
 pub mod topology;
 pub use crate::frame::{Authenticator, Compression};
 pub use scylla_cql::errors;
@@ -31,4 +35,4 @@ mod cql_types_test;
 mod cql_value_test;
 
 pub use cluster::ClusterData;
-pub use node::Node;
+pub use node::{Node, NodeCapabilities};
@@ -1,58 +1,126 @@
 /// Node represents a cluster node along with it's data and connections
-use crate::routing::{Sharder, Token};
+use crate::frame::protocol_features::ProtocolFeatures;
+use crate::routing::{Shard, Sharder, Token};
 use crate::transport::connection::Connection;
 use crate::transport::connection::VerifiedKeyspaceName;
 use crate::transport::connection_pool::{NodeConnectionPool, PoolConfig};
 use crate::transport::errors::QueryError;
+use crate::transport::topology::Peer;
 
 use std::{
+    future::Future,
     hash::{Hash, Hasher},
     net::SocketAddr,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
 };
+use uuid::Uuid;
 
 /// Node represents a cluster node along with it's data and connections
+///
+/// Nodes are identified by their host id - two `Node`s are equal
+/// if and only if they have the same `host_id`.
 #[derive(Debug)]
 pub struct Node {
     pub address: SocketAddr,
     pub datacenter: Option<String>,
     pub rack: Option<String>,
+    pub host_id: Uuid,
+    pub release_version: Option<String>,
 
-    // If the node is filtered out by the host filter, this will be None
-    pool: Option<NodeConnectionPool>,
+    // Schema version reported during the last metadata refresh
+    schema_version: Mutex<Option<Uuid>>,
+
+    // If the node is filtered out by the host filter, this will be None.
+    // The pool is shared with the node's updated versions after a metadata refresh.
+    pool: Option<Arc<NodeConnectionPool>>,
 
     down_marker: AtomicBool,
+
+    // Set while statements are prepared again on the node after it came back up
+    repreparing: AtomicBool,
 }
 
 impl Node {
     /// Creates new node which starts connecting in the background
     /// # Arguments
     ///
-    /// `address` - address to connect to
-    /// `compression` - preferred compression to use
-    /// `datacenter` - optional datacenter name
-    /// `rack` - optional rack name
+    /// `peer` - information about the node read from the cluster metadata
+    /// `pool_config` - configuration of the node's connection pool
+    /// `keyspace_name` - keyspace to use on the node's connections
+    /// `enabled` - whether connections to the node should be opened
     pub(crate) fn new(
-        address: SocketAddr,
+        peer: &Peer,
         pool_config: PoolConfig,
-        datacenter: Option<String>,
-        rack: Option<String>,
         keyspace_name: Option<VerifiedKeyspaceName>,
         enabled: bool,
     ) -> Self {
+        let address = peer.address;
         let pool = enabled.then(|| {
-            NodeConnectionPool::new(address.ip(), address.port(), pool_config, keyspace_name)
+            Arc::new(NodeConnectionPool::new(
+                address.ip(),
+                address.port(),
+                pool_config,
+                keyspace_name,
+            ))
         });
 
         Node {
             address,
-            datacenter,
-            rack,
+            datacenter: peer.datacenter.clone(),
+            rack: peer.rack.clone(),
+            host_id: peer.host_id,
+            release_version: peer.release_version.clone(),
+            schema_version: Mutex::new(peer.schema_version),
             pool,
             down_marker: false.into(),
+            repreparing: false.into(),
+        }
+    }
+
+    /// Creates the node with metadata updated from `peer`, which keeps the host id,
+    /// the down marker and, if the node stays enabled, the connection pool of `node`.
+    /// If the address has changed, the pool reconnects to the new address.
+    /// # Arguments
+    ///
+    /// `node` - the node before the metadata refresh
+    /// `peer` - information about the node read from the cluster metadata
+    /// `pool_config` - configuration of the pool, used only if the node becomes enabled
+    /// `keyspace_name` - keyspace to use, used only if the node becomes enabled
+    /// `enabled` - whether connections to the node should be opened
+    pub(crate) fn inherit_with_changes(
+        node: &Node,
+        peer: &Peer,
+        pool_config: PoolConfig,
+        keyspace_name: Option<VerifiedKeyspaceName>,
+        enabled: bool,
+    ) -> Self {
+        debug_assert_eq!(node.host_id, peer.host_id);
+
+        let pool = match &node.pool {
+            Some(pool) if enabled => {
+                if node.address != peer.address {
+                    pool.update_address(peer.address);
+                }
+                Some(pool.clone())
+            }
+            _ => Node::new(peer, pool_config, keyspace_name, enabled).pool,
+        };
+
+        Node {
+            address: peer.address,
+            datacenter: peer.datacenter.clone(),
+            rack: peer.rack.clone(),
+            host_id: peer.host_id,
+            release_version: peer.release_version.clone(),
+            schema_version: Mutex::new(peer.schema_version),
+            pool,
+            down_marker: node.is_down().into(),
+            // Statements are prepared on the updated node before it is published
+            // with the refreshed cluster data
+            repreparing: false.into(),
         }
     }
 
@@ -60,6 +128,22 @@ impl Node {
         self.pool.as_ref()?.sharder()
     }
 
+    /// Returns the options which the node reported as supported in response
+    /// to the `OPTIONS` request when establishing a connection.
+    /// Returns `None` if there is no working connection to the node.
+    pub fn capabilities(&self) -> Option<NodeCapabilities> {
+        let connection = self.pool.as_ref()?.random_connection().ok()?;
+        Some(NodeCapabilities {
+            protocol_features: *connection.get_protocol_features(),
+            sharder: connection
+                .get_shard_info()
+                .as_ref()
+                .map(|shard_info| shard_info.get_sharder()),
+            shard_aware_port: connection.get_shard_aware_port(),
+            supported_compression: connection.get_supported_compression().to_vec(),
+        })
+    }
+
     /// Get connection which should be used to connect using given token
     /// If this connection is broken get any random connection to this Node
     pub(crate) async fn connection_for_token(
@@ -69,6 +153,15 @@ impl Node {
         self.get_pool()?.connection_for_token(token)
     }
 
+    /// Get connection to the given shard
+    /// Fails if there is no working connection to this shard
+    pub(crate) async fn connection_for_shard(
+        &self,
+        shard: Shard,
+    ) -> Result<Arc<Connection>, QueryError> {
+        self.get_pool()?.connection_for_shard_number(shard)
+    }
+
     /// Get random connection
     pub(crate) async fn random_connection(&self) -> Result<Arc<Connection>, QueryError> {
         self.get_pool()?.random_connection()
@@ -85,10 +178,32 @@ impl Node {
         self.pool.is_some()
     }
 
+    /// Returns the schema version which the node reported
+    /// during the last refresh of cluster metadata.
+    /// To get the current schema versions use
+    /// [Session::schema_versions](crate::Session::schema_versions).
+    pub fn schema_version(&self) -> Option<Uuid> {
+        *self.schema_version.lock().unwrap()
+    }
+
+    pub(crate) fn update_schema_version(&self, schema_version: Option<Uuid>) {
+        *self.schema_version.lock().unwrap() = schema_version;
+    }
+
     pub(crate) fn change_down_marker(&self, is_down: bool) {
         self.down_marker.store(is_down, Ordering::Relaxed);
     }
 
+    /// Returns true while statements are prepared again on the node after it came back up.
+    /// Such nodes are moved to the end of query plans.
+    pub(crate) fn is_repreparing(&self) -> bool {
+        self.repreparing.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_repreparing(&self, repreparing: bool) {
+        self.repreparing.store(repreparing, Ordering::Relaxed);
+    }
+
     pub(crate) async fn use_keyspace(
         &self,
         keyspace_name: VerifiedKeyspaceName,
@@ -103,14 +218,34 @@ impl Node {
         self.get_pool()?.get_working_connections()
     }
 
+    /// Returns a future which completes on the next change of the node's connections.
+    /// It never completes for a disabled node.
+    pub(crate) fn pool_updated(&self) -> impl Future<Output = ()> + '_ {
+        let updated = self.pool.as_ref().map(|pool| pool.updated());
+        async move {
+            match updated {
+                Some(updated) => updated.await,
+                None => futures::future::pending().await,
+            }
+        }
+    }
+
     pub(crate) async fn wait_until_pool_initialized(&self) {
         if let Some(pool) = &self.pool {
             pool.wait_until_initialized().await;
         }
     }
 
+    #[cfg(test)]
+    pub(crate) fn shares_pool_with(&self, other: &Node) -> bool {
+        match (&self.pool, &other.pool) {
+            (Some(pool), Some(other_pool)) => Arc::ptr_eq(pool, other_pool),
+            _ => false,
+        }
+    }
+
     fn get_pool(&self) -> Result<&NodeConnectionPool, QueryError> {
-        self.pool.as_ref().ok_or_else(|| {
+        self.pool.as_deref().ok_or_else(|| {
             QueryError::IoError(Arc::new(std::io::Error::new(
                 std::io::ErrorKind::Other,
                 "No connections in the pool: the node has been disabled \
@@ -122,7 +257,7 @@ impl Node {
 
 impl PartialEq for Node {
     fn eq(&self, other: &Self) -> bool {
-        self.address == other.address
+        self.host_id == other.host_id
     }
 }
 
@@ -130,6 +265,20 @@ impl Eq for Node {}
 
 impl Hash for Node {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.address.hash(state);
+        self.host_id.hash(state);
     }
 }
+
+/// Options supported by a node, parsed from its `SUPPORTED` response
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NodeCapabilities {
+    /// Protocol extensions supported by the node
+    pub protocol_features: ProtocolFeatures,
+    /// Information about sharding, present only if the node is a Scylla node
+    pub sharder: Option<Sharder>,
+    /// Port on which the node accepts shard-aware connections
+    pub shard_aware_port: Option<u16>,
+    /// Names of compression algorithms supported by the node
+    pub supported_compression: Vec<String>,
+}
@@ -0,0 +1,240 @@
+//! Keeps track of statements prepared by the session
+
+use crate::prepared_statement::PreparedStatement;
+use crate::query::Query;
+use crate::transport::connection::Connection;
+use crate::transport::node::Node;
+
+use bytes::Bytes;
+use futures::{stream, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// How many statements are prepared concurrently on a node which came back up
+const REPREPARE_CONCURRENCY: usize = 16;
+
+/// How long to wait for connections to a node which came back up
+const REPREPARE_CONNECTIONS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The pool is considered filled when no new connection appears for this long
+const REPREPARE_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Remembers the statements prepared by the session, so that they can be
+/// prepared again on nodes which join the cluster or come back up.
+///
+/// The registry is bounded - after reaching its capacity, the statements
+/// which were registered first are forgotten.
+pub(crate) struct PreparedStatementsRegistry {
+    capacity: usize,
+    statements: Mutex<RegisteredStatements>,
+}
+
+#[derive(Default)]
+struct RegisteredStatements {
+    // Statement contents indexed by the prepared statement id
+    by_id: HashMap<Bytes, String>,
+    // Ids in order of registration, used to evict the oldest statements
+    order: VecDeque<Bytes>,
+}
+
+impl PreparedStatementsRegistry {
+    /// Creates a registry which remembers at most `capacity` statements.
+    /// A registry with zero capacity doesn't remember anything.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            statements: Mutex::new(Default::default()),
+        }
+    }
+
+    pub(crate) fn register(&self, prepared: &PreparedStatement) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut statements = self.statements.lock().unwrap();
+        if statements.by_id.contains_key(prepared.get_id()) {
+            return;
+        }
+
+        while statements.order.len() >= self.capacity {
+            if let Some(evicted_id) = statements.order.pop_front() {
+                statements.by_id.remove(&evicted_id);
+            }
+        }
+
+        statements.order.push_back(prepared.get_id().clone());
+        statements.by_id.insert(
+            prepared.get_id().clone(),
+            prepared.get_statement().to_owned(),
+        );
+    }
+
+    /// Returns contents of all the remembered statements
+    pub(crate) fn statements(&self) -> Vec<String> {
+        self.statements
+            .lock()
+            .unwrap()
+            .by_id
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.statements.lock().unwrap().by_id.is_empty()
+    }
+
+    /// Prepares all the remembered statements on every working connection of the node.
+    /// Failures are only logged - a statement which couldn't be prepared here
+    /// will be prepared on demand once the node responds with `Unprepared`.
+    pub(crate) async fn reprepare_on_node(&self, node: &Node) {
+        if self.is_empty() {
+            return;
+        }
+
+        match node.get_working_connections() {
+            Ok(connections) => self.reprepare_on_connections(node, &connections).await,
+            Err(e) => debug!(
+                node = node.address.to_string().as_str(),
+                error = e.to_string().as_str(),
+                "No working connections to re-prepare statements on"
+            ),
+        }
+    }
+
+    /// Like [`reprepare_on_node`](Self::reprepare_on_node), but for a node which has just
+    /// come back up and usually has no connections yet.
+    ///
+    /// Waits up to [`REPREPARE_CONNECTIONS_TIMEOUT`] for the pool to connect. Connections opened
+    /// while the pool is being filled are handled as well, until no new connection appears
+    /// for [`REPREPARE_SETTLE_TIME`].
+    pub(crate) async fn reprepare_on_reconnected_node(&self, node: &Node) {
+        if self.is_empty() {
+            return;
+        }
+
+        let deadline = Instant::now() + REPREPARE_CONNECTIONS_TIMEOUT;
+        let mut reprepared: Vec<Arc<Connection>> = Vec::new();
+        loop {
+            // Registered before looking at the connections, so that no update is missed
+            let pool_updated = node.pool_updated();
+
+            let new_connections: Vec<Arc<Connection>> = node
+                .get_working_connections()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|connection| {
+                    !reprepared
+                        .iter()
+                        .any(|reprepared| Arc::ptr_eq(reprepared, connection))
+                })
+                .collect();
+            if !new_connections.is_empty() {
+                self.reprepare_on_connections(node, &new_connections).await;
+                reprepared.extend(new_connections);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let wait = if reprepared.is_empty() {
+                remaining
+            } else {
+                remaining.min(REPREPARE_SETTLE_TIME)
+            };
+            if tokio::time::timeout(wait, pool_updated).await.is_err() {
+                break;
+            }
+        }
+
+        if reprepared.is_empty() {
+            debug!(
+                node = node.address.to_string().as_str(),
+                "No working connections to re-prepare statements on"
+            );
+        }
+    }
+
+    async fn reprepare_on_connections(&self, node: &Node, connections: &[Arc<Connection>]) {
+        let queries: Vec<Query> = self.statements().into_iter().map(Query::new).collect();
+        debug!(
+            node = node.address.to_string().as_str(),
+            statements = queries.len(),
+            connections = connections.len(),
+            "Re-preparing statements"
+        );
+
+        // Limit concurrency, so that a node which has just restarted isn't flooded with requests
+        let prepare_futures: Vec<_> = connections
+            .iter()
+            .flat_map(|connection| queries.iter().map(move |query| connection.prepare(query)))
+            .collect();
+
+        let results: Vec<_> = stream::iter(prepare_futures)
+            .buffer_unordered(REPREPARE_CONCURRENCY)
+            .collect()
+            .await;
+        let failed = results.iter().filter(|result| result.is_err()).count();
+
+        if failed > 0 {
+            warn!(
+                node = node.address.to_string().as_str(),
+                failed, "Failed to re-prepare some statements"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PreparedStatementsRegistry;
+    use crate::frame::response::result::PreparedMetadata;
+    use crate::prepared_statement::PreparedStatement;
+    use bytes::Bytes;
+
+    fn make_prepared(id: &'static [u8], contents: &str) -> PreparedStatement {
+        PreparedStatement::new(
+            Bytes::from_static(id),
+            PreparedMetadata::new(0, vec![], vec![]),
+            Default::default(),
+            contents.to_owned(),
+            None,
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn registry_remembers_statements() {
+        let registry = PreparedStatementsRegistry::new(8);
+        assert!(registry.is_empty());
+
+        registry.register(&make_prepared(b"1", "SELECT a FROM ks.t"));
+        registry.register(&make_prepared(b"1", "SELECT a FROM ks.t"));
+        registry.register(&make_prepared(b"2", "SELECT b FROM ks.t"));
+
+        let mut statements = registry.statements();
+        statements.sort();
+        assert_eq!(statements, vec!["SELECT a FROM ks.t", "SELECT b FROM ks.t"]);
+    }
+
+    #[test]
+    fn registry_evicts_oldest_statements() {
+        let registry = PreparedStatementsRegistry::new(2);
+
+        registry.register(&make_prepared(b"1", "SELECT a FROM ks.t"));
+        registry.register(&make_prepared(b"2", "SELECT b FROM ks.t"));
+        registry.register(&make_prepared(b"3", "SELECT c FROM ks.t"));
+
+        let mut statements = registry.statements();
+        statements.sort();
+        assert_eq!(statements, vec!["SELECT b FROM ks.t", "SELECT c FROM ks.t"]);
+    }
+
+    #[test]
+    fn registry_with_zero_capacity_is_disabled() {
+        let registry = PreparedStatementsRegistry::new(0);
+        registry.register(&make_prepared(b"1", "SELECT a FROM ks.t"));
+        assert!(registry.is_empty());
+    }
+}
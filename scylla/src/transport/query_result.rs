@@ -1,7 +1,9 @@
 use crate::frame::response::cql_to_rust::{FromRow, FromRowError};
 use crate::frame::response::result::ColumnSpec;
+use crate::frame::response::result::CqlValue;
 use crate::frame::response::result::Row;
-use crate::transport::session::{IntoTypedRows, TypedRowIter};
+use crate::transport::errors::QueryError;
+use crate::transport::session::TypedRowIter;
 use bytes::Bytes;
 use thiserror::Error;
 use uuid::Uuid;
@@ -47,8 +49,9 @@ impl QueryResult {
     /// Returns the received rows parsed as the given type.\
     /// Equal to `rows()?.into_typed()`.\
     /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows).
-    pub fn rows_typed<RowT: FromRow>(self) -> Result<TypedRowIter<RowT>, RowsExpectedError> {
-        Ok(self.rows()?.into_typed())
+    pub fn rows_typed<RowT: FromRow>(mut self) -> Result<TypedRowIter<RowT>, RowsExpectedError> {
+        let col_specs = std::mem::take(&mut self.col_specs);
+        Ok(TypedRowIter::with_col_specs(self.rows()?, col_specs))
     }
 
     /// Returns `Ok` for a result of a query that shouldn't contain any rows.\
@@ -70,8 +73,9 @@ impl QueryResult {
     /// Returns rows parsed as the given type.\
     /// When `QueryResult.rows` is `None`, returns 0 rows.\
     /// Equal to `rows_or_empty().into_typed::<RowT>()`.
-    pub fn rows_typed_or_empty<RowT: FromRow>(self) -> TypedRowIter<RowT> {
-        self.rows_or_empty().into_typed::<RowT>()
+    pub fn rows_typed_or_empty<RowT: FromRow>(mut self) -> TypedRowIter<RowT> {
+        let col_specs = std::mem::take(&mut self.col_specs);
+        TypedRowIter::with_col_specs(self.rows_or_empty(), col_specs)
     }
 
     /// Returns first row from the received rows.\
@@ -85,8 +89,9 @@ impl QueryResult {
 
     /// Returns first row from the received rows parsed as the given type.\
     /// When the first row is not available, returns an error.
-    pub fn first_row_typed<RowT: FromRow>(self) -> Result<RowT, FirstRowTypedError> {
-        Ok(self.first_row()?.into_typed()?)
+    pub fn first_row_typed<RowT: FromRow>(mut self) -> Result<RowT, FirstRowTypedError> {
+        let col_specs = std::mem::take(&mut self.col_specs);
+        Ok(self.first_row()?.into_typed_with_specs(&col_specs)?)
     }
 
     /// Returns `Option<RowT>` containing the first of a result.\
@@ -98,10 +103,11 @@ impl QueryResult {
     /// Returns `Option<RowT>` containing the first of a result.\
     /// Fails when the query isn't of a type that could return rows, same as [`rows()`](QueryResult::rows).
     pub fn maybe_first_row_typed<RowT: FromRow>(
-        self,
+        mut self,
     ) -> Result<Option<RowT>, MaybeFirstRowTypedError> {
+        let col_specs = std::mem::take(&mut self.col_specs);
         match self.maybe_first_row()? {
-            Some(row) => Ok(Some(row.into_typed::<RowT>()?)),
+            Some(row) => Ok(Some(row.into_typed_with_specs::<RowT>(&col_specs)?)),
             None => Ok(None),
         }
     }
@@ -120,8 +126,11 @@ impl QueryResult {
 
     /// Returns the only received row parsed as the given type.\
     /// Fails if the result is anything else than a single row.\
-    pub fn single_row_typed<RowT: FromRow>(self) -> Result<RowT, SingleRowTypedError> {
-        Ok(self.single_row()?.into_typed::<RowT>()?)
+    pub fn single_row_typed<RowT: FromRow>(mut self) -> Result<RowT, SingleRowTypedError> {
+        let col_specs = std::mem::take(&mut self.col_specs);
+        Ok(self
+            .single_row()?
+            .into_typed_with_specs::<RowT>(&col_specs)?)
     }
 
     /// Returns a column specification for a column with given name, or None if not found
@@ -132,6 +141,34 @@ impl QueryResult {
             .find(|(_id, spec)| spec.name == name)
     }
 
+    /// Returns whether a lightweight transaction (e.g. `INSERT ... IF NOT EXISTS`) was applied.\
+    /// The flag is read from the `[applied]` column of the first row.
+    pub fn lwt_applied(&self) -> Result<bool, LwtResultError> {
+        let rows = self.rows.as_ref().ok_or(RowsExpectedError)?;
+        let first_row = rows.first().ok_or(LwtResultError::RowsEmpty)?;
+        lwt_applied_flag(&self.col_specs, first_row)
+    }
+
+    /// Returns the result of a lightweight transaction, with the `[applied]` flag separated
+    /// from the existing values of the row, parsed as the given type.\
+    /// The existing row is `None` if the result contains no other columns than `[applied]`
+    /// or if all of them are null - for example when `INSERT ... IF NOT EXISTS` was applied.
+    pub fn lwt_result_typed<RowT: FromRow>(mut self) -> Result<LwtResult<RowT>, LwtResultError> {
+        let col_specs = std::mem::take(&mut self.col_specs);
+        let mut first_row = self.maybe_first_row()?.ok_or(LwtResultError::RowsEmpty)?;
+        let applied = lwt_applied_flag(&col_specs, &first_row)?;
+
+        first_row.columns.remove(0);
+        let existing = if first_row.columns.iter().all(Option::is_none) {
+            None
+        } else {
+            let existing_specs = col_specs.get(1..).unwrap_or(&[]);
+            Some(first_row.into_typed_with_specs(existing_specs)?)
+        };
+
+        Ok(LwtResult { applied, existing })
+    }
+
     /// This function is used to merge results of multiple paged queries into one.\
     /// other is the result of a new paged query.\
     /// It is merged with current result kept in self.\
@@ -150,6 +187,30 @@ impl QueryResult {
     }
 }
 
+// Reads the `[applied]` column, which is the first column in results of lightweight transactions
+fn lwt_applied_flag(col_specs: &[ColumnSpec], row: &Row) -> Result<bool, LwtResultError> {
+    if let Some(spec) = col_specs.first() {
+        if spec.name != "[applied]" {
+            return Err(LwtResultError::NotLwtResult);
+        }
+    }
+    match row.columns.first() {
+        Some(Some(CqlValue::Boolean(applied))) => Ok(*applied),
+        _ => Err(LwtResultError::NotLwtResult),
+    }
+}
+
+/// Result of a lightweight transaction returned by
+/// [`QueryResult::lwt_result_typed()`](QueryResult::lwt_result_typed)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LwtResult<RowT> {
+    /// Whether the transaction was applied
+    pub applied: bool,
+    /// Existing values of the columns returned along with the `[applied]` flag.\
+    /// When the transaction wasn't applied, these are the values which didn't satisfy the condition.
+    pub existing: Option<RowT>,
+}
+
 /// [`QueryResult::rows()`](QueryResult::rows) or a similar function called on a bad QueryResult.\
 /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
 /// `QueryResult.rows` is `Some` for queries that can return rows (e.g `SELECT`).\
@@ -254,6 +315,39 @@ pub enum SingleRowTypedError {
     FromRowError(#[from] FromRowError),
 }
 
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LwtResultError {
+    /// [`QueryResult::lwt_applied()`](QueryResult::lwt_applied) or a similar function called on a bad QueryResult.\
+    /// Expected `QueryResult.rows` to be `Some`, but it was `None`.\
+    /// Results of lightweight transactions always contain rows.
+    #[error(transparent)]
+    RowsExpected(#[from] RowsExpectedError),
+
+    /// Rows in `QueryResult` are empty
+    #[error("Rows in QueryResult are empty")]
+    RowsEmpty,
+
+    /// The first column of the result isn't a boolean `[applied]` column
+    #[error("QueryResult is not a result of a lightweight transaction, the first column is not a boolean [applied] column")]
+    NotLwtResult,
+
+    /// Parsing the existing row as the given type failed
+    #[error(transparent)]
+    FromRowError(#[from] FromRowError),
+}
+
+/// Error returned by [`Session::execute_typed()`](crate::Session::execute_typed)
+#[derive(Debug, Error, Clone)]
+pub enum TypedQueryError {
+    /// Executing the statement failed
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// Parsing a row as the given type failed
+    #[error(transparent)]
+    FromRowError(#[from] FromRowError),
+}
+
 impl From<FirstRowError> for FirstRowTypedError {
     fn from(err: FirstRowError) -> FirstRowTypedError {
         match err {
@@ -600,4 +694,142 @@ mod tests {
             Err(SingleRowTypedError::FromRowError(_))
         ));
     }
+
+    fn make_lwt_query_result(applied: bool, existing: Option<i32>) -> QueryResult {
+        let table_spec = TableSpec {
+            ks_name: "some_keyspace".to_string(),
+            table_name: "some_table".to_string(),
+        };
+        let spec = |name: &str, typ: ColumnType| ColumnSpec {
+            table_spec: table_spec.clone(),
+            name: name.to_string(),
+            typ,
+        };
+
+        QueryResult {
+            rows: Some(vec![Row {
+                columns: vec![
+                    Some(CqlValue::Boolean(applied)),
+                    existing.map(CqlValue::Int),
+                ],
+            }]),
+            col_specs: vec![
+                spec("[applied]", ColumnType::Boolean),
+                spec("column0", ColumnType::Int),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lwt_result_test() {
+        assert_eq!(
+            make_not_rows_query_result().lwt_applied(),
+            Err(LwtResultError::RowsExpected(RowsExpectedError))
+        );
+        assert_eq!(
+            make_rows_query_result(0).lwt_applied(),
+            Err(LwtResultError::RowsEmpty)
+        );
+        assert_eq!(
+            make_rows_query_result(1).lwt_applied(),
+            Err(LwtResultError::NotLwtResult)
+        );
+
+        assert_eq!(make_lwt_query_result(true, None).lwt_applied(), Ok(true));
+        assert_eq!(
+            make_lwt_query_result(false, Some(5)).lwt_applied(),
+            Ok(false)
+        );
+
+        assert_eq!(
+            make_lwt_query_result(true, None).lwt_result_typed::<(i32,)>(),
+            Ok(LwtResult {
+                applied: true,
+                existing: None
+            })
+        );
+        assert_eq!(
+            make_lwt_query_result(false, Some(5)).lwt_result_typed::<(i32,)>(),
+            Ok(LwtResult {
+                applied: false,
+                existing: Some((5,))
+            })
+        );
+        assert!(matches!(
+            make_lwt_query_result(false, Some(5)).lwt_result_typed::<(String,)>(),
+            Err(LwtResultError::FromRowError(_))
+        ));
+
+        // Cassandra returns only the [applied] column when the transaction was applied
+        let mut applied_only = make_lwt_query_result(true, None);
+        applied_only.col_specs.truncate(1);
+        applied_only.rows.as_mut().unwrap()[0].columns.truncate(1);
+        assert_eq!(
+            applied_only.lwt_result_typed::<(i32,)>(),
+            Ok(LwtResult {
+                applied: true,
+                existing: None
+            })
+        );
+    }
+
+    #[test]
+    fn rows_typed_matched_by_name() {
+        use crate::cql_to_rust::FromRowError;
+        use crate::macros::FromRow;
+        use crate::IntoTypedRows;
+
+        #[derive(FromRow, Debug, PartialEq, Eq)]
+        #[scylla(crate = "crate", match_by_name)]
+        struct ByName {
+            text: String,
+            #[scylla(rename = "column0")]
+            int: i32,
+        }
+
+        // Columns: (text, column0)
+        let make_result = || {
+            let mut result = make_rows_query_result(2);
+            let mut text_spec = result.col_specs[0].clone();
+            text_spec.name = "text".to_string();
+            text_spec.typ = ColumnType::Text;
+            result.col_specs.insert(0, text_spec);
+            for (i, row) in result.rows.as_mut().unwrap().iter_mut().enumerate() {
+                row.columns
+                    .insert(0, Some(CqlValue::Text(format!("val{}", i))));
+            }
+            result
+        };
+
+        // Rows converted without their column specifications can't be matched by name
+        let rows = make_result().rows.unwrap();
+        let without_specs: Vec<_> = rows.into_typed::<ByName>().collect();
+        assert_eq!(
+            without_specs,
+            vec![
+                Err(FromRowError::ColumnSpecsRequired),
+                Err(FromRowError::ColumnSpecsRequired)
+            ]
+        );
+
+        let rows: Vec<ByName> = make_result()
+            .rows_typed::<ByName>()
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ByName {
+                    text: "val0".to_string(),
+                    int: 0
+                },
+                ByName {
+                    text: "val1".to_string(),
+                    int: 1
+                }
+            ]
+        );
+    }
 }
@@ -8,12 +8,18 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use futures::future::join_all;
 use futures::future::try_join_all;
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{self, BoxStream, FuturesUnordered, Stream, StreamExt};
+use itertools::Itertools;
 use scylla_cql::frame::response::NonErrorResponse;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::future::Future;
 use std::net::SocketAddr;
+use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
 use tokio::net::lookup_host;
 use tokio::time::timeout;
@@ -26,29 +32,40 @@ use super::errors::{BadQuery, NewSessionError, QueryError};
 use super::topology::UntranslatedPeer;
 use crate::cql_to_rust::FromRow;
 use crate::frame::response::cql_to_rust::FromRowError;
-use crate::frame::response::result;
-use crate::frame::value::{BatchValues, SerializedValues, ValueList};
-use crate::prepared_statement::{PartitionKeyError, PreparedStatement};
+use crate::frame::response::result::{self, ColumnType};
+use crate::frame::value::{
+    BatchValues, NoneAsUnsetBatchValues, SerializedValues, TypeCheckError, ValueList,
+};
+use crate::prepared_statement::{PartitionKeyError, PreparedStatement, TypedPreparedStatement};
 use crate::query::Query;
-use crate::routing::Token;
+use crate::query_builder::quote_identifier;
+use crate::routing::{Shard, Token};
+use crate::statement::idempotence::infer_is_idempotent;
+use crate::statement::server_timeout::client_timeout_for;
 use crate::statement::{Consistency, SerialConsistency};
 use crate::tracing::{GetTracingConfig, TracingEvent, TracingInfo};
 use crate::transport::cluster::{Cluster, ClusterData, ClusterNeatDebug};
 use crate::transport::connection::{Connection, ConnectionConfig, VerifiedKeyspaceName};
 use crate::transport::connection_pool::PoolConfig;
 use crate::transport::host_filter::HostFilter;
-use crate::transport::iterator::{PreparedIteratorConfig, RowIterator};
+use crate::transport::iterator::{
+    PagingState, PreparedIteratorConfig, RowIterator, TypedRowIterator,
+};
 use crate::transport::load_balancing::{
-    LoadBalancingPolicy, RoundRobinPolicy, Statement, TokenAwarePolicy,
+    defer_repreparing_nodes, LoadBalancingPolicy, Plan, RoundRobinPolicy, Statement,
+    TokenAwarePolicy,
 };
 use crate::transport::metrics::Metrics;
 use crate::transport::node::Node;
-use crate::transport::query_result::QueryResult;
+use crate::transport::prepared_statements_registry::PreparedStatementsRegistry;
+use crate::transport::query_result::{QueryResult, TypedQueryError};
 use crate::transport::retry_policy::{
     DefaultRetryPolicy, QueryInfo, RetryDecision, RetryPolicy, RetrySession,
 };
 use crate::transport::speculative_execution;
 use crate::transport::speculative_execution::SpeculativeExecutionPolicy;
+use crate::transport::table_scan::{ring_ranges, ScanOptions, TableScan};
+use crate::transport::timestamp_generator::{is_write, TimestampGenerator};
 use crate::transport::Compression;
 use crate::{
     batch::{Batch, BatchStatement},
@@ -60,6 +77,9 @@ pub use crate::transport::connection_pool::PoolSize;
 #[cfg(feature = "ssl")]
 use openssl::ssl::SslContext;
 
+/// Used when checking schema agreement if the session has no request timeout
+const DEFAULT_SCHEMA_VERSION_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Copy, Clone)]
 pub enum TranslationError {
     NoRuleForAddress,
@@ -119,6 +139,10 @@ pub struct Session {
     auto_await_schema_agreement_timeout: Option<Duration>,
     request_timeout: Option<Duration>,
     refresh_metadata_on_auto_schema_agreement: bool,
+    prepared_statements: Arc<PreparedStatementsRegistry>,
+    schema_agreement_scope: SchemaAgreementScope,
+    infer_idempotence: bool,
+    timestamp_generator: Option<Arc<dyn TimestampGenerator>>,
 }
 
 /// This implementation deliberately omits some details from Cluster in order
@@ -212,6 +236,24 @@ pub struct SessionConfig {
     /// If true, full schema metadata is fetched after successfully reaching a schema agreement.
     /// It is true by default but can be disabled if successive schema-altering statements should be performed.
     pub refresh_metadata_on_auto_schema_agreement: bool,
+
+    /// Maximum number of prepared statements remembered by the session in order to prepare them
+    /// on nodes that join the cluster or come back up, before they start receiving requests.
+    /// When the limit is reached, the oldest statements are forgotten. Zero disables the feature.
+    pub prepared_statements_registry_capacity: usize,
+
+    /// Nodes which have to agree on the schema version for the schema agreement to be reached.
+    /// Nodes that are down or unreachable are never taken into account.
+    pub schema_agreement_scope: SchemaAgreementScope,
+
+    /// Generates timestamps for requests which don't have a timestamp set explicitly.
+    /// If `None`, such requests are timestamped by the coordinator.
+    pub timestamp_generator: Option<Arc<dyn TimestampGenerator>>,
+
+    /// If true, queries and statements prepared by the session which aren't explicitly marked
+    /// as idempotent or non-idempotent are marked idempotent if their CQL text shows they are.
+    /// See [`infer_is_idempotent`](crate::statement::idempotence::infer_is_idempotent).
+    pub infer_idempotence: bool,
 }
 
 /// Describes database server known on Session startup.
@@ -221,6 +263,39 @@ pub enum KnownNode {
     Address(SocketAddr),
 }
 
+/// Describes which nodes have to agree on the schema version
+/// for the schema agreement to be reached.
+///
+/// Nodes that are down or cannot be reached are never taken into account,
+/// so an unavailable datacenter doesn't prevent the agreement.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SchemaAgreementScope {
+    /// All nodes in the cluster
+    AllNodes,
+    /// Only nodes from the datacenter with given name
+    Datacenter(String),
+    /// Only nodes with given addresses
+    Nodes(Vec<SocketAddr>),
+}
+
+// `#[default]` on enum variants requires Rust 1.62, which is above our MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for SchemaAgreementScope {
+    fn default() -> Self {
+        SchemaAgreementScope::AllNodes
+    }
+}
+
+impl SchemaAgreementScope {
+    fn contains(&self, node: &Node) -> bool {
+        match self {
+            SchemaAgreementScope::AllNodes => true,
+            SchemaAgreementScope::Datacenter(dc) => node.datacenter.as_ref() == Some(dc),
+            SchemaAgreementScope::Nodes(addresses) => addresses.contains(&node.address),
+        }
+    }
+}
+
 impl SessionConfig {
     /// Creates a [`SessionConfig`] with default configuration
     /// # Default configuration
@@ -258,6 +333,10 @@ impl SessionConfig {
             address_translator: None,
             host_filter: None,
             refresh_metadata_on_auto_schema_agreement: true,
+            prepared_statements_registry_capacity: 1024,
+            schema_agreement_scope: SchemaAgreementScope::AllNodes,
+            timestamp_generator: None,
+            infer_idempotence: false,
         }
     }
 
@@ -355,6 +434,13 @@ impl Default for SessionConfig {
 /// Trait used to implement `Vec<result::Row>::into_typed<RowT>`
 // This is the only way to add custom method to Vec
 pub trait IntoTypedRows {
+    /// Converts the rows into the given type, matching columns with fields by position.
+    ///
+    /// Specifications of the columns are not known here, so rows of types
+    /// deriving `FromRow` with `#[scylla(match_by_name)]` fail to convert with
+    /// [`FromRowError::ColumnSpecsRequired`](crate::cql_to_rust::FromRowError::ColumnSpecsRequired).
+    /// Use [`QueryResult::rows_typed`](crate::QueryResult::rows_typed) or similar methods instead,
+    /// which pass the specifications of the columns.
     fn into_typed<RowT: FromRow>(self) -> TypedRowIter<RowT>;
 }
 
@@ -364,6 +450,7 @@ impl IntoTypedRows for Vec<result::Row> {
     fn into_typed<RowT: FromRow>(self) -> TypedRowIter<RowT> {
         TypedRowIter {
             row_iter: self.into_iter(),
+            col_specs: Vec::new(),
             phantom_data: Default::default(),
         }
     }
@@ -373,17 +460,120 @@ impl IntoTypedRows for Vec<result::Row> {
 /// Returned by `rows.into_typed::<(...)>()`
 pub struct TypedRowIter<RowT: FromRow> {
     row_iter: std::vec::IntoIter<result::Row>,
+    // Empty if the specifications are not known
+    col_specs: Vec<result::ColumnSpec>,
     phantom_data: std::marker::PhantomData<RowT>,
 }
 
+impl<RowT: FromRow> TypedRowIter<RowT> {
+    pub(crate) fn with_col_specs(
+        rows: Vec<result::Row>,
+        col_specs: Vec<result::ColumnSpec>,
+    ) -> Self {
+        TypedRowIter {
+            row_iter: rows.into_iter(),
+            col_specs,
+            phantom_data: Default::default(),
+        }
+    }
+}
+
 impl<RowT: FromRow> Iterator for TypedRowIter<RowT> {
     type Item = Result<RowT, FromRowError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.row_iter.next().map(RowT::from_row)
+        let col_specs = &self.col_specs;
+        self.row_iter
+            .next()
+            .map(|row| RowT::from_row_with_specs(row, col_specs))
+    }
+}
+
+/// Stream of results returned by [`Session::execute_many`].\
+/// Results are returned in the same order as the values they were executed with.
+pub struct ExecuteMany<'a> {
+    results: BoxStream<'a, Result<QueryResult, QueryError>>,
+    stop_on_error: bool,
+}
+
+impl<'a> ExecuteMany<'a> {
+    /// Ends the stream after the first error.
+    /// Statements which were not executed yet are not executed at all,
+    /// and results of statements which are being executed are dropped.
+    pub fn stop_on_error(mut self) -> Self {
+        self.stop_on_error = true;
+        self
+    }
+}
+
+impl Stream for ExecuteMany<'_> {
+    type Item = Result<QueryResult, QueryError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let result = futures::ready!(self.results.poll_next_unpin(cx));
+        if self.stop_on_error && matches!(result, Some(Err(_))) {
+            self.results = stream::empty().boxed();
+        }
+        Poll::Ready(result)
     }
 }
 
+// Calls `f` for every item and returns outputs of the futures in the order of items,
+// with at most `concurrency` futures running at the same time.
+// A slow future doesn't stop the next ones from being started - outputs of futures
+// which finished early are kept until all outputs before them are returned.
+// At most `2 * concurrency` futures are running or waiting for their outputs to be returned,
+// so a future which never finishes doesn't make the kept outputs grow without bound.
+fn pipelined<'a, I, F, Fut>(items: I, concurrency: usize, f: F) -> BoxStream<'a, Fut::Output>
+where
+    I: Iterator + Send + 'a,
+    F: FnMut(I::Item) -> Fut + Send + 'a,
+    Fut: Future + Send + 'a,
+    Fut::Output: Send + 'a,
+{
+    struct State<'a, I, F, T> {
+        items: std::iter::Enumerate<I>,
+        f: F,
+        running: FuturesUnordered<BoxFuture<'a, (usize, T)>>,
+        finished: HashMap<usize, T>,
+        next_index: usize,
+    }
+
+    let state = State {
+        items: items.enumerate(),
+        f,
+        running: FuturesUnordered::new(),
+        finished: HashMap::new(),
+        next_index: 0,
+    };
+    let concurrency = concurrency.max(1);
+    let window = 2 * concurrency;
+
+    stream::unfold(state, move |mut state| async move {
+        loop {
+            if let Some(output) = state.finished.remove(&state.next_index) {
+                state.next_index += 1;
+                return Some((output, state));
+            }
+            while state.running.len() < concurrency
+                && state.running.len() + state.finished.len() < window
+            {
+                let (index, item) = match state.items.next() {
+                    Some(item) => item,
+                    None => break,
+                };
+                let future = (state.f)(item);
+                state
+                    .running
+                    .push(async move { (index, future.await) }.boxed());
+            }
+            let (index, output) = state.running.next().await?;
+            state.finished.insert(index, output);
+        }
+    })
+    .boxed()
+}
+
 pub enum RunQueryResult<ResT> {
     IgnoredWriteError,
     Completed(ResT),
@@ -438,12 +628,17 @@ impl Session {
 
         node_addresses.extend(resolved);
 
+        let prepared_statements = Arc::new(PreparedStatementsRegistry::new(
+            config.prepared_statements_registry_capacity,
+        ));
+
         let cluster = Cluster::new(
             &node_addresses,
             config.get_pool_config(),
             config.fetch_schema_metadata,
             &config.address_translator,
             &config.host_filter,
+            prepared_statements.clone(),
         )
         .await?;
 
@@ -459,6 +654,10 @@ impl Session {
             request_timeout: config.request_timeout,
             refresh_metadata_on_auto_schema_agreement: config
                 .refresh_metadata_on_auto_schema_agreement,
+            prepared_statements,
+            schema_agreement_scope: config.schema_agreement_scope,
+            infer_idempotence: config.infer_idempotence,
+            timestamp_generator: config.timestamp_generator,
         };
 
         if let Some(keyspace_name) = config.used_keyspace {
@@ -524,6 +723,52 @@ impl Session {
         self.query_paged(query, values, None).await
     }
 
+    /// Sends the query to every node of the cluster and returns the result from each of them.
+    /// Useful for reading node-local tables, like `system.local` or virtual tables.\
+    /// Nodes rejected by the host filter are skipped. Each request goes to one node only
+    /// (see [`Query::set_target_node`]), so a node which can't be reached gets an error result.
+    ///
+    /// Fails only if the values can't be serialized.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// let results = session
+    ///     .query_on_all_nodes("SELECT release_version FROM system.local", &[])
+    ///     .await?;
+    ///
+    /// for (node, result) in results {
+    ///     let (version,): (String,) = result?.single_row_typed()?;
+    ///     println!("{} runs {}", node.address, version);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_on_all_nodes(
+        &self,
+        query: impl Into<Query>,
+        values: impl ValueList,
+    ) -> Result<Vec<(Arc<Node>, Result<QueryResult, QueryError>)>, QueryError> {
+        let query: Query = query.into();
+        let serialized_values = values.serialized()?;
+        let cluster_data = self.cluster.get_data();
+
+        let queries = cluster_data
+            .get_nodes_info()
+            .iter()
+            .filter(|node| node.is_enabled())
+            .map(|node| {
+                let mut node_query = query.clone();
+                node_query.set_target_node(Some(node.clone()));
+                let values_ref = &serialized_values;
+                async move { (node.clone(), self.query(node_query, values_ref).await) }
+            });
+
+        Ok(join_all(queries).await)
+    }
+
     /// Queries the database with a custom paging state.
     /// # Arguments
     ///
@@ -536,8 +781,10 @@ impl Session {
         values: impl ValueList,
         paging_state: Option<Bytes>,
     ) -> Result<QueryResult, QueryError> {
-        let query: Query = query.into();
-        let serialized_values = values.serialized()?;
+        let mut query: Query = query.into();
+        self.infer_idempotence(&mut query);
+        let serialized_values = none_as_unset(&query.config, values.serialized()?);
+        let timestamp = self.request_timestamp(query.get_timestamp(), || is_write(&query.contents));
 
         let span = trace_span!("Request", query = query.contents.as_str());
         let run_query_result = self
@@ -557,6 +804,7 @@ impl Session {
                                 values_ref,
                                 consistency,
                                 paging_state_ref.clone(),
+                                timestamp,
                             )
                             .await
                             .and_then(QueryResponse::into_non_error_query_response)
@@ -664,18 +912,66 @@ impl Session {
         query: impl Into<Query>,
         values: impl ValueList,
     ) -> Result<RowIterator, QueryError> {
-        let query: Query = query.into();
-        let serialized_values = values.serialized()?;
+        self.query_iter_with_paging_state(query, values, None).await
+    }
+
+    /// Run a simple query with paging, starting from the page after the one the paging state was received with.\
+    /// The paging state can be obtained from [`RowIterator::get_paging_state`]
+    /// of an iterator returned by [`query_iter`](Session::query_iter) for the same query and values.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use scylla::transport::iterator::PagingState;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session, cursor: &str) -> Result<(), Box<dyn Error>> {
+    /// use futures::stream::StreamExt;
+    ///
+    /// let paging_state: PagingState = cursor.parse()?;
+    /// let mut rows_stream = session
+    ///    .query_iter_from("SELECT a, b FROM ks.t", &[], paging_state)
+    ///    .await?
+    ///    .into_typed::<(i32, i32)>();
+    ///
+    /// while let Some(next_row_res) = rows_stream.next().await {
+    ///     let (a, b): (i32, i32) = next_row_res?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_iter_from(
+        &self,
+        query: impl Into<Query>,
+        values: impl ValueList,
+        paging_state: PagingState,
+    ) -> Result<RowIterator, QueryError> {
+        self.query_iter_with_paging_state(query, values, Some(paging_state))
+            .await
+    }
+
+    async fn query_iter_with_paging_state(
+        &self,
+        query: impl Into<Query>,
+        values: impl ValueList,
+        paging_state: Option<PagingState>,
+    ) -> Result<RowIterator, QueryError> {
+        let mut query: Query = query.into();
+        self.infer_idempotence(&mut query);
+        let serialized_values = none_as_unset(&query.config, values.serialized()?);
 
         let retry_session = match &query.config.retry_policy {
             Some(policy) => policy.new_session(),
             None => self.retry_policy.new_session(),
         };
 
+        let timestamp = self.request_timestamp(query.get_timestamp(), || is_write(&query.contents));
+
         let span = trace_span!("Request", query = query.contents.as_str());
         RowIterator::new_for_query(
             query,
             serialized_values.into_owned(),
+            paging_state,
+            timestamp,
             self.default_consistency,
             retry_session,
             self.load_balancer.clone(),
@@ -722,7 +1018,8 @@ impl Session {
     /// # }
     /// ```
     pub async fn prepare(&self, query: impl Into<Query>) -> Result<PreparedStatement, QueryError> {
-        let query = query.into();
+        let mut query = query.into();
+        self.infer_idempotence(&mut query);
 
         let connections = self.cluster.get_working_connections().await?;
 
@@ -765,9 +1062,36 @@ impl Session {
             self.extract_partitioner_name(&prepared, &self.cluster.get_data()),
         );
 
+        self.prepared_statements.register(&prepared);
+
         Ok(prepared)
     }
 
+    /// Returns the timestamp of a request: the one set on the statement or, if the statement
+    /// writes data, the next one from the timestamp generator.\
+    /// Called once per request, so that all its attempts are sent with the same timestamp.
+    fn request_timestamp(
+        &self,
+        statement_timestamp: Option<i64>,
+        is_write: impl FnOnce() -> bool,
+    ) -> Option<i64> {
+        statement_timestamp.or_else(|| {
+            let generator = self.timestamp_generator.as_ref()?;
+            is_write().then(|| generator.next_timestamp())
+        })
+    }
+
+    /// Marks the query idempotent if inference is enabled, the user didn't decide
+    /// on the query's idempotence and its text shows it's idempotent
+    fn infer_idempotence(&self, query: &mut Query) {
+        if self.infer_idempotence
+            && !query.config.is_idempotent_explicit
+            && infer_is_idempotent(&query.contents)
+        {
+            query.config.is_idempotent = true;
+        }
+    }
+
     fn extract_partitioner_name<'a>(
         &self,
         prepared: &PreparedStatement,
@@ -827,6 +1151,58 @@ impl Session {
         self.execute_paged(prepared, values, None).await
     }
 
+    /// Executes a prepared statement once for every element of `values`, with at most
+    /// `concurrency` requests executed at the same time.\
+    /// Every request is routed to replicas owning its partition key, like in [`execute`](Session::execute).
+    ///
+    /// Results are returned in the order of `values`. The next statement is sent as soon as one of
+    /// the pending ones finishes, even if earlier statements are still being executed - their results
+    /// are kept until the results before them are returned. Once `2 * concurrency` statements are pending
+    /// or have their results kept, no new statement is sent until the oldest pending one finishes.
+    /// Requests are sent only while the stream is polled.
+    /// Use [`ExecuteMany::stop_on_error`] to stop after the first failed statement.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use futures::stream::StreamExt;
+    ///
+    /// let prepared = session
+    ///     .prepare("INSERT INTO ks.tab (a, b) VALUES(?, ?)")
+    ///     .await?;
+    ///
+    /// let values = (0..1000).map(|a: i32| (a, a.to_string()));
+    /// let mut results = session.execute_many(&prepared, values, 64).stop_on_error();
+    ///
+    /// while let Some(result) = results.next().await {
+    ///     result?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn execute_many<'a, I>(
+        &'a self,
+        prepared: &'a PreparedStatement,
+        values: I,
+        concurrency: usize,
+    ) -> ExecuteMany<'a>
+    where
+        I: IntoIterator,
+        I::IntoIter: Send + 'a,
+        I::Item: ValueList + Send + 'a,
+    {
+        let results = pipelined(values.into_iter(), concurrency, move |values| {
+            self.execute(prepared, values)
+        });
+
+        ExecuteMany {
+            results,
+            stop_on_error: false,
+        }
+    }
+
     /// Executes a previously prepared statement with previously received paging state
     /// # Arguments
     ///
@@ -839,7 +1215,12 @@ impl Session {
         values: impl ValueList,
         paging_state: Option<Bytes>,
     ) -> Result<QueryResult, QueryError> {
+        check_user_types(prepared, &values)?;
         let serialized_values = values.serialized()?;
+        let serialized_values = none_as_unset(
+            &prepared.config,
+            bind_by_name(prepared, &serialized_values)?,
+        );
         let values_ref = &serialized_values;
         let paging_state_ref = &paging_state;
 
@@ -848,7 +1229,11 @@ impl Session {
         let statement_info = Statement {
             token,
             keyspace: prepared.get_keyspace_name(),
+            is_confirmed_lwt: prepared.is_confirmed_lwt(),
         };
+        let timestamp = self.request_timestamp(prepared.get_timestamp(), || {
+            is_write(prepared.get_statement())
+        });
 
         let span = trace_span!(
             "Request",
@@ -871,6 +1256,7 @@ impl Session {
                             values_ref,
                             consistency,
                             paging_state_ref.clone(),
+                            timestamp,
                         )
                         .await
                         .and_then(QueryResponse::into_non_error_query_response)
@@ -940,11 +1326,72 @@ impl Session {
         &self,
         prepared: impl Into<PreparedStatement>,
         values: impl ValueList,
+    ) -> Result<RowIterator, QueryError> {
+        self.execute_iter_with_paging_state(prepared, values, None, None)
+            .await
+    }
+
+    /// Execute a prepared statement with paging, starting from the page after the one the paging state was received with.\
+    /// The paging state can be obtained from [`RowIterator::get_paging_state`]
+    /// of an iterator returned by [`execute_iter`](Session::execute_iter) for the same statement and values.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use scylla::transport::iterator::PagingState;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use futures::stream::StreamExt;
+    ///
+    /// let mut prepared = session.prepare("SELECT a, b FROM ks.t").await?;
+    /// prepared.set_page_size(100);
+    ///
+    /// // Read the first page and save the paging state as a string
+    /// let mut rows_stream = session.execute_iter(prepared.clone(), &[]).await?;
+    /// for _ in 0..rows_stream.get_remaining_rows_in_page() {
+    ///     let row = rows_stream.next().await.unwrap()?;
+    /// }
+    /// let cursor: Option<String> = rows_stream.get_paging_state().map(|state| state.to_string());
+    ///
+    /// // Later resume from the next page
+    /// if let Some(cursor) = cursor {
+    ///     let paging_state: PagingState = cursor.parse()?;
+    ///     let mut rows_stream = session.execute_iter_from(prepared, &[], paging_state).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_iter_from(
+        &self,
+        prepared: impl Into<PreparedStatement>,
+        values: impl ValueList,
+        paging_state: PagingState,
+    ) -> Result<RowIterator, QueryError> {
+        self.execute_iter_with_paging_state(prepared, values, Some(paging_state), None)
+            .await
+    }
+
+    /// Executes a prepared statement with paging.
+    /// If `token` is given, it is used for routing instead of the token computed from the partition key.
+    pub(crate) async fn execute_iter_with_paging_state(
+        &self,
+        prepared: impl Into<PreparedStatement>,
+        values: impl ValueList,
+        paging_state: Option<PagingState>,
+        token: Option<Token>,
     ) -> Result<RowIterator, QueryError> {
         let prepared = prepared.into();
+        check_user_types(&prepared, &values)?;
         let serialized_values = values.serialized()?;
+        let serialized_values = none_as_unset(
+            &prepared.config,
+            bind_by_name(&prepared, &serialized_values)?,
+        );
 
-        let token = self.calculate_token(&prepared, &serialized_values)?;
+        let token = match token {
+            Some(token) => Some(token),
+            None => self.calculate_token(&prepared, &serialized_values)?,
+        };
 
         let retry_session = match &prepared.config.retry_policy {
             Some(policy) => policy.new_session(),
@@ -955,9 +1402,15 @@ impl Session {
             "Request",
             prepared_id = format!("{:X}", prepared.get_id()).as_str()
         );
+        let timestamp = self.request_timestamp(prepared.get_timestamp(), || {
+            is_write(prepared.get_statement())
+        });
+
         RowIterator::new_for_prepared_statement(PreparedIteratorConfig {
             prepared,
             values: serialized_values.into_owned(),
+            paging_state,
+            timestamp,
             default_consistency: self.default_consistency,
             token,
             retry_session,
@@ -969,11 +1422,196 @@ impl Session {
         .await
     }
 
+    /// Scans the whole table in parallel, with default [`ScanOptions`].\
+    /// See [`scan_table_with_options`](Session::scan_table_with_options) for details.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use futures::stream::StreamExt;
+    ///
+    /// let mut rows_stream = session.scan_table("ks", "t", &["a", "b"]).await?;
+    ///
+    /// while let Some(next_row_res) = rows_stream.next().await {
+    ///     let (a, b): (i32, i32) = next_row_res?.into_typed()?;
+    ///     println!("a, b: {}, {}", a, b);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn scan_table(
+        &self,
+        keyspace: &str,
+        table: &str,
+        columns: &[&str],
+    ) -> Result<TableScan<'_>, QueryError> {
+        self.scan_table_with_options(keyspace, table, columns, ScanOptions::default())
+            .await
+    }
+
+    /// Scans the whole table in parallel.\
+    /// The token ring is split into ranges owned by a single node (or a single shard,
+    /// see [`ScanOptions::split_per_shard`]) and each range is read with
+    /// `SELECT columns FROM keyspace.table WHERE token(pk) > ? AND token(pk) <= ?`,
+    /// sent to a replica owning the range.
+    /// At most [`ScanOptions::concurrency`] ranges are read at the same time.
+    ///
+    /// Rows are returned in no particular order. Empty `columns` selects all columns.\
+    /// The table's partition key is taken from schema metadata fetched by the driver,
+    /// so the table must exist in the metadata (see [`refresh_metadata`](Session::refresh_metadata)).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use futures::stream::StreamExt;
+    /// use scylla::transport::table_scan::{ScanCheckpoint, ScanOptions};
+    ///
+    /// let options = ScanOptions {
+    ///     concurrency: 32,
+    ///     ..Default::default()
+    /// };
+    /// let mut rows_stream = session
+    ///     .scan_table_with_options("ks", "t", &["a"], options)
+    ///     .await?;
+    ///
+    /// while let Some(next_row_res) = rows_stream.next().await {
+    ///     let (a,): (i32,) = next_row_res?.into_typed()?;
+    /// }
+    ///
+    /// // Checkpoint can be passed in ScanOptions::resume_from to continue an interrupted scan
+    /// let checkpoint: ScanCheckpoint = rows_stream.checkpoint().clone();
+    /// assert!(checkpoint.is_complete());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn scan_table_with_options(
+        &self,
+        keyspace: &str,
+        table: &str,
+        columns: &[&str],
+        options: ScanOptions,
+    ) -> Result<TableScan<'_>, QueryError> {
+        let cluster_data = self.cluster.get_data();
+        let partition_key = cluster_data
+            .get_keyspace_info()
+            .get(keyspace)
+            .and_then(|ks| ks.tables.get(table))
+            .map(|table| &table.partition_key)
+            .filter(|partition_key| !partition_key.is_empty())
+            .ok_or_else(|| {
+                QueryError::BadQuery(BadQuery::Other(format!(
+                    "Table {}.{} not found in cluster metadata",
+                    keyspace, table
+                )))
+            })?;
+
+        let selected = if columns.is_empty() {
+            "*".to_string()
+        } else {
+            columns.iter().map(|c| quote_identifier(c)).join(", ")
+        };
+        let token_of_pk = format!(
+            "token({})",
+            partition_key.iter().map(|c| quote_identifier(c)).join(", ")
+        );
+        let mut prepared = self
+            .prepare(format!(
+                "SELECT {} FROM {}.{} WHERE {} > ? AND {} <= ?",
+                selected,
+                quote_identifier(keyspace),
+                quote_identifier(table),
+                token_of_pk,
+                token_of_pk
+            ))
+            .await?;
+        prepared.set_is_idempotent(true);
+        if let Some(page_size) = options.page_size {
+            prepared.set_page_size(page_size);
+        }
+        if let Some(consistency) = options.consistency {
+            prepared.set_consistency(consistency);
+        }
+
+        let ranges = ring_ranges(cluster_data.get_ring_info(), options.split_per_shard);
+        Ok(TableScan::new(self, prepared, ranges, options))
+    }
+
+    /// Prepares a statement tied to the type of its bound values `V` and the type of result rows `R`.\
+    /// The types are checked against metadata of the prepared statement,
+    /// a mismatch results in [`BadQuery::TypeCheckError`](crate::transport::errors::BadQuery::TypeCheckError).
+    ///
+    /// Statements which don't return rows can use `()` as `R`.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use scylla::prepared_statement::TypedPreparedStatement;
+    ///
+    /// let select: TypedPreparedStatement<(i32,), (i32, String)> = session
+    ///     .prepare_typed("SELECT a, b FROM ks.tab WHERE a = ?")
+    ///     .await?;
+    ///
+    /// let rows: Vec<(i32, String)> = session.execute_typed(&select, (12345,)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn prepare_typed<V: ValueList, R: FromRow>(
+        &self,
+        query: impl Into<Query>,
+    ) -> Result<TypedPreparedStatement<V, R>, QueryError> {
+        let prepared = self.prepare(query).await?;
+        TypedPreparedStatement::new(prepared).map_err(|e| BadQuery::TypeCheckError(e).into())
+    }
+
+    /// Executes a typed prepared statement and parses the received rows.\
+    /// Returns only a single page of results, to receive multiple pages use
+    /// [execute_iter_typed](Session::execute_iter_typed)
+    ///
+    /// # Arguments
+    /// * `prepared` - the statement to execute, generated using [`Session::prepare_typed`](Session::prepare_typed)
+    /// * `values` - values bound to the query
+    pub async fn execute_typed<V: ValueList, R: FromRow>(
+        &self,
+        prepared: &TypedPreparedStatement<V, R>,
+        values: V,
+    ) -> Result<Vec<R>, TypedQueryError> {
+        let rows = self
+            .execute(prepared.get_prepared(), values)
+            .await?
+            .rows_typed_or_empty::<R>()
+            .collect::<Result<Vec<R>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Executes a typed prepared statement with paging.\
+    /// Returns an async iterator (stream) over all received rows parsed as `R`
+    ///
+    /// # Arguments
+    /// * `prepared` - the statement to execute, generated using [`Session::prepare_typed`](Session::prepare_typed)
+    /// * `values` - values bound to the query
+    pub async fn execute_iter_typed<V: ValueList, R: FromRow>(
+        &self,
+        prepared: &TypedPreparedStatement<V, R>,
+        values: V,
+    ) -> Result<TypedRowIterator<R>, QueryError> {
+        Ok(self
+            .execute_iter(prepared.get_prepared().clone(), values)
+            .await?
+            .into_typed::<R>())
+    }
+
     /// Perform a batch query\
     /// Batch contains many `simple` or `prepared` queries which are executed at once\
     /// Batch doesn't return any rows
     ///
-    /// Batch values must contain values for each of the queries
+    /// Batch values must contain values for each of the queries.
+    /// They are bound by position, named values are not supported.
     ///
     /// See [the book](https://rust-driver.docs.scylladb.com/stable/queries/batch.html) for more information
     ///
@@ -1014,16 +1652,52 @@ impl Session {
         batch: &Batch,
         values: impl BatchValues,
     ) -> Result<QueryResult, QueryError> {
+        self.batch_with_token(batch, values, None, None).await
+    }
+
+    /// Performs a batch routed to replicas owning `token` of the given keyspace.
+    pub(crate) async fn batch_with_token(
+        &self,
+        batch: &Batch,
+        values: impl BatchValues,
+        token: Option<Token>,
+        keyspace: Option<&str>,
+    ) -> Result<QueryResult, QueryError> {
+        // Nulls are sent as unset in statements which have `none_as_unset` enabled
+        let none_as_unset = batch
+            .statements
+            .iter()
+            .map(|statement| match statement {
+                BatchStatement::Query(query) => query.config.none_as_unset,
+                BatchStatement::PreparedStatement(prepared) => prepared.config.none_as_unset,
+            })
+            .collect();
+        let values = NoneAsUnsetBatchValues::new(values, none_as_unset);
         let values_ref = &values;
+        let timestamp = self.request_timestamp(batch.get_timestamp(), || true);
+
+        let is_confirmed_lwt = batch.statements.iter().any(|statement| {
+            matches!(statement, BatchStatement::PreparedStatement(prepared) if prepared.is_confirmed_lwt())
+        });
+        let statement_info = Statement {
+            token,
+            keyspace,
+            is_confirmed_lwt,
+        };
 
         let run_query_result = self
             .run_query(
-                Statement::default(),
+                statement_info,
                 &batch.config,
-                |node: Arc<Node>| async move { node.random_connection().await },
+                |node: Arc<Node>| async move {
+                    match token {
+                        Some(token) => node.connection_for_token(token).await,
+                        None => node.random_connection().await,
+                    }
+                },
                 |connection: Arc<Connection>, consistency: Consistency| async move {
                     connection
-                        .batch_with_consistency(batch, values_ref, consistency)
+                        .batch_with_consistency(batch, values_ref, consistency, timestamp)
                         .await
                 },
             )
@@ -1306,7 +1980,12 @@ impl Session {
 
         let runner = async {
             let cluster_data = self.cluster.get_data();
-            let query_plan = self.load_balancer.plan(&statement_info, &cluster_data);
+            let query_plan: Plan = match &statement_config.target_node {
+                Some(node) => Box::new(std::iter::once(node.clone())),
+                None => {
+                    defer_repreparing_nodes(self.load_balancer.plan(&statement_info, &cluster_data))
+                }
+            };
 
             // If a speculative execution policy is used to run query, query_plan has to be shared
             // between different async functions. This struct helps to wrap query_plan in mutex so it
@@ -1370,6 +2049,7 @@ impl Session {
                             ExecuteQueryContext {
                                 is_idempotent: statement_config.is_idempotent,
                                 consistency: statement_config.consistency,
+                                target_shard: statement_config.determine_target_shard(),
                                 retry_session: retry_policy.new_session(),
                                 history_data,
                             },
@@ -1403,6 +2083,7 @@ impl Session {
                         ExecuteQueryContext {
                             is_idempotent: statement_config.is_idempotent,
                             consistency: statement_config.consistency,
+                            target_shard: statement_config.determine_target_shard(),
                             retry_session: retry_policy.new_session(),
                             history_data,
                         },
@@ -1415,7 +2096,10 @@ impl Session {
             }
         };
 
-        let effective_timeout = statement_config.request_timeout.or(self.request_timeout);
+        let effective_timeout = statement_config
+            .request_timeout
+            .or(self.request_timeout)
+            .map(|timeout| client_timeout_for(timeout, statement_config.server_timeout));
         let result = match effective_timeout {
             Some(timeout) => tokio::time::timeout(timeout, runner)
                 .await
@@ -1459,10 +2143,19 @@ impl Session {
             let span = trace_span!("Executing query", node = node.address.to_string().as_str());
             'same_node_retries: loop {
                 trace!(parent: &span, "Execution started");
-                let connection: Arc<Connection> = match choose_connection(node.clone())
-                    .instrument(span.clone())
-                    .await
-                {
+                let connection_result = match context.target_shard {
+                    Some(shard) => {
+                        node.connection_for_shard(shard)
+                            .instrument(span.clone())
+                            .await
+                    }
+                    None => {
+                        choose_connection(node.clone())
+                            .instrument(span.clone())
+                            .await
+                    }
+                };
+                let connection: Arc<Connection> = match connection_result {
                     Ok(connection) => connection,
                     Err(e) => {
                         trace!(
@@ -1597,15 +2290,111 @@ impl Session {
         }
     }
 
+    /// Checks whether the nodes in the session's [`SchemaAgreementScope`]
+    /// agree on the schema version.
     pub async fn check_schema_agreement(&self) -> Result<bool, QueryError> {
-        let connections = self.cluster.get_working_connections().await?;
+        self.check_schema_agreement_in_scope(&self.schema_agreement_scope)
+            .await
+    }
 
-        let handles = connections.iter().map(|c| c.fetch_schema_version());
-        let versions = try_join_all(handles).await?;
+    /// Nodes which are down, unreachable or don't respond within the session's request timeout are ignored.
+    /// Nodes which are down, unreachable or don't respond in time are ignored.
+    /// Returns [`BadQuery`] if no known node belongs to the scope.
+    pub async fn check_schema_agreement_in_scope(
+        &self,
+        scope: &SchemaAgreementScope,
+    ) -> Result<bool, QueryError> {
+        let versions = self.schema_versions_in_scope(scope).await?;
+        Ok(versions.values().all_equal())
+    }
 
-        let local_version: Uuid = versions[0];
-        let in_agreement = versions.into_iter().all(|v| v == local_version);
-        Ok(in_agreement)
+    /// Returns the schema version reported by every node in the cluster
+    /// which is up and reachable.
+    ///
+    /// It allows to find out which nodes disagree when the schema agreement
+    /// cannot be reached.
+    pub async fn schema_versions(&self) -> Result<HashMap<SocketAddr, Uuid>, QueryError> {
+        self.schema_versions_in_scope(&SchemaAgreementScope::AllNodes)
+            .await
+    }
+
+    async fn schema_versions_in_scope(
+        &self,
+        scope: &SchemaAgreementScope,
+    ) -> Result<HashMap<SocketAddr, Uuid>, QueryError> {
+        let cluster_data = self.cluster.get_data();
+
+        let nodes_in_scope: Vec<&Arc<Node>> = cluster_data
+            .get_nodes_info()
+            .iter()
+            .filter(|node| scope.contains(node) && node.is_enabled())
+            .collect();
+        if nodes_in_scope.is_empty() {
+            return Err(QueryError::BadQuery(BadQuery::Other(format!(
+                "No known node belongs to the schema agreement scope {:?}",
+                scope
+            ))));
+        }
+
+        // A node which doesn't respond mustn't block the whole check
+        let timeout = self
+            .request_timeout
+            .unwrap_or(DEFAULT_SCHEMA_VERSION_FETCH_TIMEOUT);
+        let version_futures = nodes_in_scope
+            .into_iter()
+            .filter(|node| !node.is_down())
+            .map(|node| async move {
+                let fetch_version = async {
+                    let connection = node.random_connection().await?;
+                    connection.fetch_schema_version().await
+                };
+                let version = tokio::time::timeout(timeout, fetch_version)
+                    .await
+                    .unwrap_or_else(|e| {
+                        Err(QueryError::RequestTimeout(format!(
+                            "Fetching schema version took longer than {}ms: {}",
+                            timeout.as_millis(),
+                            e
+                        )))
+                    });
+                (node.address, version)
+            });
+
+        let mut versions = HashMap::new();
+        let mut last_error: Option<QueryError> = None;
+
+        for (address, version) in join_all(version_futures).await {
+            match version {
+                Ok(version) => {
+                    versions.insert(address, version);
+                }
+                // The node is unreachable or doesn't respond - ignore it
+                Err(
+                    e @ (QueryError::IoError(_)
+                    | QueryError::TimeoutError
+                    | QueryError::RequestTimeout(_)),
+                ) => {
+                    debug!(
+                        node = address.to_string().as_str(),
+                        error = e.to_string().as_str(),
+                        "Could not fetch schema version, ignoring the node"
+                    );
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if versions.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                QueryError::IoError(Arc::new(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "All nodes in the schema agreement scope are down",
+                )))
+            }));
+        }
+
+        Ok(versions)
     }
 
     pub async fn fetch_schema_version(&self) -> Result<Uuid, QueryError> {
@@ -1618,7 +2407,7 @@ impl Session {
         .await
     }
 
-    fn calculate_token(
+    pub(crate) fn calculate_token(
         &self,
         prepared: &PreparedStatement,
         serialized_values: &SerializedValues,
@@ -1635,6 +2424,66 @@ impl Session {
     }
 }
 
+// Named values are bound to markers of the prepared statement by their names
+pub(crate) fn bind_by_name<'a>(
+    prepared: &PreparedStatement,
+    values: &'a SerializedValues,
+) -> Result<Cow<'a, SerializedValues>, QueryError> {
+    values
+        .bind_by_name(&prepared.get_prepared_metadata().col_specs)
+        .map_err(|e| BadQuery::TypeCheckError(e).into())
+}
+
+// Values of user defined types are written in the order of their fields, which isn't checked
+// by the database, so a mismatch with the type definition would silently corrupt data.
+// Other mismatches are left to the database, which reports them on its own.
+pub(crate) fn check_user_types<V: ValueList>(
+    prepared: &PreparedStatement,
+    _values: &V,
+) -> Result<(), QueryError> {
+    let col_specs = &prepared.get_prepared_metadata().col_specs;
+    if !col_specs
+        .iter()
+        .any(|col_spec| contains_user_type(&col_spec.typ))
+    {
+        return Ok(());
+    }
+
+    match V::type_check(col_specs) {
+        Err(e) if is_user_type_mismatch(&e) => Err(BadQuery::TypeCheckError(e).into()),
+        _ => Ok(()),
+    }
+}
+
+fn is_user_type_mismatch(error: &TypeCheckError) -> bool {
+    matches!(error, TypeCheckError::IncompatibleType { cql_type, .. } if contains_user_type(cql_type))
+}
+
+fn contains_user_type(typ: &ColumnType) -> bool {
+    match typ {
+        ColumnType::UserDefinedType { .. } => true,
+        ColumnType::List(elem_type) | ColumnType::Set(elem_type) => contains_user_type(elem_type),
+        ColumnType::Map(key_type, value_type) => {
+            contains_user_type(key_type) || contains_user_type(value_type)
+        }
+        ColumnType::Tuple(types) => types.iter().any(contains_user_type),
+        _ => false,
+    }
+}
+
+// Null values are sent as unset if the statement has `none_as_unset` enabled
+pub(crate) fn none_as_unset<'a>(
+    config: &StatementConfig,
+    values: Cow<'a, SerializedValues>,
+) -> Cow<'a, SerializedValues> {
+    if !config.none_as_unset {
+        return values;
+    }
+    let mut values = values.into_owned();
+    values.replace_nulls_with_unset();
+    Cow::Owned(values)
+}
+
 fn calculate_partition_key(
     stmt: &PreparedStatement,
     values: &SerializedValues,
@@ -1691,6 +2540,7 @@ impl AllowedRunQueryResTType for NonErrorQueryResponse {}
 struct ExecuteQueryContext<'a> {
     is_idempotent: bool,
     consistency: Option<Consistency>,
+    target_shard: Option<Shard>,
     retry_session: Box<dyn RetrySession>,
     history_data: Option<HistoryData<'a>>,
 }
@@ -1744,3 +2594,217 @@ impl<'a> ExecuteQueryContext<'a> {
             .log_attempt_error(*attempt_id, error, retry_decision);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{check_user_types, pipelined, SchemaAgreementScope};
+    use crate::statement::StatementConfig;
+    use crate::transport::node::Node;
+    use crate::transport::topology::Peer;
+    use futures::StreamExt;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    fn node(datacenter: Option<&str>, port: u16) -> Node {
+        let address = SocketAddr::from(([127, 0, 0, 1], port));
+        let peer = Peer {
+            address,
+            untranslated_address: Some(address),
+            tokens: Vec::new(),
+            datacenter: datacenter.map(String::from),
+            rack: None,
+            host_id: Uuid::from_u128(port as u128),
+            release_version: None,
+            schema_version: None,
+        };
+        Node::new(&peer, Default::default(), None, false)
+    }
+
+    #[test]
+    fn target_shard_requires_target_node() {
+        let mut config = StatementConfig {
+            target_shard: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(config.determine_target_shard(), None);
+
+        config.target_node = Some(Arc::new(node(None, 1)));
+        assert_eq!(config.determine_target_shard(), Some(3));
+
+        config.target_shard = None;
+        assert_eq!(config.determine_target_shard(), None);
+    }
+
+    #[test]
+    fn user_type_field_order_is_checked() {
+        use crate::frame::response::result::{ColumnSpec, ColumnType, PreparedMetadata, TableSpec};
+        use crate::macros::IntoUserType;
+        use crate::prepared_statement::PreparedStatement;
+        use crate::transport::errors::{BadQuery, QueryError};
+        use bytes::Bytes;
+
+        #[derive(IntoUserType)]
+        #[scylla(crate = "crate")]
+        struct Reordered {
+            b: i32,
+            a: i32,
+        }
+
+        let spec = |name: &str, typ: ColumnType| ColumnSpec {
+            table_spec: TableSpec {
+                ks_name: "ks".to_string(),
+                table_name: "tab".to_string(),
+            },
+            name: name.to_string(),
+            typ,
+        };
+        let udt = ColumnType::UserDefinedType {
+            keyspace: "ks".to_string(),
+            type_name: "udt".to_string(),
+            field_types: vec![
+                ("a".to_string(), ColumnType::Int),
+                ("b".to_string(), ColumnType::Int),
+            ],
+        };
+        let prepared = |col_specs: Vec<ColumnSpec>| {
+            PreparedStatement::new(
+                Bytes::from_static(b"id"),
+                PreparedMetadata::new(col_specs.len(), vec![], col_specs),
+                Default::default(),
+                "INSERT INTO ks.tab (pk, u) VALUES (?, ?)".to_string(),
+                None,
+                Default::default(),
+            )
+        };
+        let reordered = (1_i32, Reordered { b: 2, a: 3 });
+
+        let with_udt = prepared(vec![spec("pk", ColumnType::Int), spec("u", udt.clone())]);
+        assert!(matches!(
+            check_user_types(&with_udt, &reordered),
+            Err(QueryError::BadQuery(BadQuery::TypeCheckError(_)))
+        ));
+
+        let in_list = prepared(vec![
+            spec("pk", ColumnType::Int),
+            spec("u", ColumnType::List(Box::new(udt))),
+        ]);
+        assert!(check_user_types(&in_list, &(1_i32, vec![Reordered { b: 2, a: 3 }])).is_err());
+
+        // Other mismatches are reported by the database
+        let without_udt = prepared(vec![
+            spec("pk", ColumnType::BigInt),
+            spec("v", ColumnType::Int),
+        ]);
+        assert!(check_user_types(&without_udt, &(1_i32, 2_i32)).is_ok());
+    }
+
+    #[test]
+    fn schema_agreement_scope_contains() {
+        let dc1_node = node(Some("dc1"), 1);
+        let dc2_node = node(Some("dc2"), 2);
+        let no_dc_node = node(None, 3);
+
+        let all = SchemaAgreementScope::AllNodes;
+        assert!(all.contains(&dc1_node));
+        assert!(all.contains(&dc2_node));
+        assert!(all.contains(&no_dc_node));
+
+        let dc1 = SchemaAgreementScope::Datacenter("dc1".to_string());
+        assert!(dc1.contains(&dc1_node));
+        assert!(!dc1.contains(&dc2_node));
+        assert!(!dc1.contains(&no_dc_node));
+
+        let nodes = SchemaAgreementScope::Nodes(vec![dc2_node.address, no_dc_node.address]);
+        assert!(!nodes.contains(&dc1_node));
+        assert!(nodes.contains(&dc2_node));
+        assert!(nodes.contains(&no_dc_node));
+
+        let no_nodes = SchemaAgreementScope::Nodes(Vec::new());
+        assert!(!no_nodes.contains(&dc1_node));
+    }
+
+    #[tokio::test]
+    async fn pipelined_starts_next_futures_while_the_first_one_runs() {
+        let started = AtomicUsize::new(0);
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let (sender, receiver) = tokio::sync::oneshot::channel::<()>();
+        let receiver = Mutex::new(Some(receiver));
+
+        let mut outputs = pipelined(0..6, 2, |i: usize| {
+            let first_receiver = if i == 0 {
+                receiver.lock().unwrap().take()
+            } else {
+                None
+            };
+            let (started, running, max_running) = (&started, &running, &max_running);
+            async move {
+                started.fetch_add(1, Ordering::SeqCst);
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+                match first_receiver {
+                    Some(receiver) => receiver.await.unwrap(),
+                    None => tokio::task::yield_now().await,
+                }
+                running.fetch_sub(1, Ordering::SeqCst);
+                i
+            }
+        });
+
+        // The first future finishes only after the next ones have filled the window
+        let release_first = async {
+            while started.load(Ordering::SeqCst) < 4 {
+                tokio::task::yield_now().await;
+            }
+            sender.send(()).unwrap();
+        };
+        let (first, ()) = tokio::time::timeout(
+            Duration::from_secs(5),
+            futures::future::join(outputs.next(), release_first),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(first, Some(0));
+        assert_eq!(outputs.collect::<Vec<_>>().await, vec![1, 2, 3, 4, 5]);
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn pipelined_limits_outputs_kept_for_a_slow_future() {
+        let started = AtomicUsize::new(0);
+        let (sender, receiver) = tokio::sync::oneshot::channel::<()>();
+        let receiver = Mutex::new(Some(receiver));
+
+        let mut outputs = pipelined(0..10, 2, |i: usize| {
+            let first_receiver = if i == 0 {
+                receiver.lock().unwrap().take()
+            } else {
+                None
+            };
+            let started = &started;
+            async move {
+                started.fetch_add(1, Ordering::SeqCst);
+                if let Some(receiver) = first_receiver {
+                    receiver.await.unwrap();
+                }
+                i
+            }
+        });
+
+        // While the first future runs, only 3 outputs after it are kept
+        let first = tokio::time::timeout(Duration::from_millis(100), outputs.next()).await;
+        assert!(first.is_err());
+        assert_eq!(started.load(Ordering::SeqCst), 4);
+
+        sender.send(()).unwrap();
+        assert_eq!(
+            outputs.collect::<Vec<_>>().await,
+            (0..10).collect::<Vec<_>>()
+        );
+        assert_eq!(started.load(Ordering::SeqCst), 10);
+    }
+}
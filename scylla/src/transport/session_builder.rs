@@ -2,10 +2,11 @@
 
 use super::errors::NewSessionError;
 use super::load_balancing::LoadBalancingPolicy;
-use super::session::{AddressTranslator, Session, SessionConfig};
+use super::session::{AddressTranslator, SchemaAgreementScope, Session, SessionConfig};
 use super::speculative_execution::SpeculativeExecutionPolicy;
 use super::Compression;
 use crate::transport::host_filter::HostFilter;
+use crate::transport::timestamp_generator::TimestampGenerator;
 use crate::transport::{connection_pool::PoolSize, retry_policy::RetryPolicy};
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -674,6 +675,103 @@ impl SessionBuilder {
         self.config.refresh_metadata_on_auto_schema_agreement = refresh_metadata;
         self
     }
+
+    /// Sets how many prepared statements the session remembers in order to prepare them
+    /// on nodes that join the cluster or come back up, before they receive any requests.
+    /// Setting it to zero disables re-preparing statements on such nodes.
+    /// The default is 1024.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .prepared_statements_registry_capacity(4096)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn prepared_statements_registry_capacity(mut self, capacity: usize) -> Self {
+        self.config.prepared_statements_registry_capacity = capacity;
+        self
+    }
+
+    /// Sets which nodes have to agree on the schema version for the schema agreement
+    /// to be reached. Nodes that are down or unreachable are always ignored.
+    /// The default is [`SchemaAgreementScope::AllNodes`].
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::session::SchemaAgreementScope;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .schema_agreement_scope(SchemaAgreementScope::Datacenter("dc1".to_string()))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn schema_agreement_scope(mut self, scope: SchemaAgreementScope) -> Self {
+        self.config.schema_agreement_scope = scope;
+        self
+    }
+
+    /// Sets the generator of timestamps for writes which don't have a timestamp set explicitly.
+    /// Without a generator, writes are timestamped by the coordinator, so consecutive writes
+    /// of one client can be reordered when they are coordinated by nodes with different clocks.
+    /// By default no generator is used.
+    ///
+    /// See the [timestamp generator](crate::transport::timestamp_generator) module for
+    /// the built-in generator.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::timestamp_generator::MonotonicTimestampGenerator;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .timestamp_generator(Arc::new(MonotonicTimestampGenerator::new()))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn timestamp_generator(mut self, generator: Arc<dyn TimestampGenerator>) -> Self {
+        self.config.timestamp_generator = Some(generator);
+        self
+    }
+
+    /// Enables inferring idempotence of queries and prepared statements from their CQL text.
+    /// Statements without counter updates, lightweight transaction conditions, list appends and prepends
+    /// or non-deterministic functions are marked idempotent, so that they can be retried
+    /// and executed speculatively. Idempotence set explicitly on a statement is never overwritten.
+    /// The default is false.
+    ///
+    /// See [`infer_is_idempotent`](crate::statement::idempotence::infer_is_idempotent)
+    /// for details of the analysis.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .infer_idempotence(true)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn infer_idempotence(mut self, infer: bool) -> Self {
+        self.config.infer_idempotence = infer;
+        self
+    }
 }
 
 /// Creates a [`SessionBuilder`] with default configuration, same as [`SessionBuilder::new`]
@@ -687,7 +785,8 @@ impl Default for SessionBuilder {
 mod tests {
     use super::SessionBuilder;
     use crate::transport::load_balancing::RoundRobinPolicy;
-    use crate::transport::session::KnownNode;
+    use crate::transport::session::{KnownNode, SchemaAgreementScope};
+    use crate::transport::timestamp_generator::MonotonicTimestampGenerator;
     use crate::transport::Compression;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
     use std::sync::Arc;
@@ -855,6 +954,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn prepared_statements_registry_capacity() {
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.prepared_statements_registry_capacity, 1024);
+
+        builder = builder.prepared_statements_registry_capacity(0);
+        assert_eq!(builder.config.prepared_statements_registry_capacity, 0);
+    }
+
+    #[test]
+    fn schema_agreement_scope() {
+        let mut builder = SessionBuilder::new();
+        assert_eq!(
+            builder.config.schema_agreement_scope,
+            SchemaAgreementScope::AllNodes
+        );
+
+        builder = builder.schema_agreement_scope(SchemaAgreementScope::Datacenter("dc1".into()));
+        assert_eq!(
+            builder.config.schema_agreement_scope,
+            SchemaAgreementScope::Datacenter("dc1".into())
+        );
+    }
+
+    #[test]
+    fn timestamp_generator() {
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.timestamp_generator.is_none());
+
+        builder = builder.timestamp_generator(Arc::new(MonotonicTimestampGenerator::new()));
+        assert!(builder.config.timestamp_generator.is_some());
+    }
+
+    #[test]
+    fn infer_idempotence() {
+        let mut builder = SessionBuilder::new();
+        assert!(!builder.config.infer_idempotence);
+
+        builder = builder.infer_idempotence(true);
+        assert!(builder.config.infer_idempotence);
+    }
+
     #[test]
     fn all_features() {
         let mut builder = SessionBuilder::new();
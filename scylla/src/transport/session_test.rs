@@ -9,6 +9,7 @@ use crate::statement::Consistency;
 use crate::tracing::TracingInfo;
 use crate::transport::errors::{BadKeyspaceName, BadQuery, DbError, QueryError};
 use crate::transport::partitioner::{Murmur3Partitioner, Partitioner, PartitionerName};
+use crate::transport::session::SchemaAgreementScope;
 use crate::transport::topology::Strategy::SimpleStrategy;
 use crate::transport::topology::{CollectionType, ColumnKind, CqlType, NativeType};
 use crate::utils::test_utils::{supports_feature, unique_keyspace_name};
@@ -352,6 +353,29 @@ async fn test_prepared_statement() {
         let output = rs.next().unwrap().unwrap();
         assert_eq!(input, output)
     }
+    // Check that FromRow can match columns by name
+    {
+        #[derive(scylla::FromRow, PartialEq, Debug)]
+        #[scylla(match_by_name)]
+        struct PartialComplexPk {
+            e: i32,
+            #[scylla(rename = "a")]
+            first: i32,
+        }
+        let output = session
+            .query(
+                format!(
+                    "SELECT * FROM {}.complex_pk WHERE a = 9 and b = 8 and c = 'seven'",
+                    ks
+                ),
+                &[],
+            )
+            .await
+            .unwrap()
+            .first_row_typed::<PartialComplexPk>()
+            .unwrap();
+        assert_eq!(output, PartialComplexPk { e: 5, first: 9 })
+    }
 }
 
 #[tokio::test]
@@ -1068,6 +1092,42 @@ async fn test_await_schema_agreement() {
     session.await_schema_agreement().await.unwrap();
 }
 
+#[tokio::test]
+async fn test_schema_versions() {
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let versions = session.schema_versions().await.unwrap();
+    assert!(!versions.is_empty());
+    for node in session.get_cluster_data().get_nodes_info() {
+        assert!(versions.contains_key(&node.address));
+    }
+
+    let some_node = session.get_cluster_data().get_nodes_info()[0].clone();
+    let scope = SchemaAgreementScope::Nodes(vec![some_node.address]);
+    assert!(session
+        .check_schema_agreement_in_scope(&scope)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_node_metadata() {
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let cluster_data = session.get_cluster_data();
+    let nodes = cluster_data.get_nodes_info();
+    for node in nodes {
+        assert!(node.release_version.is_some());
+        assert!(node.schema_version().is_some());
+        assert!(node.capabilities().is_some());
+    }
+
+    let host_ids: std::collections::HashSet<_> = nodes.iter().map(|node| node.host_id).collect();
+    assert_eq!(host_ids.len(), nodes.len());
+}
+
 #[tokio::test]
 async fn test_await_timed_schema_agreement() {
     use std::time::Duration;
@@ -2263,6 +2323,156 @@ async fn test_refresh_metadata_after_schema_agreement() {
     );
 }
 
+#[tokio::test]
+async fn test_udt_by_name() {
+    use crate::frame::value::UdtByName;
+    use crate::macros::{FromUserType, IntoUserType};
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(ks.clone(), false).await.unwrap();
+
+    session
+        .query("CREATE TYPE udt (a int, b text, c int)", &[])
+        .await
+        .unwrap();
+    session
+        .query("CREATE TABLE tab (pk int PRIMARY KEY, v udt)", &[])
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    // Fields are declared in a different order than in the type and `c` is missing
+    #[derive(IntoUserType)]
+    struct Reordered {
+        b: String,
+        a: i32,
+    }
+
+    #[derive(FromUserType, Debug, Clone, PartialEq)]
+    struct Udt {
+        a: i32,
+        b: String,
+        c: Option<i32>,
+    }
+
+    let value = Reordered {
+        b: "text".to_string(),
+        a: 7,
+    };
+    let expected = Udt {
+        a: 7,
+        b: "text".to_string(),
+        c: None,
+    };
+
+    // Definition from metadata of the prepared statement
+    let prepared = session
+        .prepare("INSERT INTO tab (pk, v) VALUES (?, ?)")
+        .await
+        .unwrap();
+    let v_type = &prepared.get_prepared_metadata().col_specs[1].typ;
+    let by_name = UdtByName::with_column_type(&value, v_type).unwrap();
+    session.execute(&prepared, (1, by_name)).await.unwrap();
+
+    // Definition from cluster metadata
+    session.refresh_metadata().await.unwrap();
+    let cluster_data = session.get_cluster_data();
+    let definition = &cluster_data.get_keyspace_info()[ks.as_str()].user_defined_types["udt"];
+    let by_name = UdtByName::new(
+        &value,
+        "udt",
+        definition.iter().map(|(name, _)| name.as_str()),
+    )
+    .unwrap();
+    session
+        .query("INSERT INTO tab (pk, v) VALUES (2, ?)", (by_name,))
+        .await
+        .unwrap();
+
+    let rows = session
+        .query("SELECT v FROM tab WHERE pk IN (1, 2)", &[])
+        .await
+        .unwrap()
+        .rows_typed::<(Udt,)>()
+        .unwrap()
+        .map(|row| row.unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![expected.clone(), expected]);
+}
+
+#[tokio::test]
+async fn test_prepare_typed() {
+    use crate::frame::value::TypeCheckError;
+    use crate::prepared_statement::TypedPreparedStatement;
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(ks.clone(), false).await.unwrap();
+    session
+        .query("CREATE TABLE tab (a int PRIMARY KEY, b text)", &[])
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    let insert: TypedPreparedStatement<(i32, String), ()> = session
+        .prepare_typed("INSERT INTO tab (a, b) VALUES (?, ?)")
+        .await
+        .unwrap();
+    assert_eq!(
+        session
+            .execute_typed(&insert, (1, "one".to_string()))
+            .await
+            .unwrap(),
+        Vec::<()>::new()
+    );
+
+    let select: TypedPreparedStatement<(i32,), (i32, String)> = session
+        .prepare_typed("SELECT a, b FROM tab WHERE a = ?")
+        .await
+        .unwrap();
+    assert_eq!(
+        session.execute_typed(&select, (1,)).await.unwrap(),
+        vec![(1, "one".to_string())]
+    );
+    let streamed = session
+        .execute_iter_typed(&select, (1,))
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(streamed.len(), 1);
+
+    let wrong_values = session
+        .prepare_typed::<(String,), (i32, String)>("SELECT a, b FROM tab WHERE a = ?")
+        .await;
+    assert_matches!(
+        wrong_values,
+        Err(QueryError::BadQuery(BadQuery::TypeCheckError(
+            TypeCheckError::IncompatibleType { .. }
+        )))
+    );
+
+    let wrong_row = session
+        .prepare_typed::<(i32,), (i32,)>("SELECT a, b FROM tab WHERE a = ?")
+        .await;
+    assert_matches!(
+        wrong_row,
+        Err(QueryError::BadQuery(BadQuery::TypeCheckError(
+            TypeCheckError::WrongRowSize {
+                expected: 1,
+                actual: 2
+            }
+        )))
+    );
+}
+
 #[tokio::test]
 async fn test_rate_limit_exceeded_exception() {
     let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
@@ -2424,3 +2634,868 @@ async fn test_batch_lwts_for_cassandra(session: &Session, batch: &Batch, batch_r
 
     assert_eq!(prepared_batch_res_rows, expected_prepared_batch_res_rows);
 }
+
+#[tokio::test]
+async fn test_query_builder() {
+    use crate::query_builder::{Column, Insert, QueryBuilderError, Select, Update};
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE {}.tab (pk int, ck int, tags set<text>, PRIMARY KEY (pk, ck))",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+    let cluster_data = session.get_cluster_data();
+
+    for ck in 0..3 {
+        let insert = Insert::new("tab")
+            .keyspace(&ks)
+            .value("pk", 1)
+            .value("ck", ck)
+            .value("tags", vec!["a"]);
+        insert.validate(&cluster_data).unwrap();
+        let built = insert.build().unwrap();
+        session.query(built.query, built.values).await.unwrap();
+    }
+
+    let built = Update::new("tab")
+        .keyspace(&ks)
+        .add("tags", vec!["b"])
+        .where_(Column::new("pk").eq(1))
+        .where_(Column::new("ck").in_(vec![1, 2]))
+        .build()
+        .unwrap();
+    session.query(built.query, built.values).await.unwrap();
+
+    let select = Select::new("tab")
+        .keyspace(&ks)
+        .columns(["ck", "tags"])
+        .where_(Column::new("pk").eq(1))
+        .where_(Column::new("ck").gt(0));
+    select.validate(&cluster_data).unwrap();
+    let built = select.build().unwrap();
+    let rows: Vec<(i32, BTreeSet<String>)> = session
+        .query(built.query, built.values)
+        .await
+        .unwrap()
+        .rows_typed()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let tags: BTreeSet<String> = ["a", "b"].into_iter().map(String::from).collect();
+    assert_eq!(rows, vec![(1, tags.clone()), (2, tags)]);
+
+    assert_eq!(
+        Select::new("tab")
+            .keyspace(&ks)
+            .columns(["missing"])
+            .validate(&cluster_data),
+        Err(QueryBuilderError::UnknownColumn {
+            keyspace: ks.clone(),
+            table: "tab".to_string(),
+            column: "missing".to_string()
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_prepared_named_values() {
+    use crate::frame::response::result::CqlValue;
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(ks, false).await.unwrap();
+    session
+        .query(
+            "CREATE TABLE tab (a int, b int, c text, PRIMARY KEY (a, b))",
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    #[derive(scylla::ValueList)]
+    #[scylla(named)]
+    struct Row {
+        c: String,
+        #[scylla(rename = "pk")]
+        a: i32,
+        b: i32,
+    }
+
+    let insert = session
+        .prepare("INSERT INTO tab (a, b, c) VALUES (:pk, :b, :c)")
+        .await
+        .unwrap();
+    let row = Row {
+        c: "first".to_string(),
+        a: 1,
+        b: 2,
+    };
+    session.execute(&insert, &row).await.unwrap();
+
+    // Markers without explicit names are named after their columns
+    let update = session
+        .prepare("UPDATE tab SET c = ? WHERE a = ? AND b = ?")
+        .await
+        .unwrap();
+    let mut values: HashMap<&str, CqlValue> = HashMap::new();
+    values.insert("b", CqlValue::Int(2));
+    values.insert("c", CqlValue::Text("second".to_string()));
+    values.insert("a", CqlValue::Int(1));
+    session.execute(&update, &values).await.unwrap();
+
+    let rows: Vec<(i32, i32, String)> = session
+        .query("SELECT a, b, c FROM tab", &[])
+        .await
+        .unwrap()
+        .rows_typed()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(rows, vec![(1, 2, "second".to_string())]);
+
+    values.remove("b");
+    assert_matches!(
+        session.execute(&update, &values).await,
+        Err(QueryError::BadQuery(BadQuery::TypeCheckError(_)))
+    );
+}
+
+#[tokio::test]
+async fn test_none_as_unset() {
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(ks, false).await.unwrap();
+    session
+        .query("CREATE TABLE tab (a int PRIMARY KEY, b int, c int)", &[])
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    session
+        .query("INSERT INTO tab (a, b, c) VALUES (1, 2, 3)", &[])
+        .await
+        .unwrap();
+
+    let mut update = session
+        .prepare("UPDATE tab SET b = ?, c = ? WHERE a = ?")
+        .await
+        .unwrap();
+    update.set_none_as_unset(true);
+    session
+        .execute(&update, (None::<i32>, Some(4), 1))
+        .await
+        .unwrap();
+
+    let mut query = Query::new("UPDATE tab SET b = ?, c = ? WHERE a = ?");
+    query.set_none_as_unset(true);
+    session
+        .query(query, (Some(5), None::<i32>, 1))
+        .await
+        .unwrap();
+
+    let row: (i32, i32) = session
+        .query("SELECT b, c FROM tab WHERE a = 1", &[])
+        .await
+        .unwrap()
+        .single_row_typed()
+        .unwrap();
+    assert_eq!(row, (5, 4));
+
+    // Without the option `None` overwrites the value with null
+    update.set_none_as_unset(false);
+    session
+        .execute(&update, (None::<i32>, Some(4), 1))
+        .await
+        .unwrap();
+    let row: (Option<i32>, i32) = session
+        .query("SELECT b, c FROM tab WHERE a = 1", &[])
+        .await
+        .unwrap()
+        .single_row_typed()
+        .unwrap();
+    assert_eq!(row, (None, 4));
+}
+
+#[tokio::test]
+async fn test_iter_from_paging_state() {
+    use crate::transport::iterator::{PagingState, TypedRowIterator};
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(ks, false).await.unwrap();
+    session
+        .query("CREATE TABLE tab (a int, b int, PRIMARY KEY (a, b))", &[])
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    for b in 0..10 {
+        session
+            .query("INSERT INTO tab (a, b) VALUES (0, ?)", (b,))
+            .await
+            .unwrap();
+    }
+
+    let mut query = Query::new("SELECT b FROM tab WHERE a = 0");
+    query.set_page_size(4);
+    let mut prepared = session.prepare(query.clone()).await.unwrap();
+    prepared.set_page_size(4);
+
+    // Reads the first page and returns its rows with the paging state as a string
+    async fn first_page(mut rows: TypedRowIterator<(i32,)>) -> (Vec<i32>, String) {
+        let mut result = Vec::new();
+        for _ in 0..rows.get_remaining_rows_in_page() {
+            result.push(rows.next().await.unwrap().unwrap().0);
+        }
+        assert_eq!(rows.get_remaining_rows_in_page(), 0);
+        (result, rows.get_paging_state().unwrap().to_string())
+    }
+
+    async fn all_rows(rows: TypedRowIterator<(i32,)>) -> Vec<i32> {
+        rows.map(|row| row.unwrap().0).collect().await
+    }
+
+    let (rows, cursor) = first_page(
+        session
+            .query_iter(query.clone(), &[])
+            .await
+            .unwrap()
+            .into_typed(),
+    )
+    .await;
+    assert_eq!(rows, vec![0, 1, 2, 3]);
+    let paging_state: PagingState = cursor.parse().unwrap();
+    let rest = all_rows(
+        session
+            .query_iter_from(query, &[], paging_state)
+            .await
+            .unwrap()
+            .into_typed(),
+    )
+    .await;
+    assert_eq!(rest, (4..10).collect::<Vec<_>>());
+
+    let (rows, cursor) = first_page(
+        session
+            .execute_iter(prepared.clone(), &[])
+            .await
+            .unwrap()
+            .into_typed(),
+    )
+    .await;
+    assert_eq!(rows, vec![0, 1, 2, 3]);
+    let mut resumed = session
+        .execute_iter_from(prepared, &[], cursor.parse().unwrap())
+        .await
+        .unwrap()
+        .into_typed::<(i32,)>();
+    assert_eq!(resumed.get_remaining_rows_in_page(), 4);
+    assert!(resumed.get_paging_state().is_some());
+    resumed.next().await.unwrap().unwrap();
+    assert_eq!(all_rows(resumed).await, (5..10).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_iter_page_buffering() {
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session.use_keyspace(ks, false).await.unwrap();
+    session
+        .query("CREATE TABLE tab (a int, b int, PRIMARY KEY (a, b))", &[])
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    for b in 0..20 {
+        session
+            .query("INSERT INTO tab (a, b) VALUES (0, ?)", (b,))
+            .await
+            .unwrap();
+    }
+
+    let mut prepared = session
+        .prepare(Query::new("SELECT b FROM tab WHERE a = 0").with_page_size(3))
+        .await
+        .unwrap();
+
+    for (prefetch_pages, buffer_limit) in [(0, None), (1, None), (5, Some(1)), (5, None)] {
+        prepared.set_prefetch_pages(prefetch_pages);
+        prepared.set_page_buffer_limit(buffer_limit);
+        prepared.set_page_timeout(Some(std::time::Duration::from_secs(10)));
+
+        let rows: Vec<i32> = session
+            .execute_iter(prepared.clone(), &[])
+            .await
+            .unwrap()
+            .into_typed::<(i32,)>()
+            .map(|row| row.unwrap().0)
+            .collect()
+            .await;
+        assert_eq!(rows, (0..20).collect::<Vec<_>>());
+    }
+}
+
+#[tokio::test]
+async fn test_scan_table() {
+    use crate::transport::table_scan::{ScanCheckpoint, ScanOptions};
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE {}.tab (a int, b int, c text, PRIMARY KEY ((a, b)))",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+
+    let insert = session
+        .prepare(format!("INSERT INTO {}.tab (a, b, c) VALUES (?, ?, ?)", ks))
+        .await
+        .unwrap();
+    for a in 0..100 {
+        session
+            .execute(&insert, (a, a * 2, a.to_string()))
+            .await
+            .unwrap();
+    }
+
+    let rows: BTreeSet<(i32, String)> = session
+        .scan_table(&ks, "tab", &["a", "c"])
+        .await
+        .unwrap()
+        .map(|row| row.unwrap().into_typed::<(i32, String)>().unwrap())
+        .collect()
+        .await;
+    let expected: BTreeSet<(i32, String)> = (0..100).map(|a| (a, a.to_string())).collect();
+    assert_eq!(rows, expected);
+
+    // Scan with small pages and low concurrency, stop in the middle and resume from the checkpoint
+    let options = ScanOptions {
+        concurrency: 2,
+        split_per_shard: false,
+        page_size: Some(3),
+        ..Default::default()
+    };
+    let mut scan = session
+        .scan_table_with_options(&ks, "tab", &[], options.clone())
+        .await
+        .unwrap();
+    let mut first_part: Vec<i32> = Vec::new();
+    while first_part.len() < 30 {
+        let (a, _, _) = scan
+            .next()
+            .await
+            .unwrap()
+            .unwrap()
+            .into_typed::<(i32, i32, String)>()
+            .unwrap();
+        first_part.push(a);
+    }
+    let checkpoint: ScanCheckpoint = scan.checkpoint().clone();
+    drop(scan);
+
+    // Rows returned before the checkpoint from ranges that weren't completed are read again
+    let resumed: Vec<i32> = session
+        .scan_table_with_options(
+            &ks,
+            "tab",
+            &["a"],
+            ScanOptions {
+                resume_from: checkpoint.clone(),
+                ..options
+            },
+        )
+        .await
+        .unwrap()
+        .map(|row| row.unwrap().into_typed::<(i32,)>().unwrap().0)
+        .collect()
+        .await;
+    let all: BTreeSet<i32> = first_part.iter().chain(resumed.iter()).copied().collect();
+    assert_eq!(all, (0..100).collect());
+    assert!(resumed.len() + first_part.len() >= 100);
+    assert!(resumed.len() <= 100);
+
+    let unknown = session.scan_table(&ks, "no_such_table", &[]).await;
+    assert_matches!(
+        unknown.err(),
+        Some(QueryError::BadQuery(BadQuery::Other(_)))
+    );
+}
+
+#[tokio::test]
+async fn test_bulk_writer() {
+    use crate::transport::bulk_writer::{BulkGrouping, BulkWriter, BulkWriterOptions};
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!(
+                "CREATE TABLE {}.tab (a int, b int, c text, PRIMARY KEY (a, b))",
+                ks
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    let insert = session
+        .prepare(format!("INSERT INTO {}.tab (a, b, c) VALUES (?, ?, ?)", ks))
+        .await
+        .unwrap();
+
+    for (grouping, a_offset) in [(BulkGrouping::Partition, 0), (BulkGrouping::Replica, 100)] {
+        let writer = BulkWriter::with_options(
+            &session,
+            insert.clone(),
+            BulkWriterOptions {
+                concurrency: 4,
+                grouping,
+                max_batch_rows: 7,
+                max_buffered_rows: 50,
+                ..Default::default()
+            },
+        );
+        let rows =
+            futures::stream::iter((0..1000).map(|i: i32| (i % 20 + a_offset, i, i.to_string())));
+        let report = writer.write(rows).await;
+
+        assert!(report.is_success(), "{:?}", report.failed_rows);
+        assert_eq!(report.rows_written, 1000);
+        assert!(report.batches >= 1000 / 7);
+        assert!(report.batches < 1000);
+    }
+
+    let mut count: Vec<(i64,)> = session
+        .query(format!("SELECT COUNT(*) FROM {}.tab", ks), &[])
+        .await
+        .unwrap()
+        .rows_typed::<(i64,)>()
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+    assert_eq!(count.pop(), Some((2000,)));
+
+    // Rows with missing values are rejected by the database
+    let report = BulkWriter::new(&session, insert)
+        .write(futures::stream::iter((0..10).map(|i: i32| (i,))))
+        .await;
+    assert_eq!(report.rows_written, 0);
+    assert_eq!(
+        report
+            .failed_rows
+            .iter()
+            .map(|failed| failed.index)
+            .collect::<Vec<_>>(),
+        (0..10).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_execute_many() {
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!("CREATE TABLE {}.tab (a int PRIMARY KEY, b int)", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    let insert = session
+        .prepare(format!("INSERT INTO {}.tab (a, b) VALUES (?, ?)", ks))
+        .await
+        .unwrap();
+    let results: Vec<_> = session
+        .execute_many(&insert, (0..200).map(|a: i32| (a, a * 2)), 16)
+        .collect()
+        .await;
+    assert_eq!(results.len(), 200);
+    assert!(results.iter().all(|result| result.is_ok()));
+
+    // Results are returned in the order of values
+    let select = session
+        .prepare(format!("SELECT b FROM {}.tab WHERE a = ?", ks))
+        .await
+        .unwrap();
+    let selected: Vec<i32> = session
+        .execute_many(&select, (0..200).rev().map(|a: i32| (a,)), 16)
+        .map(|result| result.unwrap().single_row_typed::<(i32,)>().unwrap().0)
+        .collect()
+        .await;
+    assert_eq!(selected, (0..200).rev().map(|a| a * 2).collect::<Vec<_>>());
+
+    // Values with the wrong number of columns fail
+    let values: Vec<Vec<i32>> = (0..20)
+        .map(|a| if a == 5 { vec![a] } else { vec![a, a] })
+        .collect();
+    let results: Vec<_> = session
+        .execute_many(&insert, values.clone(), 4)
+        .collect()
+        .await;
+    assert_eq!(results.len(), 20);
+    assert!(results[5].is_err());
+    assert_eq!(results.iter().filter(|result| result.is_err()).count(), 1);
+
+    let results: Vec<_> = session
+        .execute_many(&insert, values, 4)
+        .stop_on_error()
+        .collect()
+        .await;
+    assert_eq!(results.len(), 6);
+    assert!(results[..5].iter().all(|result| result.is_ok()));
+    assert!(results[5].is_err());
+}
+
+#[tokio::test]
+async fn test_lwt_result() {
+    use crate::transport::query_result::LwtResult;
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!("CREATE TABLE {}.tab (a int PRIMARY KEY, b int)", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    let insert = session
+        .prepare(format!(
+            "INSERT INTO {}.tab (a, b) VALUES (?, ?) IF NOT EXISTS",
+            ks
+        ))
+        .await
+        .unwrap();
+    let select = session
+        .prepare(format!("SELECT b FROM {}.tab WHERE a = ?", ks))
+        .await
+        .unwrap();
+    assert!(!select.is_confirmed_lwt());
+
+    // Only Scylla marks prepared LWT statements
+    let is_scylla = session
+        .get_cluster_data()
+        .get_nodes_info()
+        .iter()
+        .any(|node| node.sharder().is_some());
+    assert_eq!(insert.is_confirmed_lwt(), is_scylla);
+
+    let applied = session
+        .execute(&insert, (1, 2))
+        .await
+        .unwrap()
+        .lwt_applied()
+        .unwrap();
+    assert!(applied);
+
+    let result: LwtResult<(i32, i32)> = session
+        .execute(&insert, (1, 3))
+        .await
+        .unwrap()
+        .lwt_result_typed()
+        .unwrap();
+    assert_eq!(
+        result,
+        LwtResult {
+            applied: false,
+            existing: Some((1, 2))
+        }
+    );
+
+    let result: LwtResult<(i32, i32)> = session
+        .query(
+            format!("UPDATE {}.tab SET b = 4 WHERE a = 1 IF b = 2", ks),
+            &[],
+        )
+        .await
+        .unwrap()
+        .lwt_result_typed()
+        .unwrap();
+    assert!(result.applied);
+
+    let not_lwt = session.execute(&select, (1,)).await.unwrap().lwt_applied();
+    assert_matches!(
+        not_lwt,
+        Err(crate::transport::query_result::LwtResultError::NotLwtResult)
+    );
+}
+
+#[tokio::test]
+async fn test_server_timeout() {
+    use std::time::Duration;
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    // USING TIMEOUT is a Scylla extension
+    let is_scylla = session
+        .get_cluster_data()
+        .get_nodes_info()
+        .iter()
+        .any(|node| node.sharder().is_some());
+    if !is_scylla {
+        return;
+    }
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!("CREATE TABLE {}.tab (a int PRIMARY KEY, b int)", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    let timeout = Some(Duration::from_secs(2));
+
+    let mut insert: Query = format!("INSERT INTO {}.tab (a, b) VALUES (?, ?)", ks).into();
+    insert.set_server_timeout(timeout);
+    session.query(insert, (1, 2)).await.unwrap();
+
+    let mut update: Query =
+        format!("UPDATE {}.tab USING TTL 1000 SET b = ? WHERE a = ?", ks).into();
+    update.set_server_timeout(timeout);
+    session.query(update, (3, 1)).await.unwrap();
+
+    let mut select: Query = format!("SELECT b FROM {}.tab WHERE a = ?", ks).into();
+    select.set_server_timeout(timeout);
+    let prepared = session.prepare(select).await.unwrap();
+    assert_eq!(prepared.get_server_timeout(), timeout);
+    assert!(prepared.get_statement().ends_with("USING TIMEOUT 2000ms"));
+    let (b,) = session
+        .execute(&prepared, (1,))
+        .await
+        .unwrap()
+        .single_row_typed::<(i32,)>()
+        .unwrap();
+    assert_eq!(b, 3);
+
+    let mut delete: Query = format!("DELETE FROM {}.tab WHERE a = ?", ks).into();
+    delete.set_server_timeout(timeout);
+    session.query(delete, (1,)).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_timestamp_generator() {
+    use crate::transport::timestamp_generator::MonotonicTimestampGenerator;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new()
+        .known_node(uri)
+        .timestamp_generator(Arc::new(MonotonicTimestampGenerator::new()))
+        .build()
+        .await
+        .unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!("CREATE TABLE {}.tab (a int PRIMARY KEY, b int)", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    let now = || {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as i64
+    };
+    let select_writetime = format!("SELECT WRITETIME(b) FROM {}.tab WHERE a = ?", ks);
+
+    let before = now();
+    session
+        .query(format!("INSERT INTO {}.tab (a, b) VALUES (1, 1)", ks), &[])
+        .await
+        .unwrap();
+    let insert = session
+        .prepare(format!("INSERT INTO {}.tab (a, b) VALUES (?, ?)", ks))
+        .await
+        .unwrap();
+    session.execute(&insert, (2, 2)).await.unwrap();
+    let mut batch = Batch::default();
+    batch.append_statement(insert.clone());
+    session.batch(&batch, ((3, 3),)).await.unwrap();
+    let after = now();
+
+    let mut writetimes = Vec::new();
+    for a in 1..=3 {
+        let (writetime,) = session
+            .query(select_writetime.clone(), (a,))
+            .await
+            .unwrap()
+            .single_row_typed::<(i64,)>()
+            .unwrap();
+        writetimes.push(writetime);
+    }
+    assert!(writetimes.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(writetimes[0] >= before && writetimes[2] <= after + 1000);
+
+    // A timestamp set on the statement takes precedence
+    let mut insert_with_timestamp = insert.clone();
+    insert_with_timestamp.set_timestamp(Some(42));
+    session
+        .execute(&insert_with_timestamp, (4, 4))
+        .await
+        .unwrap();
+    let (writetime,) = session
+        .query(select_writetime, (4,))
+        .await
+        .unwrap()
+        .single_row_typed::<(i64,)>()
+        .unwrap();
+    assert_eq!(writetime, 42);
+}
+
+#[tokio::test]
+async fn test_infer_idempotence() {
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new()
+        .known_node(uri)
+        .infer_idempotence(true)
+        .build()
+        .await
+        .unwrap();
+
+    let ks = unique_keyspace_name();
+    session.query(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'SimpleStrategy', 'replication_factor' : 1}}", ks), &[]).await.unwrap();
+    session
+        .query(
+            format!("CREATE TABLE {}.tab (a int PRIMARY KEY, b int)", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+
+    let insert = session
+        .prepare(format!("INSERT INTO {}.tab (a, b) VALUES (?, ?)", ks))
+        .await
+        .unwrap();
+    assert!(insert.get_is_idempotent());
+
+    let lwt = session
+        .prepare(format!(
+            "INSERT INTO {}.tab (a, b) VALUES (?, ?) IF NOT EXISTS",
+            ks
+        ))
+        .await
+        .unwrap();
+    assert!(!lwt.get_is_idempotent());
+
+    // Explicitly set idempotence is not overwritten
+    let mut select = Query::new(format!("SELECT b FROM {}.tab WHERE a = ?", ks));
+    select.set_is_idempotent(false);
+    let select = session.prepare(select).await.unwrap();
+    assert!(!select.get_is_idempotent());
+}
+
+#[tokio::test]
+async fn test_target_node() {
+    use crate::frame::response::result::CqlValue;
+
+    let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+    let session = SessionBuilder::new().known_node(uri).build().await.unwrap();
+
+    let cluster_data = session.get_cluster_data();
+    let nodes = cluster_data.get_nodes_info();
+
+    // Every node reports its own host id in system.local
+    let results = session
+        .query_on_all_nodes("SELECT host_id FROM system.local", &[])
+        .await
+        .unwrap();
+    assert_eq!(results.len(), nodes.len());
+    for (node, result) in results {
+        let (host_id,) = result.unwrap().single_row_typed::<(Uuid,)>().unwrap();
+        assert_eq!(host_id, node.host_id);
+    }
+
+    let mut prepared = session
+        .prepare("SELECT host_id FROM system.local")
+        .await
+        .unwrap();
+    for node in nodes {
+        prepared.set_target_node(Some(node.clone()));
+        let (host_id,) = session
+            .execute(&prepared, &[])
+            .await
+            .unwrap()
+            .single_row_typed::<(Uuid,)>()
+            .unwrap();
+        assert_eq!(host_id, node.host_id);
+
+        let mut rows = session.execute_iter(prepared.clone(), &[]).await.unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.columns[0], Some(CqlValue::Uuid(node.host_id)));
+
+        if let Some(sharder) = node.sharder() {
+            let mut query = Query::new("SELECT host_id FROM system.local");
+            query.set_target_node(Some(node.clone()));
+            query.set_target_shard(Some(sharder.nr_shards.get() as u32 - 1));
+            session.query(query.clone(), &[]).await.unwrap();
+
+            query.set_target_shard(Some(sharder.nr_shards.get() as u32));
+            assert_matches!(
+                session.query(query, &[]).await,
+                Err(QueryError::BadQuery(BadQuery::Other(_)))
+            );
+        }
+    }
+}
@@ -0,0 +1,574 @@
+//! Parallel full table scans split over token ranges
+
+use std::collections::{BTreeMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::stream::{self, BoxStream, SelectAll};
+use futures::{Stream, StreamExt};
+
+use super::errors::QueryError;
+use super::iterator::{PagingState, RowIterator};
+use crate::frame::response::result::Row;
+use crate::routing::{Sharder, Token};
+use crate::statement::prepared_statement::PreparedStatement;
+use crate::statement::Consistency;
+use crate::transport::node::Node;
+use crate::transport::session::Session;
+
+/// Default number of token ranges scanned at the same time
+pub const DEFAULT_SCAN_CONCURRENCY: usize = 16;
+
+/// Default number of times a single token range is retried after a failure
+pub const DEFAULT_MAX_RANGE_RETRIES: usize = 3;
+
+/// Range of tokens `(start, end]` - `start` is excluded and `end` is included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TokenRange {
+    pub start: Token,
+    pub end: Token,
+}
+
+impl TokenRange {
+    pub fn new(start: Token, end: Token) -> TokenRange {
+        TokenRange { start, end }
+    }
+
+    /// Range covering the whole token ring
+    pub fn full_ring() -> TokenRange {
+        TokenRange::new(Token { value: i64::MIN }, Token { value: i64::MAX })
+    }
+
+    /// Returns true if the range contains no tokens
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    /// Returns true if `token` belongs to the range
+    pub fn contains(&self, token: Token) -> bool {
+        self.start < token && token <= self.end
+    }
+}
+
+/// Progress of a table scan - token ranges which were scanned completely.
+///
+/// Rows from a completed range have all been returned by the scan,
+/// so a checkpoint can be saved and passed in [`ScanOptions::resume_from`]
+/// to continue an interrupted scan without returning these rows again.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanCheckpoint {
+    /// Sorted, non-overlapping ranges. Adjacent ranges are merged.
+    completed_ranges: Vec<TokenRange>,
+}
+
+impl ScanCheckpoint {
+    /// Creates a checkpoint from a list of completed ranges
+    pub fn from_ranges(ranges: impl IntoIterator<Item = TokenRange>) -> ScanCheckpoint {
+        let mut checkpoint = ScanCheckpoint::default();
+        for range in ranges {
+            checkpoint.add(range);
+        }
+        checkpoint
+    }
+
+    /// Ranges that were scanned completely, sorted by token
+    pub fn completed_ranges(&self) -> &[TokenRange] {
+        &self.completed_ranges
+    }
+
+    /// Returns true if the whole ring has been scanned
+    pub fn is_complete(&self) -> bool {
+        self.completed_ranges == [TokenRange::full_ring()]
+    }
+
+    /// Marks a range as completed
+    pub fn add(&mut self, range: TokenRange) {
+        if range.is_empty() {
+            return;
+        }
+
+        let pos = self
+            .completed_ranges
+            .partition_point(|completed| completed.start < range.start);
+        self.completed_ranges.insert(pos, range);
+
+        let mut merged: Vec<TokenRange> = Vec::with_capacity(self.completed_ranges.len());
+        for range in self.completed_ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        self.completed_ranges = merged;
+    }
+
+    /// Returns parts of `range` which are not yet completed
+    pub fn remaining(&self, range: TokenRange) -> Vec<TokenRange> {
+        let mut result = Vec::new();
+        let mut start = range.start;
+
+        for completed in &self.completed_ranges {
+            if completed.end <= start {
+                continue;
+            }
+            if completed.start >= range.end {
+                break;
+            }
+            if completed.start > start {
+                result.push(TokenRange::new(start, completed.start));
+            }
+            start = completed.end;
+            if start >= range.end {
+                return result;
+            }
+        }
+
+        if start < range.end {
+            result.push(TokenRange::new(start, range.end));
+        }
+        result
+    }
+}
+
+/// Options of [`Session::scan_table_with_options`]
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Maximum number of token ranges scanned at the same time.\
+    /// Default: [`DEFAULT_SCAN_CONCURRENCY`]
+    pub concurrency: usize,
+
+    /// Split ranges owned by a node into ranges owned by a single shard.\
+    /// Requires knowledge of the node's sharding, ranges of non-Scylla nodes are not split.\
+    /// Default: true
+    pub split_per_shard: bool,
+
+    /// How many times a token range is retried after a failure.\
+    /// A retried range continues from the page on which it failed, rows are not returned twice.\
+    /// Default: [`DEFAULT_MAX_RANGE_RETRIES`]
+    pub max_range_retries: usize,
+
+    /// Page size of queries scanning token ranges.\
+    /// Default: same as in [`Session::execute_iter`]
+    pub page_size: Option<i32>,
+
+    /// Consistency of queries scanning token ranges.\
+    /// Default: session's default consistency
+    pub consistency: Option<Consistency>,
+
+    /// Ranges which were already scanned and should be skipped.\
+    /// Default: empty
+    pub resume_from: ScanCheckpoint,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            concurrency: DEFAULT_SCAN_CONCURRENCY,
+            split_per_shard: true,
+            max_range_retries: DEFAULT_MAX_RANGE_RETRIES,
+            page_size: None,
+            consistency: None,
+            resume_from: ScanCheckpoint::default(),
+        }
+    }
+}
+
+/// Splits the token ring into ranges owned by a single node.\
+/// If `split_per_shard` is true, ranges are further split into ranges owned by a single shard.
+pub(crate) fn ring_ranges(
+    ring: &BTreeMap<Token, Arc<Node>>,
+    split_per_shard: bool,
+) -> Vec<TokenRange> {
+    let owners: Vec<(Token, Option<Sharder>)> = ring
+        .iter()
+        .map(|(token, node)| {
+            let sharder = if split_per_shard {
+                node.sharder()
+            } else {
+                None
+            };
+            (*token, sharder)
+        })
+        .collect();
+
+    split_ring(&owners)
+}
+
+/// Splits the ring defined by sorted tokens into ranges `(previous token, token]`.
+/// Tokens after the last one belong to the owner of the first token.
+fn split_ring(owners: &[(Token, Option<Sharder>)]) -> Vec<TokenRange> {
+    let (first_token, first_sharder) = match owners.first() {
+        Some(first) => first,
+        None => return vec![TokenRange::full_ring()],
+    };
+
+    let mut node_ranges: Vec<(TokenRange, Option<&Sharder>)> = Vec::with_capacity(owners.len() + 1);
+    node_ranges.push((
+        TokenRange::new(Token { value: i64::MIN }, *first_token),
+        first_sharder.as_ref(),
+    ));
+    for window in owners.windows(2) {
+        let (start, _) = &window[0];
+        let (end, sharder) = &window[1];
+        node_ranges.push((TokenRange::new(*start, *end), sharder.as_ref()));
+    }
+    let (last_token, _) = owners.last().unwrap();
+    node_ranges.push((
+        TokenRange::new(*last_token, Token { value: i64::MAX }),
+        first_sharder.as_ref(),
+    ));
+
+    let mut ranges = Vec::new();
+    for (range, sharder) in node_ranges {
+        if range.is_empty() {
+            continue;
+        }
+        match sharder {
+            Some(sharder) => split_range_per_shard(range, sharder, &mut ranges),
+            None => ranges.push(range),
+        }
+    }
+    ranges
+}
+
+/// Splits a non-empty range into ranges in which all tokens belong to the same shard
+fn split_range_per_shard(range: TokenRange, sharder: &Sharder, ranges: &mut Vec<TokenRange>) {
+    let mut start = range.start;
+    loop {
+        // start < range.end, so the first token of the range never overflows
+        let first_token = Token {
+            value: start.value + 1,
+        };
+        match next_shard_start(sharder, first_token) {
+            Some(next_start) if next_start <= range.end => {
+                let end = Token {
+                    value: next_start.value - 1,
+                };
+                ranges.push(TokenRange::new(start, end));
+                start = end;
+            }
+            _ => {
+                ranges.push(TokenRange::new(start, range.end));
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the smallest token greater than `token` which is the first token of a shard's
+/// continuous range of tokens, or `None` if there is no such token.
+///
+/// The ring is divided into `2^msb_ignore` equal parts and each of them is divided
+/// between shards into `nr_shards` continuous ranges - see [`Sharder::shard_of`].
+fn next_shard_start(sharder: &Sharder, token: Token) -> Option<Token> {
+    let biased_token = (token.value as u64).wrapping_add(1u64 << 63) as u128;
+    let nr_shards = sharder.nr_shards.get() as u128;
+    let part_size: u128 = 1 << (64 - sharder.msb_ignore as u32);
+
+    let part_start = biased_token / part_size * part_size;
+    let shard = (biased_token - part_start) * nr_shards / part_size;
+    let next_shard_offset = ((shard + 1) * part_size - 1) / nr_shards + 1;
+
+    let next_start = if next_shard_offset >= part_size {
+        part_start + part_size
+    } else {
+        part_start + next_shard_offset
+    };
+
+    if next_start > u64::MAX as u128 {
+        return None;
+    }
+    Some(Token {
+        value: (next_start as u64).wrapping_sub(1u64 << 63) as i64,
+    })
+}
+
+/// Merged stream of rows from a parallel scan of a table, created by [`Session::scan_table`].
+///
+/// Rows from different token ranges are interleaved, rows from a single range are ordered by token.
+/// When scanning a range fails more times than allowed by [`ScanOptions::max_range_retries`],
+/// the error is returned from the stream and the scan continues with other ranges.
+/// The failed range is not included in the [checkpoint](TableScan::checkpoint).
+pub struct TableScan<'a> {
+    session: &'a Session,
+    prepared: Arc<PreparedStatement>,
+    max_range_retries: usize,
+    concurrency: usize,
+    pending_ranges: VecDeque<TokenRange>,
+    active_ranges: SelectAll<BoxStream<'a, RangeScanItem>>,
+    checkpoint: ScanCheckpoint,
+}
+
+enum RangeScanItem {
+    Row(Row),
+    Error(QueryError),
+    Completed(TokenRange),
+}
+
+impl<'a> TableScan<'a> {
+    pub(crate) fn new(
+        session: &'a Session,
+        prepared: PreparedStatement,
+        ranges: Vec<TokenRange>,
+        options: ScanOptions,
+    ) -> TableScan<'a> {
+        let pending_ranges = ranges
+            .into_iter()
+            .flat_map(|range| options.resume_from.remaining(range))
+            .collect();
+
+        TableScan {
+            session,
+            prepared: Arc::new(prepared),
+            max_range_retries: options.max_range_retries,
+            concurrency: options.concurrency.max(1),
+            pending_ranges,
+            active_ranges: SelectAll::new(),
+            checkpoint: options.resume_from,
+        }
+    }
+
+    /// Progress of the scan. Includes ranges from [`ScanOptions::resume_from`].
+    pub fn checkpoint(&self) -> &ScanCheckpoint {
+        &self.checkpoint
+    }
+
+    /// Number of token ranges which are being scanned or wait to be scanned
+    pub fn get_remaining_ranges(&self) -> usize {
+        self.pending_ranges.len() + self.active_ranges.len()
+    }
+
+    fn start_pending_ranges(&mut self) {
+        while self.active_ranges.len() < self.concurrency {
+            let range = match self.pending_ranges.pop_front() {
+                Some(range) => range,
+                None => return,
+            };
+
+            let state = RangeScan {
+                session: self.session,
+                prepared: self.prepared.clone(),
+                range,
+                retries_left: self.max_range_retries,
+                rows: None,
+                page_start: None,
+                rows_read_from_page: 0,
+                rows_to_skip: 0,
+                finished: false,
+            };
+            self.active_ranges
+                .push(stream::unfold(state, RangeScan::next_item).boxed());
+        }
+    }
+}
+
+impl Stream for TableScan<'_> {
+    type Item = Result<Row, QueryError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            self.start_pending_ranges();
+
+            match self.active_ranges.poll_next_unpin(cx) {
+                Poll::Ready(Some(RangeScanItem::Row(row))) => return Poll::Ready(Some(Ok(row))),
+                Poll::Ready(Some(RangeScanItem::Error(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(Some(RangeScanItem::Completed(range))) => self.checkpoint.add(range),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// State of a scan of a single token range
+struct RangeScan<'a> {
+    session: &'a Session,
+    prepared: Arc<PreparedStatement>,
+    range: TokenRange,
+    retries_left: usize,
+    rows: Option<RowIterator>,
+    // Paging state with which the current page was requested
+    page_start: Option<PagingState>,
+    rows_read_from_page: usize,
+    // Rows of the current page returned before a retry
+    rows_to_skip: usize,
+    finished: bool,
+}
+
+impl<'a> RangeScan<'a> {
+    async fn next_item(mut self) -> Option<(RangeScanItem, RangeScan<'a>)> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let rows = match &mut self.rows {
+                Some(rows) => rows,
+                None => match self.start_query().await {
+                    Ok(rows) => self.rows.insert(rows),
+                    Err(err) => match self.retry_or_fail(err) {
+                        Some(err) => return Some((RangeScanItem::Error(err), self)),
+                        None => continue,
+                    },
+                },
+            };
+
+            if rows.get_remaining_rows_in_page() == 0 && self.rows_to_skip == 0 {
+                // The next row comes from a new page
+                self.page_start = rows.get_paging_state();
+                self.rows_read_from_page = 0;
+            }
+
+            match rows.next().await {
+                Some(Ok(_)) if self.rows_to_skip > 0 => self.rows_to_skip -= 1,
+                Some(Ok(row)) => {
+                    self.rows_read_from_page += 1;
+                    return Some((RangeScanItem::Row(row), self));
+                }
+                Some(Err(err)) => {
+                    if let Some(err) = self.retry_or_fail(err) {
+                        return Some((RangeScanItem::Error(err), self));
+                    }
+                }
+                None => {
+                    self.finished = true;
+                    return Some((RangeScanItem::Completed(self.range), self));
+                }
+            }
+        }
+    }
+
+    async fn start_query(&self) -> Result<RowIterator, QueryError> {
+        let values = (self.range.start.value, self.range.end.value);
+        self.session
+            .execute_iter_with_paging_state(
+                self.prepared.as_ref().clone(),
+                values,
+                self.page_start.clone(),
+                Some(self.range.end),
+            )
+            .await
+    }
+
+    /// Prepares the state to retry the range from the page on which the scan failed.
+    /// Returns the error back if there are no retries left.
+    fn retry_or_fail(&mut self, err: QueryError) -> Option<QueryError> {
+        if self.retries_left == 0 {
+            self.finished = true;
+            return Some(err);
+        }
+        self.retries_left -= 1;
+        self.rows = None;
+        self.rows_to_skip = self.rows_read_from_page;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_shard_start, split_ring, ScanCheckpoint, TokenRange};
+    use crate::routing::{Sharder, Token};
+    use std::num::NonZeroU16;
+
+    fn range(start: i64, end: i64) -> TokenRange {
+        TokenRange::new(Token { value: start }, Token { value: end })
+    }
+
+    fn token(value: i64) -> Token {
+        Token { value }
+    }
+
+    #[test]
+    fn split_ring_into_node_ranges() {
+        assert_eq!(split_ring(&[]), vec![TokenRange::full_ring()]);
+
+        let ranges = split_ring(&[(token(-100), None), (token(0), None), (token(100), None)]);
+        assert_eq!(
+            ranges,
+            vec![
+                range(i64::MIN, -100),
+                range(-100, 0),
+                range(0, 100),
+                range(100, i64::MAX)
+            ]
+        );
+
+        let ranges = split_ring(&[(token(i64::MIN), None), (token(i64::MAX), None)]);
+        assert_eq!(ranges, vec![range(i64::MIN, i64::MAX)]);
+    }
+
+    #[test]
+    fn split_ring_into_shard_ranges() {
+        for (nr_shards, msb_ignore) in [(1, 0), (3, 0), (4, 12), (7, 12), (13, 5)] {
+            let sharder = Sharder::new(NonZeroU16::new(nr_shards).unwrap(), msb_ignore);
+            let owners = [
+                (token(-1 << 60), Some(sharder.clone())),
+                (token(12345), Some(sharder.clone())),
+            ];
+            let ranges = split_ring(&owners);
+
+            assert_eq!(ranges.first().unwrap().start, token(i64::MIN));
+            assert_eq!(ranges.last().unwrap().end, token(i64::MAX));
+            for window in ranges.windows(2) {
+                assert_eq!(window[0].end, window[1].start);
+            }
+            assert!(ranges.iter().any(|r| r.end == token(-1 << 60)));
+            assert!(ranges.iter().any(|r| r.end == token(12345)));
+
+            for r in &ranges {
+                assert!(!r.is_empty());
+                let shard = sharder.shard_of(token(r.start.value + 1));
+                assert_eq!(sharder.shard_of(r.end), shard);
+                if let Some(next) = next_shard_start(&sharder, token(r.start.value + 1)) {
+                    assert!(next > r.end);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn next_shard_start_is_shard_boundary() {
+        let sharder = Sharder::new(NonZeroU16::new(5).unwrap(), 12);
+        let mut current = token(i64::MIN);
+        for _ in 0..100 {
+            let next = next_shard_start(&sharder, current).unwrap();
+            assert!(next > current);
+            assert_ne!(
+                sharder.shard_of(next),
+                sharder.shard_of(token(next.value - 1))
+            );
+            current = next;
+        }
+
+        let sharder = Sharder::new(NonZeroU16::new(1).unwrap(), 0);
+        assert_eq!(next_shard_start(&sharder, token(i64::MIN)), None);
+    }
+
+    #[test]
+    fn checkpoint_merges_and_subtracts_ranges() {
+        let mut checkpoint = ScanCheckpoint::default();
+        checkpoint.add(range(10, 20));
+        checkpoint.add(range(30, 40));
+        checkpoint.add(range(20, 25));
+        checkpoint.add(range(5, 5));
+        assert_eq!(
+            checkpoint.completed_ranges(),
+            &[range(10, 25), range(30, 40)]
+        );
+
+        assert_eq!(
+            checkpoint.remaining(range(0, 50)),
+            vec![range(0, 10), range(25, 30), range(40, 50)]
+        );
+        assert_eq!(checkpoint.remaining(range(12, 24)), vec![]);
+        assert_eq!(checkpoint.remaining(range(24, 35)), vec![range(25, 30)]);
+        assert_eq!(checkpoint.remaining(range(40, 45)), vec![range(40, 45)]);
+
+        checkpoint.add(range(i64::MIN, 10));
+        checkpoint.add(range(25, 30));
+        checkpoint.add(range(35, i64::MAX));
+        assert!(checkpoint.is_complete());
+        assert_eq!(checkpoint.remaining(TokenRange::full_ring()), vec![]);
+    }
+}
@@ -0,0 +1,197 @@
+//! Client-side generation of write timestamps.
+//!
+//! When a write has no timestamp set, the one returned by the session's
+//! [`TimestampGenerator`] is sent with the request instead of relying on
+//! the coordinator's clock. The timestamp is generated once for all attempts of a request.
+
+use crate::statement::cql_lexer::tokenize;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Generates timestamps for writes which don't have a timestamp set explicitly.
+pub trait TimestampGenerator: Send + Sync {
+    /// Returns the next timestamp in microseconds since the unix epoch.
+    fn next_timestamp(&self) -> i64;
+}
+
+/// Default time after which [`MonotonicTimestampGenerator`] warns about timestamps drifting ahead of the clock
+pub const DEFAULT_WARNING_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Default minimal time between two drift warnings of [`MonotonicTimestampGenerator`]
+pub const DEFAULT_WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Generates strictly increasing timestamps based on the system clock, with microsecond precision.
+///
+/// If the clock goes backwards or more than one timestamp is requested in the same microsecond,
+/// the previous timestamp incremented by one is returned. A warning is logged if the generated timestamps
+/// get ahead of the clock by more than the warning threshold.
+pub struct MonotonicTimestampGenerator {
+    last: AtomicI64,
+    last_warning: AtomicI64,
+    warning_threshold: Option<Duration>,
+    warning_interval: Duration,
+}
+
+impl MonotonicTimestampGenerator {
+    /// Creates a generator which warns about drift with default threshold and interval.
+    pub fn new() -> Self {
+        Self {
+            last: AtomicI64::new(i64::MIN),
+            last_warning: AtomicI64::new(i64::MIN),
+            warning_threshold: Some(DEFAULT_WARNING_THRESHOLD),
+            warning_interval: DEFAULT_WARNING_INTERVAL,
+        }
+    }
+
+    /// Sets how far ahead of the clock timestamps can get before a warning is logged,
+    /// and the minimal time between two warnings.
+    pub fn with_warning_times(mut self, threshold: Duration, interval: Duration) -> Self {
+        self.warning_threshold = Some(threshold);
+        self.warning_interval = interval;
+        self
+    }
+
+    /// Disables drift warnings.
+    pub fn without_warnings(mut self) -> Self {
+        self.warning_threshold = None;
+        self
+    }
+
+    fn next_timestamp_at(&self, now: i64) -> i64 {
+        let mut last = self.last.load(Ordering::Relaxed);
+        let next = loop {
+            let next = if now > last { now } else { last + 1 };
+            match self
+                .last
+                .compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => break next,
+                Err(current) => last = current,
+            }
+        };
+
+        if let Some(threshold) = self.warning_threshold {
+            if next - now > threshold.as_micros() as i64 {
+                self.warn_about_drift(next, now);
+            }
+        }
+
+        next
+    }
+
+    fn warn_about_drift(&self, next: i64, now: i64) {
+        let last_warning = self.last_warning.load(Ordering::Relaxed);
+        if last_warning != i64::MIN && now - last_warning < self.warning_interval.as_micros() as i64
+        {
+            return;
+        }
+        // Only one of the threads which noticed the drift logs the warning
+        if self
+            .last_warning
+            .compare_exchange(last_warning, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            warn!(
+                "Generated timestamp {} is {}us ahead of the clock ({}); \
+                 the clock went backwards or timestamps are requested faster than one per microsecond",
+                next,
+                next - now,
+                now
+            );
+        }
+    }
+}
+
+impl Default for MonotonicTimestampGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimestampGenerator for MonotonicTimestampGenerator {
+    fn next_timestamp(&self) -> i64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_micros() as i64)
+            .unwrap_or(0);
+        self.next_timestamp_at(now)
+    }
+}
+
+/// Returns true if the statement writes data: an `INSERT`, `UPDATE`, `DELETE` or a `BATCH`.
+/// Other statements, e.g. reads and schema changes, don't get generated timestamps.
+pub(crate) fn is_write(contents: &str) -> bool {
+    matches!(
+        tokenize(contents).first(),
+        Some(first) if ["INSERT", "UPDATE", "DELETE", "BEGIN"]
+            .iter()
+            .any(|keyword| first.is_word(keyword))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_write, MonotonicTimestampGenerator, TimestampGenerator};
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn monotonic_generator_follows_clock() {
+        let generator = MonotonicTimestampGenerator::new();
+        assert_eq!(generator.next_timestamp_at(100), 100);
+        assert_eq!(generator.next_timestamp_at(250), 250);
+
+        // Same microsecond or clock going backwards
+        assert_eq!(generator.next_timestamp_at(250), 251);
+        assert_eq!(generator.next_timestamp_at(10), 252);
+        assert_eq!(generator.next_timestamp_at(300), 300);
+
+        let before = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as i64;
+        assert!(generator.next_timestamp() >= before);
+    }
+
+    #[test]
+    fn monotonic_generator_is_unique_across_threads() {
+        let generator = Arc::new(MonotonicTimestampGenerator::new().without_warnings());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let generator = generator.clone();
+                std::thread::spawn(move || {
+                    let timestamps: Vec<i64> =
+                        (0..1000).map(|_| generator.next_timestamp()).collect();
+                    assert!(timestamps.windows(2).all(|pair| pair[0] < pair[1]));
+                    timestamps
+                })
+            })
+            .collect();
+
+        let mut all = HashSet::new();
+        for handle in handles {
+            for timestamp in handle.join().unwrap() {
+                assert!(all.insert(timestamp));
+            }
+        }
+        assert_eq!(all.len(), 4000);
+    }
+
+    #[test]
+    fn writes_are_recognized() {
+        assert!(is_write("INSERT INTO ks.t (a) VALUES (?)"));
+        assert!(is_write("  update ks.t SET b = 1 WHERE a = 1"));
+        assert!(is_write("/* comment */ DELETE FROM ks.t WHERE a = 1"));
+        assert!(is_write(
+            "BEGIN UNLOGGED BATCH INSERT INTO t (a) VALUES (1); APPLY BATCH"
+        ));
+
+        assert!(!is_write("SELECT * FROM ks.t"));
+        assert!(!is_write("CREATE TABLE ks.t (a int PRIMARY KEY)"));
+        assert!(!is_write("TRUNCATE ks.t"));
+        assert!(!is_write("USE ks"));
+        assert!(!is_write(""));
+    }
+}
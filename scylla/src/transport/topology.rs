@@ -18,11 +18,13 @@ use std::fmt::Formatter;
 use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroUsize;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use strum_macros::EnumString;
 use tokio::sync::mpsc;
 use tracing::{debug, error, trace, warn};
+use uuid::Uuid;
 
 /// Allows to read current metadata from the cluster
 pub(crate) struct MetadataReader {
@@ -36,6 +38,9 @@ pub(crate) struct MetadataReader {
     known_peers: Vec<SocketAddr>,
     fetch_schema: bool,
 
+    // Cleared after the control connection's node turns out not to have system.peers_v2
+    peers_v2_supported: AtomicBool,
+
     address_translator: Option<Arc<dyn AddressTranslator>>,
     host_filter: Option<Arc<dyn HostFilter>>,
 }
@@ -52,6 +57,9 @@ pub struct Peer {
     pub tokens: Vec<Token>,
     pub datacenter: Option<String>,
     pub rack: Option<String>,
+    pub host_id: Uuid,
+    pub release_version: Option<String>,
+    pub schema_version: Option<Uuid>,
 }
 
 #[non_exhaustive] // <- so that we can add more fields in a backwards-compatible way
@@ -201,6 +209,10 @@ impl Metadata {
                     datacenter: None,
                     rack: None,
                     untranslated_address: None,
+                    // Real host ids are not known, so random ones are used
+                    host_id: Uuid::from_u128(rand::thread_rng().gen()),
+                    release_version: None,
+                    schema_version: None,
                 }
             })
             .collect();
@@ -244,6 +256,7 @@ impl MetadataReader {
             keepalive_interval,
             connection_config,
             known_peers: known_peers.into(),
+            peers_v2_supported: AtomicBool::new(true),
             fetch_schema,
             address_translator: address_translator.clone(),
             host_filter: host_filter.clone(),
@@ -298,6 +311,7 @@ impl MetadataReader {
                 self.connection_config.clone(),
                 self.keepalive_interval,
             );
+            self.peers_v2_supported.store(true, Ordering::Relaxed);
 
             debug!(
                 "Retrying to establish the control connection on {}",
@@ -331,6 +345,7 @@ impl MetadataReader {
             self.control_connection_address.port(),
             self.address_translator.as_deref(),
             self.fetch_schema,
+            &self.peers_v2_supported,
         )
         .await;
 
@@ -409,6 +424,7 @@ impl MetadataReader {
                         self.connection_config.clone(),
                         self.keepalive_interval,
                     );
+                    self.peers_v2_supported.store(true, Ordering::Relaxed);
                 }
             }
         }
@@ -440,8 +456,9 @@ async fn query_metadata(
     connect_port: u16,
     address_translator: Option<&dyn AddressTranslator>,
     fetch_schema: bool,
+    peers_v2_supported: &AtomicBool,
 ) -> Result<Metadata, QueryError> {
-    let peers_query = query_peers(conn, connect_port, address_translator);
+    let peers_query = query_peers(conn, connect_port, address_translator, peers_v2_supported);
     let keyspaces_query = query_keyspaces(conn, fetch_schema);
 
     let (peers, keyspaces) = tokio::try_join!(peers_query, keyspaces_query)?;
@@ -467,45 +484,70 @@ async fn query_peers(
     conn: &Connection,
     connect_port: u16,
     address_translator: Option<&dyn AddressTranslator>,
+    peers_v2_supported: &AtomicBool,
 ) -> Result<Vec<Peer>, QueryError> {
-    let mut peers_query =
-        Query::new("select rpc_address, data_center, rack, tokens from system.peers");
-    peers_query.set_page_size(1024);
-    let peers_query_future = conn.query_all(&peers_query, &[]);
+    let peers_query_future = query_peers_rows(conn, connect_port, peers_v2_supported);
 
-    let mut local_query =
-        Query::new("select rpc_address, data_center, rack, tokens from system.local");
+    let mut local_query = Query::new(
+        "select rpc_address, data_center, rack, tokens, host_id, release_version, schema_version \
+        from system.local",
+    );
     local_query.set_page_size(1024);
     let local_query_future = conn.query_all(&local_query, &[]);
 
-    let (peers_res, local_res) = tokio::try_join!(peers_query_future, local_query_future)?;
-
-    let peers_rows = peers_res.rows.ok_or(QueryError::ProtocolError(
-        "system.peers query response was not Rows",
-    ))?;
+    let (peers_rows, local_res) = tokio::try_join!(peers_query_future, local_query_future)?;
 
     let local_rows = local_res.rows.ok_or(QueryError::ProtocolError(
         "system.local query response was not Rows",
     ))?;
 
-    let typed_peers_rows =
-        peers_rows.into_typed::<(IpAddr, Option<String>, Option<String>, Option<Vec<String>>)>();
-
     let local_ip: IpAddr = conn.get_connect_address().ip();
     let local_address = SocketAddr::new(local_ip, connect_port);
 
-    let typed_local_rows =
-        local_rows.into_typed::<(IpAddr, Option<String>, Option<String>, Option<Vec<String>>)>();
-
-    let untranslated_rows = typed_peers_rows
-        .map(|res| res.map(|peer_row| (false, peer_row)))
-        .chain(typed_local_rows.map(|res| res.map(|local_row| (true, local_row))));
+    let typed_local_rows = local_rows
+        .into_typed::<LocalRow>()
+        .map(|row| {
+            let (rpc_address, datacenter, rack, tokens, host_id, release_version, schema_version) =
+                row.map_err(|_| QueryError::ProtocolError("system.local has invalid column type"))?;
+            Ok(NodeInfoRow {
+                untranslated_address: SocketAddr::new(rpc_address, connect_port),
+                datacenter,
+                rack,
+                tokens,
+                host_id,
+                release_version,
+                schema_version,
+            })
+        })
+        .collect::<Result<Vec<_>, QueryError>>()?;
+
+    let untranslated_rows = peers_rows
+        .into_iter()
+        .map(|peer_row| (false, peer_row))
+        .chain(
+            typed_local_rows
+                .into_iter()
+                .map(|local_row| (true, local_row)),
+        );
 
-    let translated_peers_futures = untranslated_rows.map(|untranslated_row| async {
-        let (is_local, (untranslated_ip_addr, datacenter, rack, tokens)) = untranslated_row.map_err(
-            |_| QueryError::ProtocolError("system.peers or system.local has invalid column type")
-        )?;
-        let untranslated_address = SocketAddr::new(untranslated_ip_addr, connect_port);
+    let translated_peers_futures = untranslated_rows.map(|(is_local, row)| async move {
+        let NodeInfoRow {
+            untranslated_address,
+            datacenter,
+            rack,
+            tokens,
+            host_id,
+            release_version,
+            schema_version,
+        } = row;
+
+        let host_id = match host_id {
+            Some(host_id) => host_id,
+            None => {
+                warn!("Node {} has no host id; node therefore skipped.", untranslated_address);
+                return Ok::<Option<Peer>, QueryError>(None);
+            }
+        };
 
         let (untranslated_address, address) = match (is_local, address_translator) {
             (true, None) => {
@@ -562,6 +604,9 @@ async fn query_peers(
             tokens,
             datacenter,
             rack,
+            host_id,
+            release_version,
+            schema_version,
         }))
     });
 
@@ -569,6 +614,138 @@ async fn query_peers(
     Ok(peers.into_iter().flatten().collect())
 }
 
+// Information about a node read from one of the system tables
+struct NodeInfoRow {
+    untranslated_address: SocketAddr,
+    datacenter: Option<String>,
+    rack: Option<String>,
+    tokens: Option<Vec<String>>,
+    host_id: Option<Uuid>,
+    release_version: Option<String>,
+    schema_version: Option<Uuid>,
+}
+
+type LocalRow = (
+    IpAddr,
+    Option<String>,
+    Option<String>,
+    Option<Vec<String>>,
+    Option<Uuid>,
+    Option<String>,
+    Option<Uuid>,
+);
+
+type PeersV2Row = (
+    IpAddr,
+    Option<i32>,
+    Option<String>,
+    Option<String>,
+    Option<Vec<String>>,
+    Option<Uuid>,
+    Option<String>,
+    Option<Uuid>,
+);
+
+/// Reads information about other nodes from `system.peers_v2`, which contains
+/// native transport ports of the nodes. If the table doesn't exist (it was
+/// introduced in Cassandra 4.0 and is not present in Scylla), `system.peers`
+/// is used instead and all nodes are assumed to listen on `connect_port`.
+async fn query_peers_rows(
+    conn: &Connection,
+    connect_port: u16,
+    peers_v2_supported: &AtomicBool,
+) -> Result<Vec<NodeInfoRow>, QueryError> {
+    if peers_v2_supported.load(Ordering::Relaxed) {
+        let mut peers_v2_query = Query::new(
+            "select native_address, native_port, data_center, rack, tokens, host_id, \
+            release_version, schema_version from system.peers_v2",
+        );
+        peers_v2_query.set_page_size(1024);
+
+        match conn.query_all(&peers_v2_query, &[]).await {
+            Ok(peers_v2_res) => {
+                let peers_v2_rows = peers_v2_res.rows.ok_or(QueryError::ProtocolError(
+                    "system.peers_v2 query response was not Rows",
+                ))?;
+
+                return peers_v2_rows
+                    .into_typed::<PeersV2Row>()
+                    .map(|row| {
+                        let (
+                            native_address,
+                            native_port,
+                            datacenter,
+                            rack,
+                            tokens,
+                            host_id,
+                            release_version,
+                            schema_version,
+                        ) = row.map_err(|_| {
+                            QueryError::ProtocolError("system.peers_v2 has invalid column type")
+                        })?;
+                        let port = match native_port {
+                            Some(port) => u16::try_from(port).map_err(|_| {
+                                QueryError::ProtocolError(
+                                    "system.peers_v2 contains invalid native port",
+                                )
+                            })?,
+                            None => connect_port,
+                        };
+                        Ok(NodeInfoRow {
+                            untranslated_address: SocketAddr::new(native_address, port),
+                            datacenter,
+                            rack,
+                            tokens,
+                            host_id,
+                            release_version,
+                            schema_version,
+                        })
+                    })
+                    .collect();
+            }
+            Err(QueryError::DbError(DbError::Invalid, message)) => {
+                debug!(
+                    error = message.as_str(),
+                    "system.peers_v2 is not available, falling back to system.peers"
+                );
+                peers_v2_supported.store(false, Ordering::Relaxed);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    let mut peers_query = Query::new(
+        "select rpc_address, data_center, rack, tokens, host_id, release_version, schema_version \
+        from system.peers",
+    );
+    peers_query.set_page_size(1024);
+
+    let peers_rows =
+        conn.query_all(&peers_query, &[])
+            .await?
+            .rows
+            .ok_or(QueryError::ProtocolError(
+                "system.peers query response was not Rows",
+            ))?;
+
+    peers_rows
+        .into_typed::<LocalRow>()
+        .map(|row| {
+            let (rpc_address, datacenter, rack, tokens, host_id, release_version, schema_version) =
+                row.map_err(|_| QueryError::ProtocolError("system.peers has invalid column type"))?;
+            Ok(NodeInfoRow {
+                untranslated_address: SocketAddr::new(rpc_address, connect_port),
+                datacenter,
+                rack,
+                tokens,
+                host_id,
+                release_version,
+                schema_version,
+            })
+        })
+        .collect()
+}
+
 async fn query_keyspaces(
     conn: &Connection,
     fetch_schema: bool,